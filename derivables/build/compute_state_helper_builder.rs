@@ -3,6 +3,7 @@ use std::{path::Path, fs::File};
 use std::io::{BufWriter, Write};
 
 use fundamentals::buffer_size_function;
+use fundamentals::runtime_config::RuntimeConfig;
 
 pub fn build_compute_helper_file() {
     let compute_helper_path = Path::new("../hello-wgpu/src/gpu_manager/compute_state_generated_helper.rs");
@@ -82,7 +83,7 @@ fn build_bind_groups_string() -> String {
 }
 
 fn build_layout_entries() -> Vec<String> {
-    let buffer_info = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes());
+    let buffer_info = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes(), &RuntimeConfig::load());
     let mut bind_group_layout_vec = Vec::new();
     for i in 0..buffer_info.num_max_buffers {
         let binding_num = i % 8;
@@ -111,7 +112,7 @@ fn build_layout_entries() -> Vec<String> {
 }
 
 fn build_bind_group_entries() -> Vec<String> {
-    let buffer_info = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes());
+    let buffer_info = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes(), &RuntimeConfig::load());
     let mut bind_group_entry_vec = Vec::new();
     for i in 0..buffer_info.num_max_buffers {
         let binding_num = i % 8;
@@ -3,6 +3,7 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 
 use fundamentals::buffer_size_function;
+use fundamentals::runtime_config::RuntimeConfig;
 use fundamentals::consts::{NUMBER_OF_CHUNKS_AROUND_PLAYER, CHUNK_DIMENSION, NUM_BUCKETS_PER_CHUNK, NUM_BUCKETS_PER_SIDE, WORKGROUP_SIZE};
 
 pub fn build_compute_file() {
@@ -256,7 +257,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {{
 }
 
 fn generate_indirect_buffer_bindings() -> String {
-    let buffer_size_fn_return = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes());
+    let buffer_size_fn_return = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes(), &RuntimeConfig::load());
     let mut binding_string_list = Vec::new();
     let number_of_buckets = buffer_size_fn_return.number_of_buckets_per_buffer;
     for i in 0..buffer_size_fn_return.num_max_buffers {
@@ -272,7 +273,7 @@ var<storage, read_write> indirect_buffer_{i}: array<DrawIndexedIndirect, {number
 }
 
 fn generate_set_instance_count_fn() -> String {
-    let buffer_size_fn_return = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes());
+    let buffer_size_fn_return = buffer_size_function::return_bucket_buffer_size_and_amount_information(super::vertex_builder::return_size_of_vertex_in_bytes(), &RuntimeConfig::load());
     let mut switch_cases = Vec::new();
     for i in 0..buffer_size_fn_return.num_max_buffers {
         let case = format!(
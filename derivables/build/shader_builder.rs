@@ -3,7 +3,7 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 
 use super::vertex_builder::{DATA_TOTAL_BITS, VAR_SIZE_LIST};
-use fundamentals::consts::{NUMBER_OF_CHUNKS_AROUND_PLAYER, CHUNK_DIMENSION};
+use fundamentals::consts::{NUMBER_OF_CHUNKS_AROUND_PLAYER, CHUNK_DIMENSION, NUM_TEXTURES, ATLAS_UV_RECTS, ATLAS_MATERIAL_FLAGS, MATERIAL_EMISSIVE, TEXTURE_FRAME_RANGES, TEXTURE_FRAME_DURATIONS_MS};
 
 pub fn build_shader_file() {
     let shader_path = Path::new("../hello-wgpu/src/shader.wgsl");
@@ -20,6 +20,9 @@ fn build_shader_string() -> String {
     [
         "struct CameraUniform {",
 "    view_proj: mat4x4<f32>,",
+"    view_proj_inverse: mat4x4<f32>,",
+"    position: vec4<f32>,",
+"    time_ms: u32,",
 "};",
 "@group(0) @binding(0)",
 "var<uniform> camera: CameraUniform;",
@@ -41,6 +44,8 @@ format!("var<storage, read_write> visibility_array: array<u32, {}>;", NUMBER_OF_
 "    @location(1) tex_coords: vec2<f32>,",
 "};",
 "",
+build_texture_animation().as_str(),
+"",
 "@vertex",
 "fn vs_main(",
 "    model: VertexInput,",
@@ -51,18 +56,82 @@ build_vs_main_statements().as_str(),
 "}",
 "",
 "@group(1) @binding(0)",
-"var diffuse_texture_array: binding_array<texture_2d<f32>>;",
+"var diffuse_atlas: texture_2d<f32>;",
 "@group(1) @binding(1)",
 "var sampler_diffuse: sampler;",
+"@group(1) @binding(2)",
+"var emissive_atlas: texture_2d<f32>;",
+"",
+build_atlas_uv_rects().as_str(),
+"",
+build_material_flags().as_str(),
 "",
 "@fragment",
 "fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {",
-"   var tex_color = textureSample(diffuse_texture_array[in.tex_index], sampler_diffuse, in.tex_coords);
+"    let uv_rect = atlas_uv_rects[in.tex_index];
+    let uv_size = uv_rect.zw - uv_rect.xy;
+    // Greedy meshed faces span several blocks, so the texture repeats inside its rectangle. The gradients
+    // come from the unwrapped coordinates, or the mip level would jump at every block edge.
+    let uv = uv_rect.xy + fract(in.tex_coords) * uv_size;
+    let uv_dx = dpdx(in.tex_coords) * uv_size;
+    let uv_dy = dpdy(in.tex_coords) * uv_size;
+    var tex_color = textureSampleGrad(diffuse_atlas, sampler_diffuse, uv, uv_dx, uv_dy);
+    if ((atlas_material_flags[in.tex_index] & MATERIAL_EMISSIVE) != 0u) {
+        tex_color += vec4<f32>(textureSampleGrad(emissive_atlas, sampler_diffuse, uv, uv_dx, uv_dy).rgb, 0.0);
+    }
     return tex_color;",
 "}",
     ].join("\n")
 }
 
+// Mirrors consts::TEXTURE_FRAME_RANGES, with the durations flattened to one per texture index, so the
+// vertex shader can pick the frame of an animated face. The time wraps every 49 days, restarting the loops.
+fn build_texture_animation() -> String {
+    let frame_ranges = TEXTURE_FRAME_RANGES.iter().map(|(first_frame, frame_count)| format!("vec2<u32>({}u, {}u)", first_frame, frame_count)).collect::<Vec<String>>();
+    let frame_durations = TEXTURE_FRAME_RANGES.iter().enumerate()
+        .map(|(texture_index, (first_frame, _))| format!("{}u", TEXTURE_FRAME_DURATIONS_MS[texture_index].get(texture_index - first_frame).copied().unwrap_or(0)))
+        .collect::<Vec<String>>();
+    [
+        format!("var<private> texture_frame_ranges: array<vec2<u32>, {}> = array<vec2<u32>, {}>({});", NUM_TEXTURES, NUM_TEXTURES, frame_ranges.join(", ")),
+        format!("var<private> texture_frame_durations_ms: array<u32, {}> = array<u32, {}>({});", NUM_TEXTURES, NUM_TEXTURES, frame_durations.join(", ")),
+        String::from("fn get_texture_frame_index(texture_index: u32, time_ms: u32) -> u32 {
+    let frame_range = texture_frame_ranges[texture_index];
+    if (frame_range.y == 1u) {
+        return texture_index;
+    }
+    let last_frame = frame_range.x + frame_range.y;
+    var loop_duration_ms = 0u;
+    for (var frame = frame_range.x; frame < last_frame; frame++) {
+        loop_duration_ms += texture_frame_durations_ms[frame];
+    }
+    var time_in_loop = time_ms % loop_duration_ms;
+    for (var frame = frame_range.x; frame < last_frame; frame++) {
+        if (time_in_loop < texture_frame_durations_ms[frame]) {
+            return frame;
+        }
+        time_in_loop -= texture_frame_durations_ms[frame];
+    }
+    return frame_range.x;
+}"),
+    ].join("\n")
+}
+
+// Mirrors consts::ATLAS_UV_RECTS, as (u_min, v_min, u_max, v_max) per texture index.
+fn build_atlas_uv_rects() -> String {
+    let uv_rects = ATLAS_UV_RECTS.iter().map(|uv_rect| format!("vec4<f32>({:?}, {:?}, {:?}, {:?})", uv_rect[0], uv_rect[1], uv_rect[2], uv_rect[3])).collect::<Vec<String>>();
+    format!("var<private> atlas_uv_rects: array<vec4<f32>, {}> = array<vec4<f32>, {}>({});", NUM_TEXTURES, NUM_TEXTURES, uv_rects.join(", "))
+}
+
+// Mirrors consts::ATLAS_MATERIAL_FLAGS, so the fragment shader only samples the emissive layer for textures with a map.
+// The normal and roughness/metalness layers aren't bound until there is lighting to use them.
+fn build_material_flags() -> String {
+    let flags = ATLAS_MATERIAL_FLAGS.iter().map(|flags| format!("{}u", flags)).collect::<Vec<String>>();
+    [
+        format!("const MATERIAL_EMISSIVE: u32 = {}u;", MATERIAL_EMISSIVE),
+        format!("var<private> atlas_material_flags: array<u32, {}> = array<u32, {}>({});", NUM_TEXTURES, NUM_TEXTURES, flags.join(", ")),
+    ].join("\n")
+}
+
 fn build_vertex_data() -> String {
     let mut data_vec = Vec::new();
     for i in 0..(DATA_TOTAL_BITS as f32 / 32.0).ceil() as u32 {
@@ -77,7 +146,7 @@ fn build_vs_main_statements() -> String {
         chunk_index_statement,
         format!("    visibility_array[chunk_index]=0u;"),
         format!("    out.clip_position = camera.view_proj * vec4<f32>(f32({}) + f32(chunkPositions.chunk_positions[3u*chunk_index]*{CHUNK_DIMENSION}), f32({}) + f32(chunkPositions.chunk_positions[3u*chunk_index+1u]*{CHUNK_DIMENSION}), f32({}) + f32(chunkPositions.chunk_positions[3u*chunk_index+2u]*{CHUNK_DIMENSION}), 1.0);", data_unpack_vec[0], data_unpack_vec[1], data_unpack_vec[2]),
-        format!("    out.tex_index = {};", data_unpack_vec[3]),
+        format!("    out.tex_index = get_texture_frame_index({}, camera.time_ms);", data_unpack_vec[3]),
         format!("    out.tex_coords = vec2<f32>(f32({}), f32({}));", data_unpack_vec[4], data_unpack_vec[5])
     ].join("\n")
 }
@@ -1,29 +1,66 @@
-use fundamentals::enums::block_type::{BlockType, BlockTypeSize};
-use crate::dictionaries::block_type_to_texture_coordinates::BLOCK_TYPE_TO_TEXTURE_INDICES;
+use formats::formats::block_format::{BlockProperties, Opacity};
+use fundamentals::block_registry::{self, AIR};
+use fundamentals::consts::{BlockStateSize, BlockTypeSize};
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Debug)]
 pub struct Block {
-    pub block_type: BlockTypeSize
+    pub block_state: BlockStateSize
 }
 
 impl Block {
-    pub fn new(block_type: BlockType) -> Self {
-        Block { block_type: block_type as BlockTypeSize }
+    pub fn new(block_type: BlockTypeSize) -> Self {
+        Block { block_state: block_registry::get().get_default_state(block_type) }
+    }
+
+    pub fn from_state(block_state: BlockStateSize) -> Self {
+        Block { block_state }
+    }
+
+    pub fn get_block_type(&self) -> BlockTypeSize {
+        block_registry::get().get_block_type_from_state(self.block_state)
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        block_registry::get().get_state_name(self.block_state)
     }
 
     pub fn is_air(&self) -> bool {
-        self.block_type == BlockType::AIR as BlockTypeSize
+        self.get_block_type() == AIR
+    }
+
+    pub fn get_properties(&self) -> &'static BlockProperties {
+        block_registry::get().get_properties(self.get_block_type())
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.get_properties().opacity == Opacity::Opaque
+    }
+
+    pub fn is_collidable(&self) -> bool {
+        self.get_properties().collidable
+    }
+
+    pub fn occludes_neighbours(&self) -> bool {
+        self.get_properties().occludes_neighbours
+    }
+
+    pub fn get_light_emission(&self) -> u8 {
+        self.get_properties().light_emission
+    }
+
+    pub fn get_hardness(&self) -> f32 {
+        self.get_properties().hardness
     }
 
     pub fn get_texture_indices(&self) -> [usize; 6] {
-        Self::get_texture_indices_from_int(self.block_type)
+        Self::get_texture_indices_from_state(self.block_state)
     }
-    pub fn get_texture_indices_from_type(block_type: &BlockType) -> [usize; 6] {
-        *BLOCK_TYPE_TO_TEXTURE_INDICES.get(block_type).unwrap()
+    pub fn get_texture_indices_from_state(block_state: BlockStateSize) -> [usize; 6] {
+        block_registry::get().get_texture_indices(block_state)
     }
-    pub fn get_texture_indices_from_int(btype_int: BlockTypeSize) -> [usize; 6] {
-        let block_type = BlockType::get_block_type_from_int(btype_int);
-        *BLOCK_TYPE_TO_TEXTURE_INDICES.get(&block_type).unwrap()
+    pub fn get_texture_indices_from_type(block_type: BlockTypeSize) -> [usize; 6] {
+        let block_registry = block_registry::get();
+        block_registry.get_texture_indices(block_registry.get_default_state(block_type))
     }
 }
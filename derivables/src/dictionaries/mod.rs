@@ -1 +0,0 @@
-pub mod block_type_to_texture_coordinates;
\ No newline at end of file
@@ -1,4 +1,3 @@
-pub mod dictionaries;
 pub mod vertex;
 pub mod block;
 pub mod subvoxel_vertex;
@@ -1,4 +1,4 @@
-use fundamentals::{enums::block_side::BlockSide, consts::LEFT_KEY};
+use fundamentals::enums::block_side::BlockSide;
 use cgmath;
 
 #[repr(C)]
@@ -0,0 +1,297 @@
+use std::fmt;
+use crate::formats::checksum::crc32;
+
+pub const ATLAS_MAGIC: [u8; 4] = *b"WATL";
+pub const ATLAS_CONTAINER_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasPixelFormat {
+    Rgba8UnormSrgb = 1,
+}
+
+impl AtlasPixelFormat {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(AtlasPixelFormat::Rgba8UnormSrgb),
+            _ => None,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            AtlasPixelFormat::Rgba8UnormSrgb => 4,
+        }
+    }
+}
+
+/// The contents of `data.atl`: the packed atlas layers, each with its full mip chain, and the name of
+/// every texture index packed into them.
+///
+/// Layout, little endian: the magic `WATL`, then u32 version, atlas width, atlas height, mip level count,
+/// texture count and layer count. The name table follows with, per texture, a u32 name length, the UTF-8
+/// name and a u32 animation frame. Then the layer table with, per layer, a u32 name length, the UTF-8
+/// name, a u32 pixel format and the CRC-32 of the layer's data. A CRC-32 of everything up to this point
+/// ends the header. The layers' data comes last, each mip chain from largest to smallest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasContainer {
+    pub width: u32,
+    pub height: u32,
+    pub mip_level_count: u32,
+    pub textures: Vec<AtlasContainerTexture>,
+    pub layers: Vec<AtlasContainerLayer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasContainerTexture {
+    pub name: String,
+    pub frame: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasContainerLayer {
+    pub name: String,
+    pub pixel_format: AtlasPixelFormat,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtlasContainerError {
+    BadMagic { found: Vec<u8> },
+    UnsupportedVersion { version: u32 },
+    UnknownPixelFormat { layer_index: usize, pixel_format: u32 },
+    InvalidMipLevelCount { mip_level_count: u32, width: u32, height: u32 },
+    Truncated { offset: usize, needed: usize, length: usize },
+    InvalidTextureName { texture_index: usize },
+    InvalidLayerName { layer_index: usize },
+    HeaderChecksumMismatch { expected: u32, found: u32 },
+    LayerChecksumMismatch { layer_index: usize, name: String, expected: u32, found: u32 },
+    TrailingBytes { count: usize },
+    LayoutMismatch { field: &'static str, expected: u32, found: u32 },
+    MissingLayer { name: String },
+}
+
+impl fmt::Display for AtlasContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasContainerError::BadMagic { found } =>
+                write!(f, "not an atlas file, expected magic {:?} but found {:?}", ATLAS_MAGIC, found),
+            AtlasContainerError::UnsupportedVersion { version } =>
+                write!(f, "atlas version {} is not supported, this build reads version {}", version, ATLAS_CONTAINER_VERSION),
+            AtlasContainerError::UnknownPixelFormat { layer_index, pixel_format } =>
+                write!(f, "layer {} has unknown pixel format {}", layer_index, pixel_format),
+            AtlasContainerError::InvalidMipLevelCount { mip_level_count, width, height } =>
+                write!(f, "{} mip levels is invalid for a {}x{} atlas", mip_level_count, width, height),
+            AtlasContainerError::Truncated { offset, needed, length } =>
+                write!(f, "atlas is truncated, {} bytes needed at offset {} but the file has {}", needed, offset, length),
+            AtlasContainerError::InvalidTextureName { texture_index } =>
+                write!(f, "texture {} has a name that is not UTF-8", texture_index),
+            AtlasContainerError::InvalidLayerName { layer_index } =>
+                write!(f, "layer {} has a name that is not UTF-8", layer_index),
+            AtlasContainerError::HeaderChecksumMismatch { expected, found } =>
+                write!(f, "atlas header is corrupt, checksum {:08x} does not match {:08x}", found, expected),
+            AtlasContainerError::LayerChecksumMismatch { layer_index, name, expected, found } =>
+                write!(f, "layer {} ({}) is corrupt, checksum {:08x} does not match {:08x}", layer_index, name, found, expected),
+            AtlasContainerError::TrailingBytes { count } =>
+                write!(f, "atlas has {} unexpected bytes after the last layer", count),
+            AtlasContainerError::LayoutMismatch { field, expected, found } =>
+                write!(f, "atlas {} is {} but this build expects {}, rebuild to regenerate data.atl", field, found, expected),
+            AtlasContainerError::MissingLayer { name } =>
+                write!(f, "atlas has no {} layer, rebuild to regenerate data.atl", name),
+        }
+    }
+}
+
+impl AtlasContainer {
+    /// Bytes of one layer in `pixel_format` with all of its mip levels.
+    pub fn get_layer_length(&self, pixel_format: AtlasPixelFormat) -> usize {
+        get_layer_length(pixel_format, self.width, self.height, self.mip_level_count)
+    }
+
+    pub fn get_layer(&self, name: &str) -> Result<&AtlasContainerLayer, AtlasContainerError> {
+        self.layers.iter().find(|layer| layer.name == name).ok_or_else(|| AtlasContainerError::MissingLayer { name: name.to_string() })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&ATLAS_MAGIC);
+        for value in [ATLAS_CONTAINER_VERSION, self.width, self.height, self.mip_level_count, self.textures.len() as u32, self.layers.len() as u32] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for texture in &self.textures {
+            bytes.extend_from_slice(&(texture.name.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(texture.name.as_bytes());
+            bytes.extend_from_slice(&texture.frame.to_le_bytes());
+        }
+        for layer in &self.layers {
+            bytes.extend_from_slice(&(layer.name.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(layer.name.as_bytes());
+            bytes.extend_from_slice(&(layer.pixel_format as u32).to_le_bytes());
+            bytes.extend_from_slice(&crc32(&layer.data).to_le_bytes());
+        }
+        bytes.extend_from_slice(&crc32(&bytes).to_le_bytes());
+        for layer in &self.layers {
+            bytes.extend_from_slice(&layer.data);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AtlasContainerError> {
+        let mut reader = ByteReader { bytes, offset: 0 };
+
+        let magic = reader.read_bytes(4)?;
+        if magic != ATLAS_MAGIC {
+            return Err(AtlasContainerError::BadMagic { found: magic.to_vec() });
+        }
+        let version = reader.read_u32()?;
+        if version != ATLAS_CONTAINER_VERSION {
+            return Err(AtlasContainerError::UnsupportedVersion { version });
+        }
+        let width = reader.read_u32()?;
+        let height = reader.read_u32()?;
+        let mip_level_count = reader.read_u32()?;
+        if mip_level_count == 0 || mip_level_count > u32::BITS - width.max(height).leading_zeros() {
+            return Err(AtlasContainerError::InvalidMipLevelCount { mip_level_count, width, height });
+        }
+        let num_textures = reader.read_u32()? as usize;
+        let num_layers = reader.read_u32()? as usize;
+
+        let mut textures = Vec::new();
+        for texture_index in 0..num_textures {
+            let name_length = reader.read_u32()? as usize;
+            let name = std::str::from_utf8(reader.read_bytes(name_length)?).map_err(|_| AtlasContainerError::InvalidTextureName { texture_index })?.to_string();
+            let frame = reader.read_u32()?;
+            textures.push(AtlasContainerTexture { name, frame });
+        }
+        let mut layer_table = Vec::new();
+        for layer_index in 0..num_layers {
+            let name_length = reader.read_u32()? as usize;
+            let name = std::str::from_utf8(reader.read_bytes(name_length)?).map_err(|_| AtlasContainerError::InvalidLayerName { layer_index })?.to_string();
+            let pixel_format = reader.read_u32()?;
+            let pixel_format = AtlasPixelFormat::from_u32(pixel_format).ok_or(AtlasContainerError::UnknownPixelFormat { layer_index, pixel_format })?;
+            let checksum = reader.read_u32()?;
+            layer_table.push((name, pixel_format, checksum));
+        }
+        let header_checksum = crc32(&bytes[..reader.offset]);
+        let expected_header_checksum = reader.read_u32()?;
+        if header_checksum != expected_header_checksum {
+            return Err(AtlasContainerError::HeaderChecksumMismatch { expected: expected_header_checksum, found: header_checksum });
+        }
+
+        let mut layers = Vec::with_capacity(num_layers);
+        for (layer_index, (name, pixel_format, expected_checksum)) in layer_table.into_iter().enumerate() {
+            let data = reader.read_bytes(get_layer_length(pixel_format, width, height, mip_level_count))?;
+            let checksum = crc32(data);
+            if checksum != expected_checksum {
+                return Err(AtlasContainerError::LayerChecksumMismatch { layer_index, name, expected: expected_checksum, found: checksum });
+            }
+            layers.push(AtlasContainerLayer { name, pixel_format, data: data.to_vec() });
+        }
+        if reader.offset != bytes.len() {
+            return Err(AtlasContainerError::TrailingBytes { count: bytes.len() - reader.offset });
+        }
+
+        Ok(AtlasContainer { width, height, mip_level_count, textures, layers })
+    }
+
+    /// Checks the atlas against the layout the renderer was compiled for.
+    pub fn check_layout(&self, width: u32, height: u32, mip_level_count: u32, num_textures: usize) -> Result<(), AtlasContainerError> {
+        for (field, expected, found) in [
+            ("width", width, self.width),
+            ("height", height, self.height),
+            ("mip level count", mip_level_count, self.mip_level_count),
+            ("texture count", num_textures as u32, self.textures.len() as u32),
+        ] {
+            if expected != found {
+                return Err(AtlasContainerError::LayoutMismatch { field, expected, found });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The size of every mip level of a `width` by `height` atlas, largest first.
+pub fn get_mip_level_sizes(width: u32, height: u32, mip_level_count: u32) -> impl Iterator<Item = (u32, u32)> {
+    (0..mip_level_count).map(move |level| ((width >> level).max(1), (height >> level).max(1)))
+}
+
+pub fn get_layer_length(pixel_format: AtlasPixelFormat, width: u32, height: u32, mip_level_count: u32) -> usize {
+    get_mip_level_sizes(width, height, mip_level_count)
+        .map(|(level_width, level_height)| level_width as usize * level_height as usize * pixel_format.bytes_per_pixel())
+        .sum()
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], AtlasContainerError> {
+        if self.bytes.len() - self.offset < count {
+            return Err(AtlasContainerError::Truncated { offset: self.offset, needed: count, length: self.bytes.len() });
+        }
+        let bytes = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, AtlasContainerError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_container() -> AtlasContainer {
+        let length = get_layer_length(AtlasPixelFormat::Rgba8UnormSrgb, 8, 4, 3);
+        AtlasContainer {
+            width: 8,
+            height: 4,
+            mip_level_count: 3,
+            textures: vec![
+                AtlasContainerTexture { name: String::from("dirt.png"), frame: 0 },
+                AtlasContainerTexture { name: String::from("strip:water.png"), frame: 1 },
+            ],
+            layers: vec![AtlasContainerLayer { name: String::from("albedo"), pixel_format: AtlasPixelFormat::Rgba8UnormSrgb, data: (0..length).map(|i| i as u8).collect() }],
+        }
+    }
+
+    #[test]
+    fn layers_hold_every_mip_level() {
+        assert_eq!(get_mip_level_sizes(8, 4, 4).collect::<Vec<(u32, u32)>>(), vec![(8, 4), (4, 2), (2, 1), (1, 1)]);
+        assert_eq!(get_layer_length(AtlasPixelFormat::Rgba8UnormSrgb, 8, 4, 4), (32 + 8 + 2 + 1) * 4);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let container = get_container();
+        assert_eq!(AtlasContainer::from_bytes(&container.to_bytes()), Ok(container));
+    }
+
+    #[test]
+    fn rejects_corrupt_layers() {
+        let mut bytes = get_container().to_bytes();
+        *bytes.last_mut().unwrap() ^= 1;
+        assert!(matches!(AtlasContainer::from_bytes(&bytes), Err(AtlasContainerError::LayerChecksumMismatch { layer_index: 0, .. })));
+    }
+
+    #[test]
+    fn rejects_truncated_and_padded_files() {
+        let bytes = get_container().to_bytes();
+        assert!(matches!(AtlasContainer::from_bytes(&bytes[..bytes.len() - 1]), Err(AtlasContainerError::Truncated { .. })));
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(AtlasContainer::from_bytes(&padded), Err(AtlasContainerError::TrailingBytes { count: 1 }));
+    }
+
+    #[test]
+    fn rejects_a_layout_the_build_was_not_compiled_for() {
+        let container = get_container();
+        assert_eq!(container.check_layout(8, 4, 3, 2), Ok(()));
+        assert_eq!(container.check_layout(8, 8, 3, 2), Err(AtlasContainerError::LayoutMismatch { field: "height", expected: 8, found: 4 }));
+        assert_eq!(container.check_layout(8, 4, 3, 3), Err(AtlasContainerError::LayoutMismatch { field: "texture count", expected: 3, found: 2 }));
+        assert_eq!(container.get_layer("normal"), Err(AtlasContainerError::MissingLayer { name: String::from("normal") }));
+    }
+}
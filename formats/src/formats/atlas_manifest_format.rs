@@ -0,0 +1,46 @@
+use serde::{Serialize, Deserialize};
+
+/// Describes the packed atlas PNG: where every texture index sits, in pixels and as UVs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AtlasManifestFormat {
+    pub format_version: u32,
+    pub width: u32,
+    pub height: u32,
+    pub padding: u32,
+    pub textures: Vec<AtlasTextureFormat>,
+    /// Every atlas key, including the ones that share another key's textures because their pixels are identical.
+    #[serde(default)]
+    pub keys: Vec<AtlasKeyFormat>,
+    /// The material layer images, laid out like the albedo atlas, see `texture_format::MaterialLayer`.
+    #[serde(default)]
+    pub layers: Vec<String>,
+}
+
+/// `content_hash` is the FNV-1a hash of the decoded frames, as hex, so a build can tell which textures changed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AtlasKeyFormat {
+    pub name: String,
+    pub texture_index: usize,
+    pub frame_count: usize,
+    pub content_hash: String,
+}
+
+/// One frame of a face texture. `name` is the face's atlas key, so the frames of an animation share
+/// it and differ in `frame`. The rectangle excludes the padding.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AtlasTextureFormat {
+    pub texture_index: usize,
+    pub name: String,
+    pub frame: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub u_min: f32,
+    pub v_min: f32,
+    pub u_max: f32,
+    pub v_max: f32,
+    /// Which material layers hold a map for this texture rather than the default, see `MaterialLayer::get_flag`.
+    #[serde(default)]
+    pub material_flags: u32,
+}
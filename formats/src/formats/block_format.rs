@@ -1,8 +1,291 @@
+use std::collections::BTreeMap;
 use serde::{Serialize, Deserialize};
-use crate::formats::texture_format::TextureFormat;
+use crate::formats::texture_format::{FaceMaterialFormat, FaceTextureFormat, MipSettingsFormat, TextureFormat};
+
+pub const MAX_LIGHT_EMISSION: u8 = 15;
 
 #[derive(Serialize, Deserialize)]
 pub struct BlockFormat {
+    pub format_version: u32,
     pub block_type: String,
     pub texture: TextureFormat,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub properties: BlockProperties,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<BlockStatePropertyFormat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<BlockVariantFormat>,
+}
+
+/// A state property such as `axis` with its possible values. Every combination of a block's
+/// property values is its own state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlockStatePropertyFormat {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+/// Applies to every state whose property values match `when`; the first matching variant wins.
+/// `rotate_x` turns the faces about the x axis in 90 degree steps, moving the top texture to the
+/// right face, then `rotate_y` turns them about the y axis, moving the front texture to the left face.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlockVariantFormat {
+    pub when: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<TextureFormat>,
+    #[serde(default)]
+    pub rotate_x: u32,
+    #[serde(default)]
+    pub rotate_y: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Opacity {
+    Opaque,
+    Transparent,
+    Cutout,
+}
+
+/// Fields left out of a block's `properties` take the values of a plain opaque, solid block.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BlockProperties {
+    pub opacity: Opacity,
+    pub light_emission: u8,
+    pub collidable: bool,
+    pub hardness: f32,
+    pub occludes_neighbours: bool,
+}
+
+impl Default for BlockProperties {
+    fn default() -> Self {
+        BlockProperties { opacity: Opacity::Opaque, light_emission: 0, collidable: true, hardness: 1.0, occludes_neighbours: true }
+    }
+}
+
+impl BlockProperties {
+    pub fn air() -> Self {
+        BlockProperties { opacity: Opacity::Transparent, light_emission: 0, collidable: false, hardness: 0.0, occludes_neighbours: false }
+    }
+}
+
+// Faces are ordered front, back, left, right, top, bottom, as in TextureFormat::to_vec.
+const ROTATE_X_CYCLE: [usize; 4] = [4, 3, 5, 2];
+const ROTATE_Y_CYCLE: [usize; 4] = [0, 2, 1, 3];
+
+impl BlockFormat {
+    pub fn get_num_states(&self) -> usize {
+        self.states.iter().map(|property| property.values.len()).product()
+    }
+
+    /// The property values of the state at `state_index`; the last property varies fastest.
+    pub fn get_state_values(&self, state_index: usize) -> Vec<(&str, &str)> {
+        let mut remainder = state_index;
+        let mut state_values = Vec::new();
+        for property in self.states.iter().rev() {
+            state_values.push((property.name.as_str(), property.values[remainder % property.values.len()].as_str()));
+            remainder /= property.values.len();
+        }
+        state_values.reverse();
+        state_values
+    }
+
+    /// `LOG[axis=x]` style name of a state, or just the block type for blocks without states.
+    pub fn get_state_name(&self, state_index: usize) -> String {
+        if self.states.is_empty() {
+            return self.block_type.clone();
+        }
+        let state_values = self.get_state_values(state_index).iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<String>>();
+        format!("{}[{}]", self.block_type, state_values.join(","))
+    }
+
+    pub fn get_state_names(&self) -> Vec<String> {
+        (0..self.get_num_states()).map(|state_index| self.get_state_name(state_index)).collect()
+    }
+
+    fn get_state_variant(&self, state_index: usize) -> Option<&BlockVariantFormat> {
+        let state_values = self.get_state_values(state_index);
+        self.variants.iter().find(|variant| {
+            variant.when.iter().all(|(name, value)| state_values.iter().any(|(n, v)| n == name && v == value))
+        })
+    }
+
+    pub fn get_state_mip_settings(&self, state_index: usize) -> &MipSettingsFormat {
+        match self.get_state_variant(state_index).and_then(|variant| variant.texture.as_ref()) {
+            Some(texture) => &texture.mip,
+            None => &self.texture.mip,
+        }
+    }
+
+    pub fn get_state_faces(&self, state_index: usize) -> Vec<FaceTextureFormat> {
+        self.get_state_rotated_faces(state_index, TextureFormat::to_vec)
+    }
+
+    /// The material maps of each face, rotated along with the faces.
+    pub fn get_state_face_materials(&self, state_index: usize) -> Vec<FaceMaterialFormat> {
+        self.get_state_rotated_faces(state_index, |texture| texture.material.to_vec())
+    }
+
+    /// The atlas key of each face, which covers its albedo and its material maps.
+    pub fn get_state_atlas_keys(&self, state_index: usize) -> Vec<String> {
+        self.get_state_faces(state_index).iter().zip(self.get_state_face_materials(state_index))
+            .map(|(face, face_material)| face_material.get_atlas_key(&face.get_atlas_key()))
+            .collect()
+    }
+
+    fn get_state_rotated_faces<T: Clone>(&self, state_index: usize, get_faces: impl Fn(&TextureFormat) -> Vec<T>) -> Vec<T> {
+        let variant = match self.get_state_variant(state_index) {
+            Some(variant) => variant,
+            None => return get_faces(&self.texture),
+        };
+
+        let mut faces = get_faces(variant.texture.as_ref().unwrap_or(&self.texture));
+        for _ in 0..(variant.rotate_x / 90) % 4 {
+            faces = Self::rotate_faces(faces, &ROTATE_X_CYCLE);
+        }
+        for _ in 0..(variant.rotate_y / 90) % 4 {
+            faces = Self::rotate_faces(faces, &ROTATE_Y_CYCLE);
+        }
+        faces
+    }
+
+    fn rotate_faces<T: Clone>(faces: Vec<T>, cycle: &[usize; 4]) -> Vec<T> {
+        let mut rotated_faces = faces.clone();
+        for i in 0..4 {
+            rotated_faces[cycle[(i + 1) % 4]] = faces[cycle[i]].clone();
+        }
+        rotated_faces
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for property in &self.states {
+            if property.values.is_empty() {
+                return Err(format!("{}: state property {} has no values", self.block_type, property.name));
+            }
+        }
+        for variant in &self.variants {
+            for (name, value) in &variant.when {
+                match self.states.iter().find(|property| &property.name == name) {
+                    None => return Err(format!("{}: variant refers to unknown state property {}", self.block_type, name)),
+                    Some(property) if !property.values.contains(value) => return Err(format!("{}: {} is not a value of state property {}", self.block_type, value, name)),
+                    _ => {}
+                }
+            }
+            if variant.rotate_x % 90 != 0 || variant.rotate_y % 90 != 0 {
+                return Err(format!("{}: variant rotations must be multiples of 90", self.block_type));
+            }
+        }
+        if self.properties.light_emission > MAX_LIGHT_EMISSION {
+            return Err(format!("{}: light_emission {} is above the maximum of {}", self.block_type, self.properties.light_emission, MAX_LIGHT_EMISSION));
+        }
+        if self.properties.hardness < 0.0 {
+            return Err(format!("{}: hardness {} is negative", self.block_type, self.properties.hardness));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXTURE_JSON: &str = r#"{ "front": "front.png", "back": "back.png", "left": "left.png", "right": "right.png", "top": "top.png", "bottom": "bottom.png" }"#;
+
+    // `fields` are added to a block named LOG with the texture above.
+    fn get_block_format(fields: &str) -> BlockFormat {
+        let separator = if fields.is_empty() { "" } else { ", " };
+        serde_json::from_str(&format!(r#"{{ "format_version": 5, "block_type": "LOG", "texture": {}{}{} }}"#, TEXTURE_JSON, separator, fields)).unwrap()
+    }
+
+    #[test]
+    fn left_out_properties_are_those_of_an_opaque_solid_block() {
+        let properties = get_block_format("").properties;
+        assert_eq!(properties.opacity, Opacity::Opaque);
+        assert_eq!((properties.light_emission, properties.collidable, properties.hardness, properties.occludes_neighbours), (0, true, 1.0, true));
+
+        let properties = get_block_format(r#""properties": { "opacity": "transparent", "occludes_neighbours": false }"#).properties;
+        assert_eq!(properties.opacity, Opacity::Transparent);
+        assert_eq!((properties.light_emission, properties.collidable, properties.hardness, properties.occludes_neighbours), (0, true, 1.0, false));
+    }
+
+    #[test]
+    fn validate_accepts_variants_of_known_states() {
+        let block_format = get_block_format(r#""states": [{ "name": "axis", "values": ["x", "y"] }], "variants": [{ "when": { "axis": "x" }, "rotate_x": 270 }]"#);
+
+        assert_eq!(block_format.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_invalid_states_variants_and_properties() {
+        let cases = [
+            (r#""states": [{ "name": "axis", "values": [] }]"#, "LOG: state property axis has no values"),
+            (r#""states": [{ "name": "axis", "values": ["x"] }], "variants": [{ "when": { "facing": "x" } }]"#, "LOG: variant refers to unknown state property facing"),
+            (r#""states": [{ "name": "axis", "values": ["x"] }], "variants": [{ "when": { "axis": "w" } }]"#, "LOG: w is not a value of state property axis"),
+            (r#""states": [{ "name": "axis", "values": ["x"] }], "variants": [{ "when": { "axis": "x" }, "rotate_y": 45 }]"#, "LOG: variant rotations must be multiples of 90"),
+            (r#""properties": { "light_emission": 16 }"#, "LOG: light_emission 16 is above the maximum of 15"),
+            (r#""properties": { "hardness": -1.0 }"#, "LOG: hardness -1 is negative"),
+        ];
+        for (fields, error) in cases {
+            assert_eq!(get_block_format(fields).validate(), Err(String::from(error)), "{}", fields);
+        }
+    }
+
+    // The face order of TextureFormat::to_vec, as the file names each face starts with.
+    fn get_face_names(block_format: &BlockFormat, state_index: usize) -> Vec<String> {
+        block_format.get_state_faces(state_index).iter().map(|face| face.get_atlas_key().trim_end_matches(".png").to_string()).collect()
+    }
+
+    #[test]
+    fn state_indices_count_through_the_last_property_fastest() {
+        let block_format = get_block_format(r#""states": [{ "name": "axis", "values": ["x", "y", "z"] }, { "name": "lit", "values": ["false", "true"] }]"#);
+
+        assert_eq!(block_format.get_num_states(), 6);
+        assert_eq!(block_format.get_state_values(0), [("axis", "x"), ("lit", "false")]);
+        assert_eq!(block_format.get_state_values(1), [("axis", "x"), ("lit", "true")]);
+        assert_eq!(block_format.get_state_values(4), [("axis", "z"), ("lit", "false")]);
+        assert_eq!(block_format.get_state_names(), [
+            "LOG[axis=x,lit=false]", "LOG[axis=x,lit=true]", "LOG[axis=y,lit=false]",
+            "LOG[axis=y,lit=true]", "LOG[axis=z,lit=false]", "LOG[axis=z,lit=true]",
+        ]);
+        assert_eq!(get_block_format("").get_state_names(), ["LOG"]);
+    }
+
+    #[test]
+    fn each_rotate_x_step_moves_the_top_face_to_the_right() {
+        let block_format = get_block_format(r#""states": [{ "name": "r", "values": ["0", "90", "180", "270", "360"] }],
+            "variants": [{ "when": { "r": "90" }, "rotate_x": 90 }, { "when": { "r": "180" }, "rotate_x": 180 },
+                         { "when": { "r": "270" }, "rotate_x": 270 }, { "when": { "r": "360" }, "rotate_x": 360 }]"#);
+
+        // front, back, left, right, top, bottom
+        assert_eq!(get_face_names(&block_format, 0), ["front", "back", "left", "right", "top", "bottom"]);
+        assert_eq!(get_face_names(&block_format, 1), ["front", "back", "bottom", "top", "left", "right"]);
+        assert_eq!(get_face_names(&block_format, 2), ["front", "back", "right", "left", "bottom", "top"]);
+        assert_eq!(get_face_names(&block_format, 3), ["front", "back", "top", "bottom", "right", "left"]);
+        assert_eq!(get_face_names(&block_format, 4), get_face_names(&block_format, 0));
+    }
+
+    #[test]
+    fn each_rotate_y_step_moves_the_front_face_to_the_left() {
+        let block_format = get_block_format(r#""states": [{ "name": "r", "values": ["0", "90", "180", "270"] }],
+            "variants": [{ "when": { "r": "90" }, "rotate_y": 90 }, { "when": { "r": "180" }, "rotate_y": 180 }, { "when": { "r": "270" }, "rotate_y": 270 }]"#);
+
+        assert_eq!(get_face_names(&block_format, 1), ["right", "left", "front", "back", "top", "bottom"]);
+        assert_eq!(get_face_names(&block_format, 2), ["back", "front", "right", "left", "top", "bottom"]);
+        assert_eq!(get_face_names(&block_format, 3), ["left", "right", "back", "front", "top", "bottom"]);
+    }
+
+    #[test]
+    fn rotate_x_applies_before_rotate_y_and_materials_turn_with_their_faces() {
+        let block_format = get_block_format(r#""states": [{ "name": "axis", "values": ["y", "x"] }],
+            "variants": [{ "when": { "axis": "x" }, "rotate_x": 90, "rotate_y": 90,
+                "texture": { "front": "front.png", "back": "back.png", "left": "left.png", "right": "right.png", "top": "top.png", "bottom": "bottom.png",
+                             "material": { "top": { "emissive": "glow.png" } } } }]"#);
+
+        // rotate_x gives front, back, bottom, top, left, right, then rotate_y turns that about y.
+        assert_eq!(get_face_names(&block_format, 1), ["top", "bottom", "front", "back", "left", "right"]);
+        let emissive_faces = block_format.get_state_face_materials(1).iter().map(|face_material| face_material.emissive.is_some()).collect::<Vec<bool>>();
+        assert_eq!(emissive_faces, [true, false, false, false, false, false]);
+        assert_eq!(block_format.get_state_atlas_keys(1)[0], "top.png|emissive=glow.png");
+    }
+}
@@ -0,0 +1,9 @@
+use serde::{Serialize, Deserialize};
+
+/// The block state names in ID order when block data was written. Saved alongside that data so its
+/// IDs can be translated after `data/blocks.json` changes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlockIdTableFormat {
+    pub format_version: u32,
+    pub block_states: Vec<String>,
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+
+/// Rules for loading block data written before blocks were renamed or removed from `data/blocks.json`.
+/// Keys are state names such as `LOG[axis=x]`, or block names covering every state of that block.
+/// Values name the state, or the block whose default state, to load instead; `AIR` deletes the block.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlockReplacementFormat {
+    pub format_version: u32,
+    #[serde(default)]
+    pub replacements: BTreeMap<String, String>,
+}
+
+impl BlockReplacementFormat {
+    /// Follows the rules from `name` until `is_current` accepts a name, which is returned. A rule for
+    /// an exact state wins over a rule for its block, and replacements may be replaced in turn.
+    pub fn resolve(&self, name: &str, is_current: impl Fn(&str) -> bool) -> Result<String, String> {
+        let mut current_name = name.to_string();
+        let mut visited = Vec::new();
+        while !is_current(&current_name) {
+            let block_name = current_name.split('[').next().unwrap_or(&current_name);
+            let replacement = match self.replacements.get(&current_name).or_else(|| self.replacements.get(block_name)) {
+                Some(replacement) => replacement,
+                None if visited.is_empty() => return Err(format!("{} is not a current block and has no replacement", name)),
+                None => return Err(format!("{} is replaced by {}, which is not a current block and has no replacement", name, current_name)),
+            };
+            visited.push(current_name);
+            if visited.contains(replacement) {
+                return Err(format!("replacements form a cycle: {} -> {}", visited.join(" -> "), replacement));
+            }
+            current_name = replacement.clone();
+        }
+        Ok(current_name)
+    }
+
+    /// Checks that every rule ends at a current block or state.
+    pub fn validate(&self, is_current: impl Fn(&str) -> bool) -> Result<(), Vec<String>> {
+        let errors = self.replacements.keys()
+            .filter_map(|name| self.resolve(name, &is_current).err().map(|e| format!("replacements.{}: {}", name, e)))
+            .collect::<Vec<String>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block_replacements(replacements: &[(&str, &str)]) -> BlockReplacementFormat {
+        BlockReplacementFormat {
+            format_version: 1,
+            replacements: replacements.iter().map(|(name, replacement)| (name.to_string(), replacement.to_string())).collect(),
+        }
+    }
+
+    fn is_current(name: &str) -> bool {
+        ["AIR", "STONE", "LOG", "LOG[axis=x]", "LOG[axis=z]"].contains(&name)
+    }
+
+    #[test]
+    fn current_names_resolve_to_themselves() {
+        assert_eq!(get_block_replacements(&[("STONE", "LOG")]).resolve("STONE", is_current), Ok(String::from("STONE")));
+    }
+
+    #[test]
+    fn a_rule_for_the_exact_state_wins_over_the_rule_for_its_block() {
+        let block_replacements = get_block_replacements(&[("OLD_LOG", "LOG"), ("OLD_LOG[axis=z]", "LOG[axis=z]")]);
+
+        assert_eq!(block_replacements.resolve("OLD_LOG[axis=z]", is_current), Ok(String::from("LOG[axis=z]")));
+        assert_eq!(block_replacements.resolve("OLD_LOG[axis=x]", is_current), Ok(String::from("LOG")));
+    }
+
+    #[test]
+    fn replacements_are_followed_in_turn() {
+        let block_replacements = get_block_replacements(&[("ROCK", "COBBLE"), ("COBBLE", "STONE")]);
+
+        assert_eq!(block_replacements.resolve("ROCK", is_current), Ok(String::from("STONE")));
+    }
+
+    #[test]
+    fn a_cycle_is_an_error() {
+        let block_replacements = get_block_replacements(&[("ROCK", "COBBLE"), ("COBBLE", "ROCK")]);
+
+        assert_eq!(block_replacements.resolve("ROCK", is_current), Err(String::from("replacements form a cycle: ROCK -> COBBLE -> ROCK")));
+    }
+
+    #[test]
+    fn a_name_without_a_rule_is_an_error() {
+        let block_replacements = get_block_replacements(&[("ROCK", "COBBLE")]);
+
+        assert_eq!(block_replacements.resolve("GONE", is_current), Err(String::from("GONE is not a current block and has no replacement")));
+        assert_eq!(block_replacements.resolve("ROCK", is_current), Err(String::from("ROCK is replaced by COBBLE, which is not a current block and has no replacement")));
+    }
+
+    #[test]
+    fn validate_reports_every_rule_that_does_not_end_at_a_current_name() {
+        let block_replacements = get_block_replacements(&[("FLOWER", "AIR"), ("ROCK", "COBBLE"), ("SAND", "GRAVEL")]);
+
+        let errors = block_replacements.validate(is_current).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("replacements.ROCK: "), "{}", errors[0]);
+        assert!(errors[1].starts_with("replacements.SAND: "), "{}", errors[1]);
+        assert_eq!(get_block_replacements(&[("FLOWER", "AIR")]).validate(is_current), Ok(()));
+    }
+}
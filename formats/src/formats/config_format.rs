@@ -1,12 +1,16 @@
+use std::fmt;
 use serde::{Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ConfigFormat {
+    pub format_version: u32,
     pub num_additional_threads_specified: usize,
     pub use_all_system_threads: bool,
     pub render_radius: usize,
     pub max_amount_of_blocktypes: u32,
     pub texture_dimension: u32,
+    /// Pixels of gutter around every texture in the packed atlas, filled with the texture's edge pixels.
+    pub atlas_padding: u32,
     pub chunk_dimension: u8,
     pub num_tasks_per_thread: usize,
     pub task_priorities: TaskPriorities,
@@ -14,6 +18,12 @@ pub struct ConfigFormat {
     pub mesh_method: String,
     pub chunk_generation_method: String,
     pub chunk_generation_options: ChunkGenerationOptions,
+    /// Where chunks are saved and loaded from, relative to the repository root. Empty disables saving.
+    pub world_directory: String,
+    /// Chunks farther than this from the player's chunk are unloaded. At least `render_radius`.
+    pub unload_radius: usize,
+    /// Memory the loaded chunks' blocks may take before the farthest ones outside the render radius are unloaded.
+    pub chunk_memory_budget_mb: u32,
     pub min_memory_mb: u32,
     pub max_memory_mb: u32,
     pub max_subvoxel_objects: u32,
@@ -23,19 +33,253 @@ pub struct ConfigFormat {
     pub grid_aligned_subvoxel_placement_dimension: u32
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TaskPriorities {
     pub chunk: usize,
     pub update_chunk_padding_x: usize,
     pub update_chunk_padding_y: usize,
     pub update_chunk_padding_z: usize,
     pub mesh: usize,
-    pub mesh_side: usize
+    pub mesh_side: usize,
+    pub set_block: usize
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ChunkGenerationOptions {
     pub perlin_positive_threshold: f32,
     pub perlin_negative_threshold: f32,
     pub perlin_scale_factor: f32,
-}
\ No newline at end of file
+}
+
+pub const MESH_METHODS: [&str; 2] = ["greedy", "cull"];
+pub const CHUNK_GENERATION_METHODS: [&str; 4] = ["perlin", "checkerboard", "solid", "empty"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigErrorKind {
+    ChunkDimensionNotEven { chunk_dimension: u8 },
+    MinMemoryAboveMaxMemory { min_memory_mb: u32, max_memory_mb: u32 },
+    UnloadRadiusBelowRenderRadius { unload_radius: usize, render_radius: usize },
+    NegativeThresholdAbovePositiveThreshold { perlin_negative_threshold: f32, perlin_positive_threshold: f32 },
+    UnknownMeshMethod { mesh_method: String },
+    UnknownChunkGenerationMethod { chunk_generation_method: String },
+    TooManyBlockTypesForBlockTypeSize { max_amount_of_blocktypes: u32, block_type_size_bits: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub field_path: String,
+    pub kind: ConfigErrorKind,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ConfigErrorKind::ChunkDimensionNotEven { chunk_dimension } =>
+                write!(f, "{}: {} is not a multiple of 2, as index buffer writes must be a multiple of 4", self.field_path, chunk_dimension),
+            ConfigErrorKind::MinMemoryAboveMaxMemory { min_memory_mb, max_memory_mb } =>
+                write!(f, "{}: {} is larger than max_memory_mb {}", self.field_path, min_memory_mb, max_memory_mb),
+            ConfigErrorKind::UnloadRadiusBelowRenderRadius { unload_radius, render_radius } =>
+                write!(f, "{}: {} is smaller than render_radius {}, so rendered chunks would be unloaded", self.field_path, unload_radius, render_radius),
+            ConfigErrorKind::NegativeThresholdAbovePositiveThreshold { perlin_negative_threshold, perlin_positive_threshold } =>
+                write!(f, "{}: {} is larger than perlin_positive_threshold {}", self.field_path, perlin_negative_threshold, perlin_positive_threshold),
+            ConfigErrorKind::UnknownMeshMethod { mesh_method } =>
+                write!(f, "{}: unknown mesh method \"{}\", expected one of {:?}", self.field_path, mesh_method, MESH_METHODS),
+            ConfigErrorKind::UnknownChunkGenerationMethod { chunk_generation_method } =>
+                write!(f, "{}: unknown chunk generation method \"{}\", expected one of {:?}", self.field_path, chunk_generation_method, CHUNK_GENERATION_METHODS),
+            ConfigErrorKind::TooManyBlockTypesForBlockTypeSize { max_amount_of_blocktypes, block_type_size_bits } =>
+                write!(f, "{}: {} block types do not fit in a {} bit BlockTypeSize", self.field_path, max_amount_of_blocktypes, block_type_size_bits),
+        }
+    }
+}
+
+impl ConfigFormat {
+    /// Checks every rule and returns all violations rather than stopping at the first one.
+    /// `block_type_size_bits` is the width of the generated `BlockTypeSize`.
+    pub fn validate(&self, block_type_size_bits: u32) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        if !self.chunk_dimension.is_multiple_of(2) {
+            errors.push(ConfigError {
+                field_path: String::from("chunk_dimension"),
+                kind: ConfigErrorKind::ChunkDimensionNotEven { chunk_dimension: self.chunk_dimension },
+            });
+        }
+
+        if self.min_memory_mb > self.max_memory_mb {
+            errors.push(ConfigError {
+                field_path: String::from("min_memory_mb"),
+                kind: ConfigErrorKind::MinMemoryAboveMaxMemory { min_memory_mb: self.min_memory_mb, max_memory_mb: self.max_memory_mb },
+            });
+        }
+
+        if self.unload_radius < self.render_radius {
+            errors.push(ConfigError {
+                field_path: String::from("unload_radius"),
+                kind: ConfigErrorKind::UnloadRadiusBelowRenderRadius { unload_radius: self.unload_radius, render_radius: self.render_radius },
+            });
+        }
+
+        let options = &self.chunk_generation_options;
+        if options.perlin_negative_threshold > options.perlin_positive_threshold {
+            errors.push(ConfigError {
+                field_path: String::from("chunk_generation_options.perlin_negative_threshold"),
+                kind: ConfigErrorKind::NegativeThresholdAbovePositiveThreshold {
+                    perlin_negative_threshold: options.perlin_negative_threshold,
+                    perlin_positive_threshold: options.perlin_positive_threshold,
+                },
+            });
+        }
+
+        if !MESH_METHODS.contains(&self.mesh_method.as_str()) {
+            errors.push(ConfigError {
+                field_path: String::from("mesh_method"),
+                kind: ConfigErrorKind::UnknownMeshMethod { mesh_method: self.mesh_method.clone() },
+            });
+        }
+
+        if !CHUNK_GENERATION_METHODS.contains(&self.chunk_generation_method.as_str()) {
+            errors.push(ConfigError {
+                field_path: String::from("chunk_generation_method"),
+                kind: ConfigErrorKind::UnknownChunkGenerationMethod { chunk_generation_method: self.chunk_generation_method.clone() },
+            });
+        }
+
+        if block_type_size_bits < 32 && self.max_amount_of_blocktypes as u64 > 1u64 << block_type_size_bits {
+            errors.push(ConfigError {
+                field_path: String::from("max_amount_of_blocktypes"),
+                kind: ConfigErrorKind::TooManyBlockTypesForBlockTypeSize { max_amount_of_blocktypes: self.max_amount_of_blocktypes, block_type_size_bits },
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_valid_config() -> ConfigFormat {
+        ConfigFormat {
+            format_version: 5,
+            num_additional_threads_specified: 1,
+            use_all_system_threads: true,
+            render_radius: 5,
+            max_amount_of_blocktypes: 65536,
+            texture_dimension: 16,
+            atlas_padding: 4,
+            chunk_dimension: 32,
+            num_tasks_per_thread: 15,
+            task_priorities: TaskPriorities { chunk: 1, update_chunk_padding_x: 2, update_chunk_padding_y: 2, update_chunk_padding_z: 2, mesh: 4, mesh_side: 3, set_block: 5 },
+            movement_speed: 2.0,
+            mesh_method: String::from("greedy"),
+            chunk_generation_method: String::from("perlin"),
+            chunk_generation_options: ChunkGenerationOptions { perlin_positive_threshold: 0.2, perlin_negative_threshold: -0.2, perlin_scale_factor: 0.02 },
+            world_directory: String::from("saves/world"),
+            unload_radius: 7,
+            chunk_memory_budget_mb: 256,
+            min_memory_mb: 512,
+            max_memory_mb: 1024,
+            max_subvoxel_objects: 500,
+            max_subvoxels_in_models: 1250,
+            max_subvoxel_colors: 32,
+            max_grid_aligned_subvoxel_objects: 1024,
+            grid_aligned_subvoxel_placement_dimension: 16,
+        }
+    }
+
+    fn get_single_error(config: &ConfigFormat, block_type_size_bits: u32) -> ConfigError {
+        let errors = config.validate(block_type_size_bits).unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        errors.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        assert_eq!(get_valid_config().validate(16), Ok(()));
+    }
+
+    #[test]
+    fn rejects_odd_chunk_dimension() {
+        let config = ConfigFormat { chunk_dimension: 31, ..get_valid_config() };
+        let error = get_single_error(&config, 16);
+        assert_eq!(error.field_path, "chunk_dimension");
+        assert_eq!(error.kind, ConfigErrorKind::ChunkDimensionNotEven { chunk_dimension: 31 });
+    }
+
+    #[test]
+    fn rejects_min_memory_above_max_memory() {
+        let config = ConfigFormat { min_memory_mb: 2048, ..get_valid_config() };
+        let error = get_single_error(&config, 16);
+        assert_eq!(error.field_path, "min_memory_mb");
+        assert_eq!(error.kind, ConfigErrorKind::MinMemoryAboveMaxMemory { min_memory_mb: 2048, max_memory_mb: 1024 });
+        assert_eq!(ConfigFormat { min_memory_mb: 1024, ..get_valid_config() }.validate(16), Ok(()));
+    }
+
+    #[test]
+    fn rejects_unload_radius_below_render_radius() {
+        let config = ConfigFormat { unload_radius: 4, ..get_valid_config() };
+        let error = get_single_error(&config, 16);
+        assert_eq!(error.field_path, "unload_radius");
+        assert_eq!(error.kind, ConfigErrorKind::UnloadRadiusBelowRenderRadius { unload_radius: 4, render_radius: 5 });
+        assert_eq!(ConfigFormat { unload_radius: 5, ..get_valid_config() }.validate(16), Ok(()));
+    }
+
+    #[test]
+    fn rejects_negative_threshold_above_positive_threshold() {
+        let chunk_generation_options = ChunkGenerationOptions { perlin_positive_threshold: -0.3, perlin_negative_threshold: -0.2, perlin_scale_factor: 0.02 };
+        let config = ConfigFormat { chunk_generation_options, ..get_valid_config() };
+        let error = get_single_error(&config, 16);
+        assert_eq!(error.field_path, "chunk_generation_options.perlin_negative_threshold");
+        assert_eq!(error.kind, ConfigErrorKind::NegativeThresholdAbovePositiveThreshold { perlin_negative_threshold: -0.2, perlin_positive_threshold: -0.3 });
+    }
+
+    #[test]
+    fn rejects_unknown_mesh_method() {
+        let config = ConfigFormat { mesh_method: String::from("marching_cubes"), ..get_valid_config() };
+        let error = get_single_error(&config, 16);
+        assert_eq!(error.field_path, "mesh_method");
+        assert_eq!(error.kind, ConfigErrorKind::UnknownMeshMethod { mesh_method: String::from("marching_cubes") });
+        for mesh_method in MESH_METHODS {
+            assert_eq!(ConfigFormat { mesh_method: String::from(mesh_method), ..get_valid_config() }.validate(16), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_chunk_generation_method() {
+        let config = ConfigFormat { chunk_generation_method: String::from("Perlin"), ..get_valid_config() };
+        let error = get_single_error(&config, 16);
+        assert_eq!(error.field_path, "chunk_generation_method");
+        assert_eq!(error.kind, ConfigErrorKind::UnknownChunkGenerationMethod { chunk_generation_method: String::from("Perlin") });
+        for chunk_generation_method in CHUNK_GENERATION_METHODS {
+            assert_eq!(ConfigFormat { chunk_generation_method: String::from(chunk_generation_method), ..get_valid_config() }.validate(16), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_more_block_types_than_block_type_size_holds() {
+        let config = get_valid_config();
+        let error = get_single_error(&config, 8);
+        assert_eq!(error.field_path, "max_amount_of_blocktypes");
+        assert_eq!(error.kind, ConfigErrorKind::TooManyBlockTypesForBlockTypeSize { max_amount_of_blocktypes: 65536, block_type_size_bits: 8 });
+        // Exactly 2^bits IDs fit, and a 32 bit size holds any u32.
+        assert_eq!(ConfigFormat { max_amount_of_blocktypes: 256, ..get_valid_config() }.validate(8), Ok(()));
+        assert_eq!(ConfigFormat { max_amount_of_blocktypes: u32::MAX, ..get_valid_config() }.validate(32), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_violation_at_once() {
+        let config = ConfigFormat {
+            chunk_dimension: 33,
+            min_memory_mb: 2048,
+            mesh_method: String::from("marching_cubes"),
+            ..get_valid_config()
+        };
+        let field_paths: Vec<String> = config.validate(8).unwrap_err().into_iter().map(|error| error.field_path).collect();
+        assert_eq!(field_paths, vec!["chunk_dimension", "min_memory_mb", "mesh_method", "max_amount_of_blocktypes"]);
+    }
+}
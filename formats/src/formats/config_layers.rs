@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use crate::formats::config_format::ConfigFormat;
+use crate::formats::migration::{self, MigrationError};
+
+pub const ENV_PREFIX: &str = "WGPU_PLAYGROUND_";
+pub const PROFILE_ENV_VAR: &str = "WGPU_PLAYGROUND_PROFILE";
+pub const DATA_DIR_ENV_VAR: &str = "WGPU_PLAYGROUND_DATA_DIR";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Base(PathBuf),
+    Profile(PathBuf),
+    Environment(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Base(path) => write!(f, "base {}", path.display()),
+            ConfigSource::Profile(path) => write!(f, "profile {}", path.display()),
+            ConfigSource::Environment(var) => write!(f, "env {}", var),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigLayerError {
+    Io { path: PathBuf, error: std::io::Error },
+    Parse { source: ConfigSource, error: serde_json::Error },
+    Migration { source: ConfigSource, error: MigrationError },
+    UnknownField { source: ConfigSource, field_path: String },
+    InvalidValue { source: ConfigSource, field_path: String, value: String },
+    Deserialize { error: serde_json::Error },
+}
+
+impl fmt::Display for ConfigLayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigLayerError::Io { path, error } => write!(f, "could not read {}: {}", path.display(), error),
+            ConfigLayerError::Parse { source, error } => write!(f, "{}: {}", source, error),
+            ConfigLayerError::Migration { source, error } => write!(f, "{}: {}", source, error),
+            ConfigLayerError::UnknownField { source, field_path } => write!(f, "{}: unknown field {}", source, field_path),
+            ConfigLayerError::InvalidValue { source, field_path, value } => write!(f, "{}: invalid value {} for {}", source, value, field_path),
+            ConfigLayerError::Deserialize { error } => write!(f, "merged config is not a valid ConfigFormat: {}", error),
+        }
+    }
+}
+
+/// A config assembled from a complete base file, an optional partial profile file and
+/// `WGPU_PLAYGROUND_*` environment variables, applied in that order. Nested fields are
+/// addressed with `__` in variable names, e.g. `WGPU_PLAYGROUND_TASK_PRIORITIES__MESH`.
+pub struct ConfigLayers {
+    merged: Value,
+    sources: BTreeMap<String, ConfigSource>,
+    warnings: Vec<String>,
+}
+
+impl ConfigLayers {
+    /// Loads the base file, the `config.<profile>.json` next to it when a profile is given, then the environment overrides.
+    pub fn load<I: IntoIterator<Item = (String, String)>>(base_path: &Path, profile: Option<&str>, env_vars: I) -> Result<Self, ConfigLayerError> {
+        let mut layers = Self::from_base_file(base_path)?;
+        if let Some(profile) = profile {
+            layers.apply_profile_file(&Self::profile_path(base_path, profile))?;
+        }
+        layers.apply_env_vars(env_vars)?;
+        Ok(layers)
+    }
+
+    pub fn profile_path(base_path: &Path, profile: &str) -> PathBuf {
+        let stem = base_path.file_stem().and_then(|s| s.to_str()).unwrap_or("config");
+        base_path.with_file_name(format!("{}.{}.json", stem, profile))
+    }
+
+    pub fn from_base_file(path: &Path) -> Result<Self, ConfigLayerError> {
+        let json = read_file(path)?;
+        Self::from_base_json(&json, ConfigSource::Base(path.to_path_buf()))
+    }
+
+    /// The base file is migrated to the current `ConfigFormat` version before any layer is applied to it.
+    pub fn from_base_json(json: &str, source: ConfigSource) -> Result<Self, ConfigLayerError> {
+        let migrated = migration::migrate_str::<ConfigFormat>(json).map_err(|error| ConfigLayerError::Migration { source: source.clone(), error })?;
+        let merged = serde_json::to_value(&migrated.value).unwrap();
+        let mut sources = BTreeMap::new();
+        for field_path in leaf_paths(&merged, "") {
+            sources.insert(field_path, source.clone());
+        }
+        let warnings = migrated.warnings.into_iter().map(|warning| format!("{}: {}", source, warning)).collect();
+        Ok(ConfigLayers { merged, sources, warnings })
+    }
+
+    pub fn apply_profile_file(&mut self, path: &Path) -> Result<(), ConfigLayerError> {
+        let json = read_file(path)?;
+        self.apply_json(&json, ConfigSource::Profile(path.to_path_buf()))
+    }
+
+    pub fn apply_json(&mut self, json: &str, source: ConfigSource) -> Result<(), ConfigLayerError> {
+        let overlay: Value = serde_json::from_str(json).map_err(|error| ConfigLayerError::Parse { source: source.clone(), error })?;
+        merge(&mut self.merged, &overlay, "", &source, &mut self.sources)
+    }
+
+    /// Applies every variable starting with `WGPU_PLAYGROUND_` except the profile and data directory selectors. Unknown names are an error
+    /// so that a typo doesn't silently leave a value at its default.
+    pub fn apply_env_vars<I: IntoIterator<Item = (String, String)>>(&mut self, env_vars: I) -> Result<(), ConfigLayerError> {
+        let field_paths = self.sources.keys().cloned().collect::<Vec<String>>();
+        for (name, raw_value) in env_vars {
+            if !name.starts_with(ENV_PREFIX) || name == PROFILE_ENV_VAR || name == DATA_DIR_ENV_VAR {
+                continue;
+            }
+            let source = ConfigSource::Environment(name.clone());
+            let field_path = match field_paths.iter().find(|path| env_var_name(path) == name) {
+                Some(field_path) => field_path.clone(),
+                None => return Err(ConfigLayerError::UnknownField { source, field_path: name }),
+            };
+
+            let target = leaf_mut(&mut self.merged, &field_path).unwrap();
+            let value = if target.is_string() {
+                Value::String(raw_value)
+            } else {
+                match serde_json::from_str::<Value>(&raw_value) {
+                    Ok(value) if !value.is_object() && !value.is_array() => value,
+                    _ => return Err(ConfigLayerError::InvalidValue { source, field_path, value: raw_value }),
+                }
+            };
+            *target = value;
+            self.sources.insert(field_path, source);
+        }
+        Ok(())
+    }
+
+    pub fn to_config_format(&self) -> Result<ConfigFormat, ConfigLayerError> {
+        serde_json::from_value(self.merged.clone()).map_err(|error| ConfigLayerError::Deserialize { error })
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn field_paths(&self) -> impl Iterator<Item = &String> {
+        self.sources.keys()
+    }
+
+    pub fn source_of(&self, field_path: &str) -> Option<&ConfigSource> {
+        self.sources.get(field_path)
+    }
+
+    /// One `field = value  # source` line per setting in the merged config.
+    pub fn describe(&self) -> String {
+        let width = self.sources.keys().map(|path| path.len()).max().unwrap_or(0);
+        self.sources.iter().map(|(field_path, source)| {
+            let value = leaf(&self.merged, field_path).map(|v| v.to_string()).unwrap_or_default();
+            format!("{:width$} = {:<12} # {}", field_path, value, source, width = width)
+        }).collect::<Vec<String>>().join("\n")
+    }
+}
+
+pub fn env_var_name(field_path: &str) -> String {
+    format!("{}{}", ENV_PREFIX, field_path.replace('.', "__").to_uppercase())
+}
+
+fn read_file(path: &Path) -> Result<String, ConfigLayerError> {
+    std::fs::read_to_string(path).map_err(|error| ConfigLayerError::Io { path: path.to_path_buf(), error })
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn leaf_paths(value: &Value, prefix: &str) -> Vec<String> {
+    match value {
+        Value::Object(map) => map.iter().flat_map(|(key, child)| leaf_paths(child, &join_path(prefix, key))).collect(),
+        _ => vec![prefix.to_string()],
+    }
+}
+
+fn leaf<'a>(value: &'a Value, field_path: &str) -> Option<&'a Value> {
+    field_path.split('.').try_fold(value, |current, key| current.get(key))
+}
+
+fn leaf_mut<'a>(value: &'a mut Value, field_path: &str) -> Option<&'a mut Value> {
+    field_path.split('.').try_fold(value, |current, key| current.get_mut(key))
+}
+
+fn merge(target: &mut Value, overlay: &Value, prefix: &str, source: &ConfigSource, sources: &mut BTreeMap<String, ConfigSource>) -> Result<(), ConfigLayerError> {
+    let overlay_map = match overlay.as_object() {
+        Some(map) => map,
+        None => return Err(ConfigLayerError::InvalidValue { source: source.clone(), field_path: prefix.to_string(), value: overlay.to_string() }),
+    };
+    for (key, overlay_value) in overlay_map {
+        let field_path = join_path(prefix, key);
+        let target_value = match target.get_mut(key) {
+            Some(target_value) => target_value,
+            None => return Err(ConfigLayerError::UnknownField { source: source.clone(), field_path }),
+        };
+        if target_value.is_object() {
+            merge(target_value, overlay_value, &field_path, source, sources)?;
+        } else if overlay_value.is_object() {
+            return Err(ConfigLayerError::InvalidValue { source: source.clone(), field_path, value: overlay_value.to_string() });
+        } else {
+            *target_value = overlay_value.clone();
+            sources.insert(field_path, source.clone());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_layers() -> ConfigLayers {
+        ConfigLayers::from_base_json(include_str!("../../fixtures/migration/config_v5.json"), ConfigSource::Base(PathBuf::from("config.json"))).unwrap()
+    }
+
+    fn get_env_vars(env_vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        env_vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn profile() -> ConfigSource {
+        ConfigSource::Profile(PathBuf::from("config.fast.json"))
+    }
+
+    #[test]
+    fn a_profile_overrides_one_nested_field_and_keeps_its_siblings() {
+        let mut layers = get_layers();
+        layers.apply_json(r#"{ "task_priorities": { "mesh": 9 }, "chunk_generation_options": { "perlin_scale_factor": 0.5 } }"#, profile()).unwrap();
+        let config_format = layers.to_config_format().unwrap();
+
+        assert_eq!(config_format.task_priorities.mesh, 9);
+        assert_eq!(config_format.task_priorities.mesh_side, 3);
+        assert_eq!(config_format.task_priorities.set_block, 5);
+        assert_eq!(config_format.chunk_generation_options.perlin_scale_factor, 0.5);
+        assert_eq!(config_format.chunk_generation_options.perlin_positive_threshold, 0.2);
+    }
+
+    #[test]
+    fn an_unknown_field_in_a_profile_is_an_error() {
+        let mut layers = get_layers();
+
+        let error = layers.apply_json(r#"{ "task_priorities": { "meshh": 9 } }"#, profile()).unwrap_err();
+        assert!(matches!(error, ConfigLayerError::UnknownField { ref field_path, .. } if field_path == "task_priorities.meshh"), "{}", error);
+        let error = layers.apply_json(r#"{ "task_priorities": 9 }"#, profile()).unwrap_err();
+        assert!(matches!(error, ConfigLayerError::InvalidValue { .. }), "{}", error);
+    }
+
+    #[test]
+    fn env_vars_address_nested_fields_and_parse_for_their_target() {
+        let mut layers = get_layers();
+        let env_vars = [
+            ("WGPU_PLAYGROUND_TASK_PRIORITIES__MESH", "7"),
+            ("WGPU_PLAYGROUND_MOVEMENT_SPEED", "3.5"),
+            ("WGPU_PLAYGROUND_USE_ALL_SYSTEM_THREADS", "false"),
+            // Strings are taken as they are, without JSON quotes.
+            ("WGPU_PLAYGROUND_WORLD_DIRECTORY", "saves/other"),
+            ("WGPU_PLAYGROUND_PROFILE", "fast"),
+            ("WGPU_PLAYGROUND_DATA_DIR", "/data"),
+            ("HOME", "/root"),
+        ];
+        layers.apply_env_vars(get_env_vars(&env_vars)).unwrap();
+        let config_format = layers.to_config_format().unwrap();
+
+        assert_eq!(config_format.task_priorities.mesh, 7);
+        assert_eq!(config_format.task_priorities.chunk, 1);
+        assert_eq!(config_format.movement_speed, 3.5);
+        assert!(!config_format.use_all_system_threads);
+        assert_eq!(config_format.world_directory, "saves/other");
+    }
+
+    #[test]
+    fn env_vars_must_name_a_field_and_hold_a_plain_value() {
+        for (name, value) in [("WGPU_PLAYGROUND_TASK_PRIORITIES__MESHH", "7"), ("WGPU_PLAYGROUND_TASK_PRIORITIES", "7")] {
+            let error = get_layers().apply_env_vars(get_env_vars(&[(name, value)])).unwrap_err();
+            assert!(matches!(error, ConfigLayerError::UnknownField { .. }), "{}: {}", name, error);
+        }
+        for value in ["{\"a\": 1}", "[1, 2]", "fast"] {
+            let error = get_layers().apply_env_vars(get_env_vars(&[("WGPU_PLAYGROUND_RENDER_RADIUS", value)])).unwrap_err();
+            assert!(matches!(error, ConfigLayerError::InvalidValue { .. }), "{}: {}", value, error);
+        }
+    }
+
+    #[test]
+    fn every_value_reports_the_layer_it_came_from() {
+        let mut layers = get_layers();
+        layers.apply_json(r#"{ "task_priorities": { "mesh": 9, "chunk": 8 } }"#, profile()).unwrap();
+        layers.apply_env_vars(get_env_vars(&[("WGPU_PLAYGROUND_TASK_PRIORITIES__CHUNK", "6")])).unwrap();
+
+        assert_eq!(layers.source_of("task_priorities.mesh_side"), Some(&ConfigSource::Base(PathBuf::from("config.json"))));
+        assert_eq!(layers.source_of("task_priorities.mesh"), Some(&profile()));
+        let env_source = ConfigSource::Environment(String::from("WGPU_PLAYGROUND_TASK_PRIORITIES__CHUNK"));
+        assert_eq!(layers.source_of("task_priorities.chunk"), Some(&env_source));
+        assert_eq!(layers.source_of("task_priorities"), None);
+
+        let description = layers.describe();
+        let line = |field_path: &str| description.lines().find(|line| line.starts_with(&format!("{} ", field_path))).unwrap().to_string();
+        assert!(line("task_priorities.mesh_side").ends_with("= 3            # base config.json"), "{}", description);
+        assert!(line("task_priorities.mesh").ends_with("= 9            # profile config.fast.json"), "{}", description);
+        assert!(line("task_priorities.chunk").ends_with("= 6            # env WGPU_PLAYGROUND_TASK_PRIORITIES__CHUNK"), "{}", description);
+        assert_eq!(description.lines().count(), layers.field_paths().count());
+    }
+}
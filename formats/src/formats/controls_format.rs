@@ -1,14 +1,200 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use crate::formats::input_names::{nearest_name, KEY_CODE_NAMES, MOUSE_BUTTON_NAMES};
 
-#[derive(Serialize, Deserialize)]
+const MODIFIER_NAMES: [&str; 4] = ["Shift", "Ctrl", "Alt", "Super"];
+
+/// Each action maps to any number of bindings. A binding is an input, optionally preceded by
+/// modifiers, e.g. `"KeyCode::KeyW"`, `"MouseButton::Left"` or `"Ctrl+Shift+KeyCode::KeyS"`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ControlsFormat {
-    pub up: String,
-    pub down: String,
-    pub left: String,
-    pub right: String,
-    pub forward: String,
-    pub backward: String,
+    pub format_version: u32,
+    pub actions: BTreeMap<String, Vec<String>>,
 
     pub mouse_sensitivity_threshold: f64,
     pub mouse_sensitivity: f32,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Super,
+}
+
+/// `K` and `B` are the key and mouse button types names deserialize to, winit's `KeyCode` and `MouseButton`
+/// for the game. This crate doesn't depend on winit, so it never lists the valid names itself.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BindingInput<K, B> {
+    Key(K),
+    MouseButton(B),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Binding<K, B> {
+    pub modifiers: Vec<Modifier>,
+    pub input: BindingInput<K, B>,
+}
+
+/// The bindings of every action, by action name.
+pub type ActionBindings<K, B> = BTreeMap<String, Vec<Binding<K, B>>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingErrorKind {
+    Empty,
+    UnknownModifier { modifier: String, suggestion: Option<String> },
+    MissingInputPrefix { input: String },
+    UnknownKey { key: String, suggestion: Option<String> },
+    UnknownMouseButton { button: String, suggestion: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingError {
+    pub field_path: String,
+    pub binding: String,
+    pub kind: BindingErrorKind,
+}
+
+impl fmt::Display for BindingErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (message, suggestion) = match self {
+            BindingErrorKind::Empty => (String::from("binding is empty"), &None),
+            BindingErrorKind::UnknownModifier { modifier, suggestion } => (format!("unknown modifier {}, expected Shift, Ctrl, Alt or Super", modifier), suggestion),
+            BindingErrorKind::MissingInputPrefix { input } => (format!("{} must be written as KeyCode::<name> or MouseButton::<name>", input), &None),
+            BindingErrorKind::UnknownKey { key, suggestion } => (format!("unknown key KeyCode::{}", key), suggestion),
+            BindingErrorKind::UnknownMouseButton { button, suggestion } => (format!("unknown mouse button MouseButton::{}", button), suggestion),
+        };
+        match suggestion {
+            Some(suggestion) => write!(f, "{}, did you mean {}?", message, suggestion),
+            None => write!(f, "{}", message),
+        }
+    }
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?}: {}", self.field_path, self.binding, self.kind)
+    }
+}
+
+impl<K: DeserializeOwned, B: DeserializeOwned> Binding<K, B> {
+    /// Parses a binding, resolving its input by deserializing the name as a `K` or `B`.
+    pub fn parse(binding: &str) -> Result<Self, BindingErrorKind> {
+        let mut parts = binding.split('+').map(|part| part.trim()).collect::<Vec<&str>>();
+        let input = match parts.pop() {
+            Some(input) if !input.is_empty() => input,
+            _ => return Err(BindingErrorKind::Empty),
+        };
+
+        let mut modifiers = Vec::new();
+        for part in parts {
+            let modifier = match part {
+                "Shift" => Modifier::Shift,
+                "Ctrl" => Modifier::Ctrl,
+                "Alt" => Modifier::Alt,
+                "Super" => Modifier::Super,
+                _ => return Err(BindingErrorKind::UnknownModifier { modifier: part.to_string(), suggestion: nearest_name(part, &MODIFIER_NAMES).map(String::from) }),
+            };
+            modifiers.push(modifier);
+        }
+
+        let input = if let Some(key) = input.strip_prefix("KeyCode::") {
+            match deserialize_name(key) {
+                Some(key) => BindingInput::Key(key),
+                None => return Err(BindingErrorKind::UnknownKey { key: key.to_string(), suggestion: nearest_name(key, &KEY_CODE_NAMES).map(|name| format!("KeyCode::{}", name)) }),
+            }
+        } else if let Some(button) = input.strip_prefix("MouseButton::") {
+            match deserialize_name(button) {
+                Some(button) => BindingInput::MouseButton(button),
+                None => return Err(BindingErrorKind::UnknownMouseButton { button: button.to_string(), suggestion: nearest_name(button, &MOUSE_BUTTON_NAMES).map(|name| format!("MouseButton::{}", name)) }),
+            }
+        } else {
+            return Err(BindingErrorKind::MissingInputPrefix { input: input.to_string() });
+        };
+        Ok(Binding { modifiers, input })
+    }
+}
+
+// Unit variants serialize as their names, so a name is valid exactly when it deserializes.
+fn deserialize_name<T: DeserializeOwned>(name: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::from(name)).ok()
+}
+
+impl ControlsFormat {
+    /// Parses every binding, reporting each invalid one with its field path, e.g. `actions.move_forward[1]`.
+    pub fn parse_bindings<K: DeserializeOwned, B: DeserializeOwned>(&self) -> Result<ActionBindings<K, B>, Vec<BindingError>> {
+        let mut action_bindings = BTreeMap::new();
+        let mut errors = Vec::new();
+        for (action, bindings) in &self.actions {
+            let mut parsed_bindings = Vec::new();
+            for (i, binding) in bindings.iter().enumerate() {
+                match Binding::parse(binding) {
+                    Ok(parsed_binding) => parsed_bindings.push(parsed_binding),
+                    Err(kind) => errors.push(BindingError { field_path: format!("actions.{}[{}]", action, i), binding: binding.clone(), kind }),
+                }
+            }
+            action_bindings.insert(action.clone(), parsed_bindings);
+        }
+        if errors.is_empty() {
+            Ok(action_bindings)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stand-ins for winit's types, which deserialize from their variant names the same way.
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Key {
+        KeyW,
+        ShiftLeft,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Button {
+        Left,
+    }
+
+    fn get_controls_format(actions: &[(&str, &[&str])]) -> ControlsFormat {
+        ControlsFormat {
+            format_version: 3,
+            actions: actions.iter().map(|(action, bindings)| (action.to_string(), bindings.iter().map(|binding| binding.to_string()).collect())).collect(),
+            mouse_sensitivity_threshold: 0.5,
+            mouse_sensitivity: 0.8,
+        }
+    }
+
+    #[test]
+    fn resolves_names_by_deserializing_them() {
+        let controls_format = get_controls_format(&[("move_forward", &["KeyCode::KeyW", "Shift + MouseButton::Left"])]);
+        let action_bindings = controls_format.parse_bindings::<Key, Button>().unwrap();
+        assert_eq!(action_bindings["move_forward"], vec![
+            Binding { modifiers: vec![], input: BindingInput::Key(Key::KeyW) },
+            Binding { modifiers: vec![Modifier::Shift], input: BindingInput::MouseButton(Button::Left) },
+        ]);
+    }
+
+    #[test]
+    fn reports_every_invalid_binding_with_its_field_and_a_suggestion() {
+        let controls_format = get_controls_format(&[
+            ("move_down", &["KeyCode::ShiftLeft", "KeyCode::ShiftLetf"]),
+            ("look", &["MouseButton::Lfet", "Ctlr+KeyCode::KeyW", "KeyW", ""]),
+        ]);
+        let errors = controls_format.parse_bindings::<Key, Button>().unwrap_err();
+        let errors = errors.iter().map(|error| error.to_string()).collect::<Vec<String>>();
+        assert_eq!(errors, vec![
+            "actions.look[0]: \"MouseButton::Lfet\": unknown mouse button MouseButton::Lfet, did you mean MouseButton::Left?",
+            "actions.look[1]: \"Ctlr+KeyCode::KeyW\": unknown modifier Ctlr, expected Shift, Ctrl, Alt or Super, did you mean Ctrl?",
+            "actions.look[2]: \"KeyW\": KeyW must be written as KeyCode::<name> or MouseButton::<name>",
+            "actions.look[3]: \"\": binding is empty",
+            "actions.move_down[1]: \"KeyCode::ShiftLetf\": unknown key KeyCode::ShiftLetf, did you mean KeyCode::ShiftLeft?",
+        ]);
+    }
+}
@@ -0,0 +1,59 @@
+/// Variant names of winit 0.30 `KeyCode`, only used to suggest a name for a misspelled key. Bindings are
+/// checked by deserializing winit's `KeyCode` itself, so a name missing here is still accepted.
+pub const KEY_CODE_NAMES: [&str; 194] = [
+    "Backquote", "Backslash", "BracketLeft", "BracketRight", "Comma", "Digit0", "Digit1", "Digit2",
+    "Digit3", "Digit4", "Digit5", "Digit6", "Digit7", "Digit8", "Digit9", "Equal",
+    "IntlBackslash", "IntlRo", "IntlYen", "KeyA", "KeyB", "KeyC", "KeyD", "KeyE",
+    "KeyF", "KeyG", "KeyH", "KeyI", "KeyJ", "KeyK", "KeyL", "KeyM",
+    "KeyN", "KeyO", "KeyP", "KeyQ", "KeyR", "KeyS", "KeyT", "KeyU",
+    "KeyV", "KeyW", "KeyX", "KeyY", "KeyZ", "Minus", "Period", "Quote",
+    "Semicolon", "Slash", "AltLeft", "AltRight", "Backspace", "CapsLock", "ContextMenu", "ControlLeft",
+    "ControlRight", "Enter", "SuperLeft", "SuperRight", "ShiftLeft", "ShiftRight", "Space", "Tab",
+    "Convert", "KanaMode", "Lang1", "Lang2", "Lang3", "Lang4", "Lang5", "NonConvert",
+    "Delete", "End", "Help", "Home", "Insert", "PageDown", "PageUp", "ArrowDown",
+    "ArrowLeft", "ArrowRight", "ArrowUp", "NumLock", "Numpad0", "Numpad1", "Numpad2", "Numpad3",
+    "Numpad4", "Numpad5", "Numpad6", "Numpad7", "Numpad8", "Numpad9", "NumpadAdd", "NumpadBackspace",
+    "NumpadClear", "NumpadClearEntry", "NumpadComma", "NumpadDecimal", "NumpadDivide", "NumpadEnter", "NumpadEqual", "NumpadHash",
+    "NumpadMemoryAdd", "NumpadMemoryClear", "NumpadMemoryRecall", "NumpadMemoryStore", "NumpadMemorySubtract", "NumpadMultiply", "NumpadParenLeft", "NumpadParenRight",
+    "NumpadStar", "NumpadSubtract", "Escape", "Fn", "FnLock", "PrintScreen", "ScrollLock", "Pause",
+    "BrowserBack", "BrowserFavorites", "BrowserForward", "BrowserHome", "BrowserRefresh", "BrowserSearch", "BrowserStop", "Eject",
+    "LaunchApp1", "LaunchApp2", "LaunchMail", "MediaPlayPause", "MediaSelect", "MediaStop", "MediaTrackNext", "MediaTrackPrevious",
+    "Power", "Sleep", "AudioVolumeDown", "AudioVolumeMute", "AudioVolumeUp", "WakeUp", "Meta", "Hyper",
+    "Turbo", "Abort", "Resume", "Suspend", "Again", "Copy", "Cut", "Find",
+    "Open", "Paste", "Props", "Select", "Undo", "Hiragana", "Katakana", "F1",
+    "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9",
+    "F10", "F11", "F12", "F13", "F14", "F15", "F16", "F17",
+    "F18", "F19", "F20", "F21", "F22", "F23", "F24", "F25",
+    "F26", "F27", "F28", "F29", "F30", "F31", "F32", "F33",
+    "F34", "F35",
+];
+
+/// Named winit `MouseButton` variants, for suggestions like `KEY_CODE_NAMES`; `Other(u16)` can't be bound by name.
+pub const MOUSE_BUTTON_NAMES: [&str; 5] = ["Left", "Right", "Middle", "Back", "Forward"];
+
+/// The candidate closest to `name` by edit distance, ignoring case, if it is close enough to be a likely typo.
+pub fn nearest_name<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let (distance, candidate) = candidates.iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), *candidate))
+        .min_by_key(|(distance, _)| *distance)?;
+    if distance <= (name.len() / 3).max(2) {
+        Some(candidate)
+    } else {
+        None
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous_row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current_row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + if a_char == *b_char { 0 } else { 1 };
+            current_row.push(substitution.min(previous_row[j + 1] + 1).min(current_row[j] + 1));
+        }
+        previous_row = current_row;
+    }
+    previous_row[b.len()]
+}
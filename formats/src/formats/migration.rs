@@ -0,0 +1,382 @@
+use std::fmt;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use crate::formats::block_format::{BlockFormat, BlockProperties};
+use crate::formats::config_format::ConfigFormat;
+use crate::formats::controls_format::ControlsFormat;
+use crate::formats::block_replacement_format::BlockReplacementFormat;
+use crate::formats::block_id_table_format::BlockIdTableFormat;
+use crate::formats::atlas_manifest_format::AtlasManifestFormat;
+
+/// Documents written before versioning have no `format_version` and are treated as version 0.
+pub const FORMAT_VERSION_FIELD: &str = "format_version";
+
+/// Upgrades a document from version `n` to `n + 1`, pushing a warning for anything it can't carry over.
+pub type MigrationStep = fn(&mut Map<String, Value>, &mut Vec<String>);
+
+pub trait VersionedFormat: Serialize + DeserializeOwned {
+    const FORMAT_NAME: &'static str;
+    const CURRENT_VERSION: u32;
+
+    /// `migrations()[n]` upgrades version `n` to `n + 1`, so its length must equal `CURRENT_VERSION`.
+    fn migrations() -> &'static [MigrationStep];
+}
+
+impl VersionedFormat for ConfigFormat {
+    const FORMAT_NAME: &'static str = "config";
+    const CURRENT_VERSION: u32 = 5;
+
+    fn migrations() -> &'static [MigrationStep] {
+        &[config_v0_to_v1, config_v1_to_v2, config_v2_to_v3, config_v3_to_v4, config_v4_to_v5]
+    }
+}
+
+impl VersionedFormat for BlockFormat {
+    const FORMAT_NAME: &'static str = "block";
+    const CURRENT_VERSION: u32 = 5;
+
+    fn migrations() -> &'static [MigrationStep] {
+        &[stamp_only, block_v1_to_v2, stamp_only, stamp_only, stamp_only]
+    }
+}
+
+impl VersionedFormat for ControlsFormat {
+    const FORMAT_NAME: &'static str = "controls";
+    const CURRENT_VERSION: u32 = 3;
+
+    fn migrations() -> &'static [MigrationStep] {
+        &[stamp_only, controls_v1_to_v2, controls_v2_to_v3]
+    }
+}
+
+impl VersionedFormat for BlockReplacementFormat {
+    const FORMAT_NAME: &'static str = "block replacement";
+    const CURRENT_VERSION: u32 = 1;
+
+    fn migrations() -> &'static [MigrationStep] {
+        &[stamp_only]
+    }
+}
+
+impl VersionedFormat for AtlasManifestFormat {
+    const FORMAT_NAME: &'static str = "atlas manifest";
+    const CURRENT_VERSION: u32 = 3;
+
+    fn migrations() -> &'static [MigrationStep] {
+        &[stamp_only, stamp_only, stamp_only]
+    }
+}
+
+impl VersionedFormat for BlockIdTableFormat {
+    const FORMAT_NAME: &'static str = "block id table";
+    const CURRENT_VERSION: u32 = 1;
+
+    fn migrations() -> &'static [MigrationStep] {
+        &[stamp_only]
+    }
+}
+
+// The subvoxel limits were added after the first configs were written; these are the values data/config.json shipped with.
+fn config_v0_to_v1(document: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    let defaults = [
+        ("max_subvoxel_objects", 500),
+        ("max_subvoxels_in_models", 1250),
+        ("max_subvoxel_colors", 32),
+        ("max_grid_aligned_subvoxel_objects", 1024),
+        ("grid_aligned_subvoxel_placement_dimension", 16),
+    ];
+    for (field, default) in defaults {
+        if !document.contains_key(field) {
+            warnings.push(format!("{} missing, using default {}", field, default));
+            document.insert(field.to_string(), Value::from(default));
+        }
+    }
+}
+
+// Textures used to be stored without a gutter; 4 pixels keeps the first two mip levels free of neighbouring textures.
+fn config_v1_to_v2(document: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    if !document.contains_key("atlas_padding") {
+        warnings.push(String::from("atlas_padding missing, using default 4"));
+        document.insert("atlas_padding".to_string(), Value::from(4));
+    }
+}
+
+// Worlds weren't saved before; new configs keep them next to the data directory.
+fn config_v2_to_v3(document: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    if !document.contains_key("world_directory") {
+        warnings.push(String::from("world_directory missing, using default \"saves/world\""));
+        document.insert("world_directory".to_string(), Value::from("saves/world"));
+    }
+}
+
+// Chunks used to stay loaded forever. Two chunks past the render radius keeps turning on the spot from reloading.
+fn config_v3_to_v4(document: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    if !document.contains_key("unload_radius") {
+        let unload_radius = document.get("render_radius").and_then(Value::as_u64).unwrap_or(0) + 2;
+        warnings.push(format!("unload_radius missing, using default {}", unload_radius));
+        document.insert("unload_radius".to_string(), Value::from(unload_radius));
+    }
+    if !document.contains_key("chunk_memory_budget_mb") {
+        warnings.push(String::from("chunk_memory_budget_mb missing, using default 256"));
+        document.insert("chunk_memory_budget_mb".to_string(), Value::from(256));
+    }
+}
+
+// Block edits came after the other tasks; running them first keeps breaking and placing responsive.
+fn config_v4_to_v5(document: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    if let Some(Value::Object(task_priorities)) = document.get_mut("task_priorities") {
+        if !task_priorities.contains_key("set_block") {
+            warnings.push(String::from("task_priorities.set_block missing, using default 5"));
+            task_priorities.insert("set_block".to_string(), Value::from(5));
+        }
+    }
+}
+
+// Every block before properties existed was rendered and collided with as an opaque solid.
+fn block_v1_to_v2(document: &mut Map<String, Value>, _warnings: &mut Vec<String>) {
+    if !document.contains_key("properties") {
+        document.insert("properties".to_string(), serde_json::to_value(BlockProperties::default()).unwrap());
+    }
+}
+
+// The six single-key movement fields became actions, joined by the bindings that used to be hard-coded.
+fn controls_v1_to_v2(document: &mut Map<String, Value>, _warnings: &mut Vec<String>) {
+    let mut actions = Map::new();
+    for (field, action) in [("up", "move_up"), ("down", "move_down"), ("left", "move_left"), ("right", "move_right"), ("forward", "move_forward"), ("backward", "move_backward")] {
+        if let Some(binding) = document.remove(field) {
+            actions.insert(action.to_string(), Value::Array(vec![binding]));
+        }
+    }
+    for (action, binding) in [("look", "MouseButton::Left"), ("toggle_wireframe", "KeyCode::ControlLeft"), ("rotate_subvoxel", "KeyCode::KeyR")] {
+        actions.insert(action.to_string(), Value::Array(vec![Value::from(binding)]));
+    }
+    document.insert("actions".to_string(), Value::Object(actions));
+}
+
+// Breaking and placing blocks were added with their own actions. The left mouse button already looks around.
+fn controls_v2_to_v3(document: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    if let Some(Value::Object(actions)) = document.get_mut("actions") {
+        for (action, binding) in [("break_block", "MouseButton::Right"), ("place_block", "MouseButton::Middle"), ("select_next_block", "KeyCode::KeyE")] {
+            if !actions.contains_key(action) {
+                warnings.push(format!("actions.{} missing, using default {}", action, binding));
+                actions.insert(action.to_string(), Value::Array(vec![Value::from(binding)]));
+            }
+        }
+    }
+}
+
+fn stamp_only(_document: &mut Map<String, Value>, _warnings: &mut Vec<String>) {}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    NotAnObject { format_name: &'static str },
+    InvalidVersion { format_name: &'static str, value: Value },
+    VersionTooNew { format_name: &'static str, found: u32, current: u32 },
+    Deserialize { format_name: &'static str, error: serde_json::Error },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::NotAnObject { format_name } => write!(f, "{} document is not a JSON object", format_name),
+            MigrationError::InvalidVersion { format_name, value } => write!(f, "{} document has invalid {} {}", format_name, FORMAT_VERSION_FIELD, value),
+            MigrationError::VersionTooNew { format_name, found, current } => write!(f, "{} document has version {} but the newest known version is {}", format_name, found, current),
+            MigrationError::Deserialize { format_name, error } => write!(f, "{} document could not be read after migration: {}", format_name, error),
+        }
+    }
+}
+
+pub struct Migrated<T> {
+    pub value: T,
+    pub from_version: u32,
+    pub warnings: Vec<String>,
+}
+
+/// Runs the migration steps from the document's version up to `T::CURRENT_VERSION` and stamps the new version,
+/// without checking the result against `T`.
+pub fn upgrade<T: VersionedFormat>(value: Value) -> Result<Migrated<Map<String, Value>>, MigrationError> {
+    let mut document = match value {
+        Value::Object(document) => document,
+        _ => return Err(MigrationError::NotAnObject { format_name: T::FORMAT_NAME }),
+    };
+    let from_version = match document.get(FORMAT_VERSION_FIELD) {
+        None => 0,
+        Some(version) => match version.as_u64().and_then(|version| u32::try_from(version).ok()) {
+            Some(version) => version,
+            None => return Err(MigrationError::InvalidVersion { format_name: T::FORMAT_NAME, value: version.clone() }),
+        },
+    };
+    if from_version > T::CURRENT_VERSION {
+        return Err(MigrationError::VersionTooNew { format_name: T::FORMAT_NAME, found: from_version, current: T::CURRENT_VERSION });
+    }
+
+    let mut warnings = Vec::new();
+    for step in &T::migrations()[from_version as usize..T::CURRENT_VERSION as usize] {
+        step(&mut document, &mut warnings);
+    }
+    document.insert(FORMAT_VERSION_FIELD.to_string(), Value::from(T::CURRENT_VERSION));
+    Ok(Migrated { value: document, from_version, warnings })
+}
+
+/// Upgrades and deserializes a document, warning about every field the current format no longer has.
+pub fn migrate<T: VersionedFormat>(value: Value) -> Result<Migrated<T>, MigrationError> {
+    let Migrated { value: document, from_version, mut warnings } = upgrade::<T>(value)?;
+    let document = Value::Object(document);
+    let migrated: T = serde_json::from_value(document.clone()).map_err(|error| MigrationError::Deserialize { format_name: T::FORMAT_NAME, error })?;
+
+    let round_tripped = serde_json::to_value(&migrated).unwrap();
+    for field_path in dropped_fields(&document, &round_tripped, "") {
+        warnings.push(format!("dropping unknown field {}", field_path));
+    }
+    let warnings = warnings.into_iter()
+        .map(|warning| format!("{} v{} -> v{}: {}", T::FORMAT_NAME, from_version, T::CURRENT_VERSION, warning))
+        .collect();
+    Ok(Migrated { value: migrated, from_version, warnings })
+}
+
+pub fn migrate_str<T: VersionedFormat>(json: &str) -> Result<Migrated<T>, MigrationError> {
+    let value: Value = serde_json::from_str(json).map_err(|error| MigrationError::Deserialize { format_name: T::FORMAT_NAME, error })?;
+    migrate(value)
+}
+
+/// Migrates every element of a JSON array, e.g. `data/blocks.json`.
+pub fn migrate_vec_str<T: VersionedFormat>(json: &str) -> Result<Migrated<Vec<T>>, MigrationError> {
+    let values: Vec<Value> = serde_json::from_str(json).map_err(|error| MigrationError::Deserialize { format_name: T::FORMAT_NAME, error })?;
+    let mut migrated_values = Vec::new();
+    let mut from_version = T::CURRENT_VERSION;
+    let mut warnings = Vec::new();
+    for (i, value) in values.into_iter().enumerate() {
+        let migrated = migrate::<T>(value)?;
+        from_version = from_version.min(migrated.from_version);
+        warnings.extend(migrated.warnings.into_iter().map(|warning| format!("[{}] {}", i, warning)));
+        migrated_values.push(migrated.value);
+    }
+    Ok(Migrated { value: migrated_values, from_version, warnings })
+}
+
+fn dropped_fields(original: &Value, kept: &Value, prefix: &str) -> Vec<String> {
+    let (original, kept) = match (original.as_object(), kept.as_object()) {
+        (Some(original), Some(kept)) => (original, kept),
+        _ => return Vec::new(),
+    };
+    let mut dropped = Vec::new();
+    for (key, original_value) in original {
+        let field_path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match kept.get(key) {
+            Some(kept_value) => dropped.extend(dropped_fields(original_value, kept_value, &field_path)),
+            None => dropped.push(field_path),
+        }
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every fixture of a format holds the same settings as they were written at its version, so each
+    // one migrates to the format's current fixture.
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!("../../fixtures/migration/", $name, ".json"))
+        };
+    }
+
+    fn assert_migrates_to<T: VersionedFormat>(fixture: &str, current: &str, from_version: u32, expected_warnings: &[&str]) {
+        let migrated = migrate_str::<T>(fixture).unwrap_or_else(|e| panic!("v{}: {}", from_version, e));
+        assert_eq!(migrated.from_version, from_version);
+        let current: T = serde_json::from_str(current).unwrap();
+        assert_eq!(serde_json::to_value(&migrated.value).unwrap(), serde_json::to_value(&current).unwrap(), "v{} migrated to", from_version);
+        assert_eq!(migrated.warnings, expected_warnings, "v{} warnings", from_version);
+    }
+
+    #[test]
+    fn migrates_every_config_version() {
+        let current = fixture!("config_v5");
+        let v4_warnings = ["task_priorities.set_block missing, using default 5"];
+        let v3_warnings = ["unload_radius missing, using default 7", "chunk_memory_budget_mb missing, using default 256"];
+        let v2_warnings = ["world_directory missing, using default \"saves/world\""];
+        let v1_warnings = ["atlas_padding missing, using default 4"];
+        let v0_warnings = [
+            "max_subvoxel_objects missing, using default 500",
+            "max_subvoxels_in_models missing, using default 1250",
+            "max_subvoxel_colors missing, using default 32",
+            "max_grid_aligned_subvoxel_objects missing, using default 1024",
+            "grid_aligned_subvoxel_placement_dimension missing, using default 16",
+        ];
+        let steps: [&[&str]; 5] = [&v0_warnings, &v1_warnings, &v2_warnings, &v3_warnings, &v4_warnings];
+        let fixtures = [fixture!("config_v0"), fixture!("config_v1"), fixture!("config_v2"), fixture!("config_v3"), fixture!("config_v4"), current];
+        for (from_version, fixture) in fixtures.iter().enumerate() {
+            let expected_warnings: Vec<String> = steps[from_version..].iter()
+                .flat_map(|warnings| warnings.iter())
+                .map(|warning| format!("config v{} -> v5: {}", from_version, warning))
+                .collect();
+            let expected_warnings: Vec<&str> = expected_warnings.iter().map(String::as_str).collect();
+            assert_migrates_to::<ConfigFormat>(fixture, current, from_version as u32, &expected_warnings);
+        }
+    }
+
+    #[test]
+    fn migrates_every_block_version() {
+        let current = fixture!("block_v5");
+        let fixtures = [fixture!("block_v0"), fixture!("block_v1"), fixture!("block_v2"), fixture!("block_v3"), fixture!("block_v4"), current];
+        for (from_version, fixture) in fixtures.iter().enumerate() {
+            // Blocks without properties quietly become opaque solids, as they were rendered before.
+            assert_migrates_to::<BlockFormat>(fixture, current, from_version as u32, &[]);
+        }
+    }
+
+    #[test]
+    fn migrates_every_controls_version() {
+        let current = fixture!("controls_v3");
+        let fixtures = [fixture!("controls_v0"), fixture!("controls_v1"), fixture!("controls_v2")];
+        for (from_version, fixture) in fixtures.iter().enumerate() {
+            let expected_warnings = [
+                format!("controls v{} -> v3: actions.break_block missing, using default MouseButton::Right", from_version),
+                format!("controls v{} -> v3: actions.place_block missing, using default MouseButton::Middle", from_version),
+                format!("controls v{} -> v3: actions.select_next_block missing, using default KeyCode::KeyE", from_version),
+            ];
+            let expected_warnings: Vec<&str> = expected_warnings.iter().map(String::as_str).collect();
+            assert_migrates_to::<ControlsFormat>(fixture, current, from_version as u32, &expected_warnings);
+        }
+        assert_migrates_to::<ControlsFormat>(current, current, 3, &[]);
+    }
+
+    #[test]
+    fn migrates_every_atlas_manifest_version() {
+        let current = fixture!("atlas_manifest_v3");
+        for (from_version, fixture) in [(1, fixture!("atlas_manifest_v1")), (2, fixture!("atlas_manifest_v2")), (3, current)] {
+            assert_migrates_to::<AtlasManifestFormat>(fixture, current, from_version, &[]);
+        }
+    }
+
+    #[test]
+    fn migrates_block_replacements_and_id_tables() {
+        let block_replacements = fixture!("block_replacement_v1");
+        assert_migrates_to::<BlockReplacementFormat>(block_replacements, block_replacements, 1, &[]);
+        let id_table = fixture!("block_id_table_v1");
+        assert_migrates_to::<BlockIdTableFormat>(id_table, id_table, 1, &[]);
+    }
+
+    #[test]
+    fn warns_about_dropped_fields() {
+        let mut document: Value = serde_json::from_str(fixture!("controls_v3")).unwrap();
+        document["mouse_acceleration"] = Value::from(1.5);
+        let migrated = migrate::<ControlsFormat>(document).unwrap();
+        assert_eq!(migrated.warnings, vec!["controls v3 -> v3: dropping unknown field mouse_acceleration"]);
+    }
+
+    #[test]
+    fn rejects_unreadable_versions() {
+        let mut document: Value = serde_json::from_str(fixture!("config_v5")).unwrap();
+        document[FORMAT_VERSION_FIELD] = Value::from(6);
+        assert!(matches!(migrate::<ConfigFormat>(document.clone()), Err(MigrationError::VersionTooNew { found: 6, current: 5, .. })));
+        // Would wrap to version 0 if truncated to u32.
+        document[FORMAT_VERSION_FIELD] = Value::from(1u64 << 32);
+        assert!(matches!(migrate::<ConfigFormat>(document.clone()), Err(MigrationError::InvalidVersion { .. })));
+        document[FORMAT_VERSION_FIELD] = Value::from("5");
+        assert!(matches!(migrate::<ConfigFormat>(document), Err(MigrationError::InvalidVersion { .. })));
+        assert!(matches!(migrate_str::<ConfigFormat>("[]"), Err(MigrationError::NotAnObject { .. })));
+    }
+}
@@ -1,4 +1,13 @@
 pub mod block_format;
 pub mod config_format;
 pub mod texture_format;
-pub mod controls_format;
\ No newline at end of file
+pub mod controls_format;
+pub mod config_layers;
+pub mod migration;
+pub mod input_names;
+
+pub mod block_replacement_format;
+pub mod block_id_table_format;
+pub mod atlas_manifest_format;
+pub mod checksum;
+pub mod atlas_container;
\ No newline at end of file
@@ -1,17 +1,208 @@
 use serde::{Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
 pub struct TextureFormat {
-    front: String,
-    back: String,
-    left: String,
-    right: String,
-    top: String,
-    bottom: String,
+    front: FaceTextureFormat,
+    back: FaceTextureFormat,
+    left: FaceTextureFormat,
+    right: FaceTextureFormat,
+    top: FaceTextureFormat,
+    bottom: FaceTextureFormat,
+    #[serde(default)]
+    pub mip: MipSettingsFormat,
+    #[serde(default, skip_serializing_if = "MaterialFormat::is_empty")]
+    pub material: MaterialFormat,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MipFilter {
+    Box,
+    Triangle,
+    Lanczos,
+}
+
+/// How the atlas builder downsamples the mip levels of a block's textures.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
+#[serde(default)]
+pub struct MipSettingsFormat {
+    pub filter: MipFilter,
+    /// Average in linear light instead of on the sRGB bytes, which darkens textures as they shrink.
+    pub linear_space: bool,
+    /// For cutout textures, the alpha test threshold (0-255). Every level then keeps the share of
+    /// pixels passing the test that the full size texture has, so foliage doesn't thin out with distance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha_cutoff: Option<u8>,
+}
+
+impl Default for MipSettingsFormat {
+    fn default() -> Self {
+        MipSettingsFormat { filter: MipFilter::Box, linear_space: true, alpha_cutoff: None }
+    }
+}
+
+/// Material maps of the faces that have them, packed into atlas layers with the same layout as the
+/// albedo atlas. Faces without a map get a flat default in that layer, see `MaterialLayer::get_default_pixel`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, Default)]
+#[serde(default)]
+pub struct MaterialFormat {
+    #[serde(skip_serializing_if = "FaceMaterialFormat::is_empty")]
+    front: FaceMaterialFormat,
+    #[serde(skip_serializing_if = "FaceMaterialFormat::is_empty")]
+    back: FaceMaterialFormat,
+    #[serde(skip_serializing_if = "FaceMaterialFormat::is_empty")]
+    left: FaceMaterialFormat,
+    #[serde(skip_serializing_if = "FaceMaterialFormat::is_empty")]
+    right: FaceMaterialFormat,
+    #[serde(skip_serializing_if = "FaceMaterialFormat::is_empty")]
+    top: FaceMaterialFormat,
+    #[serde(skip_serializing_if = "FaceMaterialFormat::is_empty")]
+    bottom: FaceMaterialFormat,
+}
+
+/// Image file names of one face's maps. A map covers every frame of an animated face, so it has to
+/// be the size of a frame. `roughness_metalness` keeps roughness in green and metalness in blue, as glTF does.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, Default)]
+#[serde(default)]
+pub struct FaceMaterialFormat {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roughness_metalness: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emissive: Option<String>,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum MaterialLayer {
+    Normal,
+    RoughnessMetalness,
+    Emissive,
+}
+
+/// A face is either a single image file name or an animation.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
+#[serde(untagged)]
+pub enum FaceTextureFormat {
+    Static(String),
+    Animated(AnimatedTextureFormat),
+}
+
+/// Frames come either from a list of image files or from a vertical `strip` of square frames.
+/// `frame_durations_ms` holds one duration per frame, or a single duration shared by every frame.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
+pub struct AnimatedTextureFormat {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip: Option<String>,
+    pub frame_durations_ms: Vec<u32>,
 }
 
 impl TextureFormat {
-    pub fn to_vec(&self) -> Vec<String> {
+    pub fn to_vec(&self) -> Vec<FaceTextureFormat> {
+        vec![self.front.clone(), self.back.clone(), self.left.clone(), self.right.clone(), self.top.clone(), self.bottom.clone()]
+    }
+}
+
+impl MaterialFormat {
+    pub fn is_empty(&self) -> bool {
+        self.to_vec().iter().all(|face_material| face_material.is_empty())
+    }
+
+    pub fn to_vec(&self) -> Vec<FaceMaterialFormat> {
         vec![self.front.clone(), self.back.clone(), self.left.clone(), self.right.clone(), self.top.clone(), self.bottom.clone()]
     }
-}
\ No newline at end of file
+}
+
+impl FaceMaterialFormat {
+    pub fn is_empty(&self) -> bool {
+        MaterialLayer::ALL.iter().all(|layer| self.get_map(*layer).is_none())
+    }
+
+    pub fn get_map(&self, layer: MaterialLayer) -> Option<&String> {
+        match layer {
+            MaterialLayer::Normal => self.normal.as_ref(),
+            MaterialLayer::RoughnessMetalness => self.roughness_metalness.as_ref(),
+            MaterialLayer::Emissive => self.emissive.as_ref(),
+        }
+    }
+
+    /// The albedo's atlas key extended with the maps, so faces only share texture indices when all their layers match.
+    pub fn get_atlas_key(&self, albedo_key: &str) -> String {
+        let mut atlas_key = albedo_key.to_string();
+        for layer in MaterialLayer::ALL {
+            if let Some(map_name) = self.get_map(layer) {
+                atlas_key.push_str(&format!("|{}={}", layer.get_name(), map_name));
+            }
+        }
+        atlas_key
+    }
+
+    /// The material flags of a texture with these maps, see `MaterialLayer::get_flag`.
+    pub fn get_flags(&self) -> u32 {
+        MaterialLayer::ALL.iter().filter(|layer| self.get_map(**layer).is_some()).map(|layer| layer.get_flag()).sum()
+    }
+}
+
+impl MaterialLayer {
+    pub const ALL: [MaterialLayer; 3] = [MaterialLayer::Normal, MaterialLayer::RoughnessMetalness, MaterialLayer::Emissive];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            MaterialLayer::Normal => "normal",
+            MaterialLayer::RoughnessMetalness => "roughness_metalness",
+            MaterialLayer::Emissive => "emissive",
+        }
+    }
+
+    /// The bit set in a texture's material flags when its face has this map.
+    pub fn get_flag(&self) -> u32 {
+        1 << (*self as u32)
+    }
+
+    /// What the layer holds where a face has no map: a normal facing straight out, fully rough and
+    /// not metallic, and no emission.
+    pub fn get_default_pixel(&self) -> [u8; 4] {
+        match self {
+            MaterialLayer::Normal => [128, 128, 255, 255],
+            MaterialLayer::RoughnessMetalness => [0, 255, 0, 255],
+            MaterialLayer::Emissive => [0, 0, 0, 255],
+        }
+    }
+}
+
+impl FaceTextureFormat {
+    /// Names the face's frames in the atlas. Faces with the same key share texture indices, so
+    /// blocks added after the atlas was built can reuse its textures.
+    pub fn get_atlas_key(&self) -> String {
+        match self {
+            FaceTextureFormat::Static(texture_name) => texture_name.clone(),
+            FaceTextureFormat::Animated(animated_texture) => {
+                let frames = match &animated_texture.strip {
+                    Some(strip_name) => format!("strip:{}", strip_name),
+                    None => format!("frames:{}", animated_texture.frames.join("+")),
+                };
+                let frame_durations_ms = animated_texture.frame_durations_ms.iter().map(|duration| duration.to_string()).collect::<Vec<String>>();
+                format!("{}@{}", frames, frame_durations_ms.join(","))
+            }
+        }
+    }
+}
+
+impl AnimatedTextureFormat {
+    /// Per-frame durations once the number of frames is known, which for a strip is only after the image is loaded.
+    pub fn get_frame_durations_ms(&self, frame_count: usize) -> Result<Vec<u32>, String> {
+        if self.frames.is_empty() == self.strip.is_none() {
+            return Err(String::from("an animated texture needs exactly one of frames or strip"));
+        }
+        if self.frame_durations_ms.contains(&0) {
+            return Err(String::from("frame durations must be greater than 0"));
+        }
+        match self.frame_durations_ms.len() {
+            1 => Ok(vec![self.frame_durations_ms[0]; frame_count]),
+            n if n == frame_count => Ok(self.frame_durations_ms.clone()),
+            n => Err(format!("{} frame durations given for {} frames", n, frame_count)),
+        }
+    }
+}
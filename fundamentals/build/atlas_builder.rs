@@ -1,87 +1,303 @@
-use formats::formats::texture_format::TextureFormat;
+use formats::formats::texture_format::{FaceMaterialFormat, FaceTextureFormat, MaterialLayer, MipFilter, MipSettingsFormat};
 use ::formats::formats::{block_format::BlockFormat, config_format::ConfigFormat};
-use std::collections::{HashMap, HashSet};
+use ::formats::formats::atlas_manifest_format::{AtlasKeyFormat, AtlasManifestFormat, AtlasTextureFormat};
+use ::formats::formats::atlas_container::{AtlasContainer, AtlasContainerLayer, AtlasContainerTexture, AtlasPixelFormat};
+use ::formats::formats::checksum;
+use ::formats::formats::migration::{self, VersionedFormat};
+use crate::atlas_packer;
+use crate::atlas_report::AtlasReport;
+use crate::mipmaps;
+use image::{Rgba, RgbaImage};
+use std::collections::HashMap;
 use std::io::Write;
 use std::path::Path;
 
 pub struct AtlasBuilder {
     pub atlas_index_width: u32,
     pub atlas_index_height: u32,
-    pub block_string_to_texture_indices: Vec<(String, [usize;6])>,
+    pub texture_key_to_texture_index: Vec<(String, usize)>,
     pub num_textures: usize,
+    pub texture_animations: Vec<TextureAnimation>,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    pub atlas_mip_level_count: u32,
+    pub texture_uv_rects: Vec<[f32; 4]>,
+    pub texture_material_flags: Vec<u32>,
 }
 
 impl AtlasBuilder {
     pub fn build_and_save_atlas(vec_block_format: &Vec<BlockFormat>, config_format: &ConfigFormat) -> Self {
         let mut image_index = 0;
     
-        let blocks = vec_block_format.iter().map(|bf| (bf.block_type.to_string(), &bf.texture)).collect::<Vec<(String, &TextureFormat)>>();
-        let mut block_string_to_texture_indices = Vec::new();
+        // Every face of every state, variant textures included. The runtime block registry looks
+        // faces up by their atlas key, so blocks themselves are not recorded here.
+        let faces = vec_block_format.iter().flat_map(|bf| {
+            (0..bf.get_num_states()).flat_map(|state_index| {
+                let mip_settings = bf.get_state_mip_settings(state_index);
+                bf.get_state_faces(state_index).into_iter().zip(bf.get_state_face_materials(state_index))
+                    .map(|(face, face_material)| (bf.block_type.clone(), face, face_material, mip_settings.clone()))
+                    .collect::<Vec<(String, FaceTextureFormat, FaceMaterialFormat, MipSettingsFormat)>>()
+            }).collect::<Vec<(String, FaceTextureFormat, FaceMaterialFormat, MipSettingsFormat)>>()
+        }).collect::<Vec<(String, FaceTextureFormat, FaceMaterialFormat, MipSettingsFormat)>>();
+        let mut texture_key_to_texture_index = Vec::new();
         let mut texture_string_to_texture_indices = HashMap::new();
+        let mut texture_string_to_mip_settings = HashMap::new();
     
-        let mut set_of_textures = HashSet::new();
+        let mut texture_animations = Vec::new();
+        let mut frames = Vec::new();
+        let mut frame_mip_settings = Vec::new();
+        let mut frame_names = Vec::new();
+        let mut keys = Vec::new();
+        let mut content_to_texture_index = HashMap::new();
+        let mut texture_material_maps: Vec<[Option<RgbaImage>; 3]> = Vec::new();
+        let mut texture_material_flags = Vec::new();
 
-        for (_, block_textures) in blocks.iter() {
-            for block_texture in block_textures.to_vec() {
-                set_of_textures.insert(block_texture);
+        for (block_name, block_texture, face_material, mip_settings) in faces {
+            let texture_key = face_material.get_atlas_key(&block_texture.get_atlas_key());
+            // A texture is stored once, so everything using it has to agree on how it is mipmapped.
+            if let Some((first_block_name, first_mip_settings)) = texture_string_to_mip_settings.get(&texture_key) {
+                if *first_mip_settings != mip_settings {
+                    panic!("{} and {} use texture {} with different mip settings: {:?} and {:?}", first_block_name, block_name, texture_key, first_mip_settings, mip_settings);
+                }
             }
-        }
-
-        let atlas_num_images_width_max = 2_u32.pow((set_of_textures.len() as f32).log(4.0).ceil() as u32); // Calculates the minimum power of 2 square that can fit
-        let altas_num_images_height_max = atlas_num_images_width_max;
-    
-        let mut texture_vec = Vec::new();
-        
-        let mip_level = (config_format.texture_dimension as f32).log2() as usize;
-
-        for (block_name, block_textures) in blocks {
-            let mut block_texture_indices = [0;6];
-            let mut i = 0;
-            for block_texture in block_textures.to_vec() {
-                if !texture_string_to_texture_indices.contains_key(&block_texture) {
-                    texture_string_to_texture_indices.insert(block_texture.clone(), image_index);
-                    let block_texture = format!("../resources/{}", block_texture);
-                    let texture_path = Path::new(&block_texture);
-                    let texture = image::io::Reader::open(texture_path).unwrap().decode().unwrap();
-                    let mut mip_texture_vec = vec![texture.clone()];
-                    for i in 1..mip_level as u32+1 {
-                        let texture_downsized = texture.resize(texture.width() / 2u32.pow(i), texture.height() / 2u32.pow(i), image::imageops::FilterType::Nearest);
-                        mip_texture_vec.push(texture_downsized);
-                    }
-                    block_texture_indices[i] = image_index;
-            
-                    texture_vec.push((image_index, mip_texture_vec));
+            if texture_string_to_texture_indices.contains_key(&texture_key) {
+                continue;
+            }
+            texture_string_to_mip_settings.insert(texture_key.clone(), (block_name.clone(), mip_settings.clone()));
+            let loaded_frames = Self::load_frames(&block_texture).into_iter().map(|texture| texture.to_rgba8()).collect::<Vec<RgbaImage>>();
+            let frame_durations_ms = match &block_texture {
+                FaceTextureFormat::Animated(animated_texture) => Some(animated_texture.get_frame_durations_ms(loaded_frames.len())
+                    .unwrap_or_else(|e| panic!("Invalid animated texture on {}: {}", block_name, e))),
+                FaceTextureFormat::Static(_) => None,
+            };
+            let material_maps = MaterialLayer::ALL.map(|layer| face_material.get_map(layer).map(|map_name| {
+                let map = Self::load_texture(map_name).to_rgba8();
+                if map.dimensions() != loaded_frames[0].dimensions() {
+                    panic!("{}: {} map {} is {}x{} but its albedo frames are {}x{}", block_name, layer.get_name(), map_name, map.width(), map.height(), loaded_frames[0].width(), loaded_frames[0].height());
+                }
+                map
+            }));
+            let content_hash = Self::get_content_hash(&loaded_frames, &material_maps);
+            keys.push(AtlasKeyFormat { name: texture_key.clone(), texture_index: 0, frame_count: loaded_frames.len(), content_hash: format!("{:016x}", content_hash) });
 
-                    image_index += 1;
-                } else {
-                    block_texture_indices[i] = *texture_string_to_texture_indices.get(&block_texture).unwrap();
+            // Identical pixels under another name reuse the textures already stored, unless they are mipmapped or animated differently.
+            let content_key = (content_hash, mip_settings.clone(), frame_durations_ms.clone());
+            if let Some(first_index) = content_to_texture_index.get(&content_key).copied() {
+                if frames[first_index..first_index + loaded_frames.len()] == loaded_frames[..] && texture_material_maps[first_index] == material_maps {
+                    keys.last_mut().unwrap().texture_index = first_index;
+                    texture_string_to_texture_indices.insert(texture_key.clone(), first_index);
+                    texture_key_to_texture_index.push((texture_key, first_index));
+                    continue;
                 }
-                
-                i += 1;
             }
-            block_string_to_texture_indices.push((block_name.clone(), block_texture_indices));
-            
+
+            let first_index = image_index;
+            keys.last_mut().unwrap().texture_index = first_index;
+            content_to_texture_index.insert(content_key, first_index);
+            for (frame, texture) in loaded_frames.into_iter().enumerate() {
+                frames.push(texture);
+                frame_mip_settings.push(mip_settings.clone());
+                texture_material_maps.push(material_maps.clone());
+                texture_material_flags.push(face_material.get_flags());
+                frame_names.push((texture_key.clone(), frame));
+                image_index += 1;
+            }
+            if let Some(frame_durations_ms) = frame_durations_ms {
+                texture_animations.push(TextureAnimation { first_index, frame_durations_ms });
+            }
+            texture_string_to_texture_indices.insert(texture_key.clone(), first_index);
+            texture_key_to_texture_index.push((texture_key, first_index));
         }
+
+        let atlas_num_images_width_max = 2_u32.pow((image_index as f32).log(4.0).ceil() as u32); // Calculates the minimum power of 2 square that can fit every frame
+        let altas_num_images_height_max = atlas_num_images_width_max;
     
-        let num_textures = texture_vec.len();
+        let num_textures = frames.len();
 
-        let mut data_buf: Vec<u8> = Vec::new();
+        let packed_atlas = atlas_packer::pack(&frames, config_format.atlas_padding);
+        let atlas_mip_level_count = packed_atlas.get_mip_level_count(config_format.atlas_padding);
+        let mip_chains = frames.iter().zip(&frame_mip_settings)
+            .map(|(frame, mip_settings)| Self::get_mip_chain(frame, atlas_mip_level_count, mip_settings))
+            .collect::<Vec<Vec<RgbaImage>>>();
+        let albedo_mip_levels = packed_atlas.blit_mip_levels(&mip_chains, config_format.atlas_padding);
+        albedo_mip_levels[0].save("../hello-wgpu/src/atlas.png").expect("Unable to save atlas.png");
 
-        for (_, mip_texture_vec) in texture_vec {
-            for texture in mip_texture_vec {
-                data_buf.extend_from_slice(&texture.to_rgba8().as_flat_samples().as_slice());
-            }
+        // The renderer uploads the atlas from data.atl, which carries the mip levels a PNG can't.
+        let mut atlas_container = AtlasContainer {
+            width: packed_atlas.width,
+            height: packed_atlas.height,
+            mip_level_count: atlas_mip_level_count,
+            textures: frame_names.iter().map(|(name, frame)| AtlasContainerTexture { name: name.clone(), frame: *frame as u32 }).collect(),
+            layers: vec![AtlasContainerLayer {
+                name: String::from("albedo"),
+                pixel_format: AtlasPixelFormat::Rgba8UnormSrgb,
+                data: albedo_mip_levels.iter().flat_map(|level| level.as_raw().iter().copied()).collect(),
+            }],
+        };
+
+        // Every layer is written, with defaults where faces have no map, so lighting can bind all of them once it
+        // exists. Until then the chunk shader only draws the emissive layer, so only that one goes into data.atl.
+        let mut layer_file_names = Vec::new();
+        for layer in MaterialLayer::ALL {
+            let layer_frames = frames.iter().zip(&texture_material_maps).map(|(frame, material_maps)| {
+                material_maps[layer as usize].clone().unwrap_or_else(|| RgbaImage::from_pixel(frame.width(), frame.height(), Rgba(layer.get_default_pixel())))
+            }).collect::<Vec<RgbaImage>>();
+            let layer_image = if layer == MaterialLayer::Emissive {
+                // Emitted light is a color like the albedo, so it is averaged in linear light too.
+                let mip_settings = MipSettingsFormat { filter: MipFilter::Box, linear_space: true, alpha_cutoff: None };
+                let mip_chains = layer_frames.iter()
+                    .map(|layer_frame| Self::get_mip_chain(layer_frame, atlas_mip_level_count, &mip_settings))
+                    .collect::<Vec<Vec<RgbaImage>>>();
+                let mip_levels = packed_atlas.blit_mip_levels(&mip_chains, config_format.atlas_padding);
+                atlas_container.layers.push(AtlasContainerLayer {
+                    name: layer.get_name().to_string(),
+                    pixel_format: AtlasPixelFormat::Rgba8UnormSrgb,
+                    data: mip_levels.iter().flat_map(|level| level.as_raw().iter().copied()).collect(),
+                });
+                mip_levels[0].clone()
+            } else {
+                packed_atlas.blit_layer(&layer_frames, config_format.atlas_padding)
+            };
+            let layer_file_name = format!("atlas_{}.png", layer.get_name());
+            layer_image.save(format!("../hello-wgpu/src/{}", layer_file_name))
+                .unwrap_or_else(|e| panic!("Unable to save {}: {}", layer_file_name, e));
+            layer_file_names.push(layer_file_name);
         }
 
         let mut data_atl = std::fs::File::create("../hello-wgpu/src/data.atl").expect("Unable to create file");
-        data_atl.write_all(&data_buf).expect("Unable to create file");
+        data_atl.write_all(&atlas_container.to_bytes()).expect("Unable to create file");
+
+        let texture_uv_rects = packed_atlas.rects.iter().map(|rect| [
+            rect.x as f32 / packed_atlas.width as f32,
+            rect.y as f32 / packed_atlas.height as f32,
+            (rect.x + rect.width) as f32 / packed_atlas.width as f32,
+            (rect.y + rect.height) as f32 / packed_atlas.height as f32,
+        ]).collect::<Vec<[f32; 4]>>();
+        let atlas_manifest = AtlasManifestFormat {
+            format_version: AtlasManifestFormat::CURRENT_VERSION,
+            width: packed_atlas.width,
+            height: packed_atlas.height,
+            padding: config_format.atlas_padding,
+            textures: packed_atlas.rects.iter().zip(&texture_uv_rects).zip(frame_names).enumerate().map(|(texture_index, ((rect, uv_rect), (name, frame)))| AtlasTextureFormat {
+                texture_index,
+                name,
+                frame,
+                x: rect.x,
+                y: rect.y,
+                width: rect.width,
+                height: rect.height,
+                u_min: uv_rect[0],
+                v_min: uv_rect[1],
+                u_max: uv_rect[2],
+                v_max: uv_rect[3],
+                material_flags: texture_material_flags[texture_index],
+            }).collect(),
+            keys,
+            layers: layer_file_names,
+        };
+
+        // The manifest of the previous build, if it is still readable, is what the report compares against.
+        let previous_atlas_manifest = std::fs::read_to_string("../hello-wgpu/src/atlas.json").ok()
+            .and_then(|atlas_json| migration::migrate_str::<AtlasManifestFormat>(&atlas_json).ok())
+            .map(|migrated| migrated.value);
+        let atlas_report = AtlasReport::new(previous_atlas_manifest.as_ref(), &atlas_manifest);
+        std::fs::write("../hello-wgpu/src/atlas-report.txt", atlas_report.to_text()).expect("Unable to create atlas-report.txt");
+        if atlas_report.has_changes() {
+            println!("cargo:warning=Atlas changed: {}, see hello-wgpu/src/atlas-report.txt", atlas_report.get_summary());
+        }
+
+        std::fs::write("../hello-wgpu/src/atlas.json", serde_json::to_string_pretty(&atlas_manifest).unwrap()).expect("Unable to create atlas.json");
 
         AtlasBuilder { 
             atlas_index_height: altas_num_images_height_max,
             atlas_index_width: atlas_num_images_width_max,
-            block_string_to_texture_indices,
-            num_textures
+            texture_key_to_texture_index,
+            num_textures,
+            texture_animations,
+            atlas_width: packed_atlas.width,
+            atlas_height: packed_atlas.height,
+            atlas_mip_level_count,
+            texture_uv_rects,
+            texture_material_flags,
         }
     }
+
+    fn load_frames(block_texture: &FaceTextureFormat) -> Vec<image::DynamicImage> {
+        match block_texture {
+            FaceTextureFormat::Static(texture_name) => vec![Self::load_texture(texture_name)],
+            FaceTextureFormat::Animated(animated_texture) => match &animated_texture.strip {
+                Some(strip_name) => {
+                    let strip = Self::load_texture(strip_name);
+                    let frame_dimension = strip.width();
+                    if strip.height() % frame_dimension != 0 {
+                        panic!("Animation strip {} is {}x{}, its height must be a multiple of its width", strip_name, strip.width(), strip.height());
+                    }
+                    (0..strip.height() / frame_dimension).map(|frame| strip.crop_imm(0, frame * frame_dimension, frame_dimension, frame_dimension)).collect()
+                }
+                None => animated_texture.frames.iter().map(|frame_name| Self::load_texture(frame_name)).collect(),
+            },
+        }
+    }
+
+    fn get_mip_chain(texture: &RgbaImage, mip_level_count: u32, mip_settings: &MipSettingsFormat) -> Vec<RgbaImage> {
+        let mut mip_chain = vec![texture.clone()];
+        mip_chain.extend(mipmaps::generate_mip_levels(texture, mip_level_count - 1, mip_settings));
+        mip_chain
+    }
+
+    fn get_content_hash(frames: &Vec<RgbaImage>, material_maps: &[Option<RgbaImage>; 3]) -> u64 {
+        let mut bytes = Vec::new();
+        for frame in frames {
+            bytes.extend_from_slice(&frame.width().to_le_bytes());
+            bytes.extend_from_slice(&frame.height().to_le_bytes());
+            bytes.extend_from_slice(frame.as_raw());
+        }
+        // Textures without maps hash their frames alone, so their hashes stay what they were before there were maps.
+        if material_maps.iter().any(|material_map| material_map.is_some()) {
+            for material_map in material_maps {
+                match material_map {
+                    Some(material_map) => {
+                        bytes.push(1);
+                        bytes.extend_from_slice(material_map.as_raw());
+                    }
+                    None => bytes.push(0),
+                }
+            }
+        }
+        checksum::fnv1a_64(&bytes)
+    }
+
+    fn load_texture(texture_name: &str) -> image::DynamicImage {
+        let block_texture = format!("../resources/{}", texture_name);
+        let texture_path = Path::new(&block_texture);
+        image::io::Reader::open(texture_path).unwrap().decode().unwrap()
+    }
+
+    /// The (first frame, frame count) of the animation each texture index belongs to; static textures are their own single frame.
+    pub fn get_texture_frame_ranges(&self) -> Vec<(usize, usize)> {
+        let mut texture_frame_ranges = (0..self.num_textures).map(|texture_index| (texture_index, 1)).collect::<Vec<(usize, usize)>>();
+        for animation in &self.texture_animations {
+            let frame_count = animation.frame_durations_ms.len();
+            for texture_index in animation.first_index..animation.first_index + frame_count {
+                texture_frame_ranges[texture_index] = (animation.first_index, frame_count);
+            }
+        }
+        texture_frame_ranges
+    }
+
+    pub fn get_texture_frame_durations_ms(&self) -> Vec<Vec<u32>> {
+        let mut texture_frame_durations_ms = vec![Vec::new(); self.num_textures];
+        for animation in &self.texture_animations {
+            for texture_index in animation.first_index..animation.first_index + animation.frame_durations_ms.len() {
+                texture_frame_durations_ms[texture_index] = animation.frame_durations_ms.clone();
+            }
+        }
+        texture_frame_durations_ms
+    }
+}
+
+pub struct TextureAnimation {
+    pub first_index: usize,
+    pub frame_durations_ms: Vec<u32>,
 }
 
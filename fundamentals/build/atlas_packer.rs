@@ -0,0 +1,99 @@
+use image::RgbaImage;
+
+pub struct PackedRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct PackedAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub rects: Vec<PackedRect>,
+}
+
+/// Packs the textures onto shelves, tallest first. The atlas has power of two sides and is widened
+/// until it is no taller than it is wide. Each texture gets `padding` pixels of gutter on every side,
+/// filled by repeating its edge pixels, so filtering and mip levels down to a gutter of one pixel
+/// never pick up a neighbouring texture.
+pub fn pack(textures: &Vec<RgbaImage>, padding: u32) -> PackedAtlas {
+    let mut order = (0..textures.len()).collect::<Vec<usize>>();
+    order.sort_by_key(|i| (std::cmp::Reverse(textures[*i].height()), std::cmp::Reverse(textures[*i].width())));
+
+    let padded_area = textures.iter().map(|texture| ((texture.width() + 2*padding) * (texture.height() + 2*padding)) as u64).sum::<u64>();
+    let widest = textures.iter().map(|texture| texture.width() + 2*padding).max().unwrap_or(1);
+    let mut width = ((padded_area as f64).sqrt().ceil() as u32).max(widest).next_power_of_two();
+
+    loop {
+        let (rects, used_height) = place_on_shelves(textures, &order, padding, width);
+        let height = used_height.max(1).next_power_of_two();
+        if height <= width {
+            return PackedAtlas { width, height, rects };
+        }
+        width *= 2;
+    }
+}
+
+impl PackedAtlas {
+    /// Draws another set of textures, such as a material layer, with the same layout as the albedo atlas.
+    pub fn blit_layer(&self, textures: &Vec<RgbaImage>, padding: u32) -> RgbaImage {
+        blit(textures, &self.rects, padding, self.width, self.height)
+    }
+
+    /// Mip levels stop before a texture's gutter drops below one pixel or a rectangle no longer
+    /// halves evenly, since past that point a level would blend neighbouring textures.
+    pub fn get_mip_level_count(&self, padding: u32) -> u32 {
+        let alignment = self.rects.iter().flat_map(|rect| [rect.x, rect.y, rect.width, rect.height])
+            .fold(padding, |alignment, value| alignment | value);
+        alignment.trailing_zeros().min(padding.checked_ilog2().unwrap_or(0)) + 1
+    }
+
+    /// Draws every mip level of a layer, largest first. `mip_chains` holds the levels of each texture
+    /// in texture index order, so a layer can use mip settings per texture.
+    pub fn blit_mip_levels(&self, mip_chains: &Vec<Vec<RgbaImage>>, padding: u32) -> Vec<RgbaImage> {
+        let mip_level_count = self.get_mip_level_count(padding);
+        (0..mip_level_count as usize).map(|level| {
+            let textures = mip_chains.iter().map(|mip_chain| mip_chain[level].clone()).collect::<Vec<RgbaImage>>();
+            let rects = self.rects.iter().map(|rect| PackedRect { x: rect.x >> level, y: rect.y >> level, width: rect.width >> level, height: rect.height >> level }).collect::<Vec<PackedRect>>();
+            blit(&textures, &rects, padding >> level, (self.width >> level).max(1), (self.height >> level).max(1))
+        }).collect()
+    }
+}
+
+// Returns the unpadded rectangle of every texture, by texture index, and the height the shelves use.
+fn place_on_shelves(textures: &Vec<RgbaImage>, order: &Vec<usize>, padding: u32, width: u32) -> (Vec<PackedRect>, u32) {
+    let mut rects = textures.iter().map(|_| PackedRect { x: 0, y: 0, width: 0, height: 0 }).collect::<Vec<PackedRect>>();
+    let mut shelf_x = 0;
+    let mut shelf_y = 0;
+    let mut shelf_height = 0;
+    for i in order {
+        let texture = &textures[*i];
+        let padded_width = texture.width() + 2*padding;
+        let padded_height = texture.height() + 2*padding;
+        if shelf_x + padded_width > width {
+            shelf_y += shelf_height;
+            shelf_x = 0;
+            shelf_height = 0;
+        }
+        rects[*i] = PackedRect { x: shelf_x + padding, y: shelf_y + padding, width: texture.width(), height: texture.height() };
+        shelf_x += padded_width;
+        shelf_height = shelf_height.max(padded_height);
+    }
+    (rects, shelf_y + shelf_height)
+}
+
+fn blit(textures: &Vec<RgbaImage>, rects: &Vec<PackedRect>, padding: u32, width: u32, height: u32) -> RgbaImage {
+    let mut atlas = RgbaImage::new(width, height);
+    for (texture, rect) in textures.iter().zip(rects) {
+        let padding = padding as i64;
+        for y in -padding..rect.height as i64 + padding {
+            for x in -padding..rect.width as i64 + padding {
+                let source_x = x.clamp(0, rect.width as i64 - 1) as u32;
+                let source_y = y.clamp(0, rect.height as i64 - 1) as u32;
+                atlas.put_pixel((rect.x as i64 + x) as u32, (rect.y as i64 + y) as u32, *texture.get_pixel(source_x, source_y));
+            }
+        }
+    }
+    atlas
+}
@@ -0,0 +1,105 @@
+use formats::formats::atlas_manifest_format::AtlasManifestFormat;
+use std::collections::BTreeMap;
+
+/// What changed in the atlas since the previous build, written next to atlas.json so texture
+/// changes can be checked without running the app.
+pub struct AtlasReport {
+    pub has_previous: bool,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// (atlas key, key whose textures it shares)
+    pub duplicates: Vec<(String, String)>,
+    pub width: u32,
+    pub height: u32,
+    pub num_textures: usize,
+    pub texture_fill: f32,
+    pub padded_fill: f32,
+}
+
+impl AtlasReport {
+    pub fn new(previous: Option<&AtlasManifestFormat>, current: &AtlasManifestFormat) -> Self {
+        // Manifests from before version 2 have no keys to compare against.
+        let previous = previous.filter(|previous| !previous.keys.is_empty() || previous.textures.is_empty());
+        let current_hashes = current.keys.iter().map(|key| (key.name.as_str(), key.content_hash.as_str())).collect::<BTreeMap<&str, &str>>();
+        let previous_hashes = previous.map(|previous| previous.keys.iter().map(|key| (key.name.as_str(), key.content_hash.as_str())).collect::<BTreeMap<&str, &str>>())
+            .unwrap_or_default();
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        for (name, content_hash) in &current_hashes {
+            match previous_hashes.get(name) {
+                None => added.push(name.to_string()),
+                Some(previous_hash) if previous_hash != content_hash => changed.push(name.to_string()),
+                Some(_) => {}
+            }
+        }
+        let removed = previous_hashes.keys().filter(|name| !current_hashes.contains_key(*name)).map(|name| name.to_string()).collect();
+
+        // Keys are listed in build order, so the first key of a texture index is the one that stored it.
+        let mut texture_index_to_key = BTreeMap::new();
+        let mut duplicates = Vec::new();
+        for key in &current.keys {
+            match texture_index_to_key.get(&key.texture_index) {
+                Some(first_key) => duplicates.push((key.name.clone(), String::clone(first_key))),
+                None => { texture_index_to_key.insert(key.texture_index, key.name.clone()); }
+            }
+        }
+
+        // In u64, as the areas of a large atlas don't fit in a u32.
+        let atlas_area = (current.width as u64 * current.height as u64) as f64;
+        let texture_area = current.textures.iter().map(|texture| texture.width as u64 * texture.height as u64).sum::<u64>() as f64;
+        let padded_area = current.textures.iter().map(|texture| (texture.width + 2*current.padding) as u64 * (texture.height + 2*current.padding) as u64).sum::<u64>() as f64;
+
+        AtlasReport {
+            has_previous: previous.is_some(),
+            added,
+            removed,
+            changed,
+            duplicates,
+            width: current.width,
+            height: current.height,
+            num_textures: current.textures.len(),
+            texture_fill: (texture_area / atlas_area) as f32,
+            padded_fill: (padded_area / atlas_area) as f32,
+        }
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.has_previous && !(self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty())
+    }
+
+    pub fn get_summary(&self) -> String {
+        format!("{} added, {} removed, {} changed", self.added.len(), self.removed.len(), self.changed.len())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("Atlas: {}x{}, {} textures", self.width, self.height, self.num_textures),
+            format!("Fill: {:.1}% textures, {:.1}% with padding", self.texture_fill * 100.0, self.padded_fill * 100.0),
+            String::new(),
+        ];
+
+        if self.has_previous {
+            lines.push(format!("Since the previous build: {}", self.get_summary()));
+            for (label, names) in [("Added", &self.added), ("Removed", &self.removed), ("Changed", &self.changed)] {
+                for name in names {
+                    lines.push(format!("  {}: {}", label, name));
+                }
+            }
+        } else {
+            lines.push(String::from("No previous atlas.json with content hashes to compare against."));
+        }
+
+        if !self.duplicates.is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Identical textures stored once:"));
+            for (name, first_name) in &self.duplicates {
+                lines.push(format!("  {} uses the textures of {}", name, first_name));
+            }
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
@@ -3,6 +3,7 @@ use std::io::{BufWriter, Write};
 use std::path::Path;
 use formats::formats::config_format::{self, ConfigFormat};
 use formats::formats::controls_format::ControlsFormat;
+use formats::formats::texture_format::MaterialLayer;
 
 pub fn generate_consts(config_format: &ConfigFormat, consts_model: &ConstsModel, controls_format: &ControlsFormat) {
     let consts_path = Path::new("src/consts.rs");
@@ -14,22 +15,17 @@ pub fn generate_consts(config_format: &ConfigFormat, consts_model: &ConstsModel,
     let num_vertices_in_bucket = (config_format.chunk_dimension as u32)*(config_format.chunk_dimension as u32);
     let num_buckets_per_chunk = (config_format.chunk_dimension as u32)*(config_format.chunk_dimension as u32)*(config_format.chunk_dimension as u32) * 2 / num_vertices_in_bucket;
 
-    let mip_level = (config_format.texture_dimension as f32).log2() as u32;
-    let texture_length_with_mipmaps = generate_texture_length_with_mipmap_level(mip_level, config_format.texture_dimension);
-
-    if config_format.chunk_dimension % 2 != 0 {
-        panic!("Chunk dimension must be a multiple of 2, as index buffer writes must be a multiple of 4.")
-    }
-
     let bits_per_subvoxel_palette = get_palette_type_size(config_format.max_subvoxel_colors);
+    let block_id_size = get_block_id_size(config_format.max_amount_of_blocktypes);
     writeln!(
         &mut consts_file,
         "{}",
         [
-            String::from("use crate::world_position::WorldPosition;"),
-            String::from("use winit::keyboard::KeyCode;\n"),
-            format!("pub const NUM_BLOCK_TYPES: u16 = {};", consts_model.num_block_types),
-            format!("pub const NUM_ADDITIONAL_THREADS: usize = {};", generate_num_threads(&config_format)),
+            String::from("use crate::world_position::WorldPosition;\n"),
+            format!("pub const MAX_AMOUNT_OF_BLOCKTYPES: u32 = {};", config_format.max_amount_of_blocktypes),
+            String::from("// Block states share the ID space, so both fit max_amount_of_blocktypes."),
+            format!("pub type BlockTypeSize = u{};", block_id_size),
+            String::from("pub type BlockStateSize = BlockTypeSize;"),
             format!("pub const RENDER_DISTANCE: usize = {};", config_format.render_radius),
             format!("pub const FOV_DISTANCE: usize = {};", config_format.render_radius*config_format.chunk_dimension as usize),
             format!("pub const CHUNK_DIMENSION: i32 = {};", config_format.chunk_dimension),
@@ -54,34 +50,13 @@ pub fn generate_consts(config_format: &ConfigFormat, consts_model: &ConstsModel,
             format!("pub const NUM_BUCKETS_PER_CHUNK: usize = {};", num_buckets_per_chunk),
             format!("pub const NUM_BUCKETS_PER_SIDE: u32 = {};", num_buckets_per_chunk / 6),
             format!("pub const NUM_BUCKETS: usize = {};", num_buckets_per_chunk * num_chunks_around_player as u32),
-            format!("pub const MESH_METHOD: &str = \"{}\";", config_format.mesh_method),
-            format!("pub const CHUNK_GENERATION_METHOD: &str = \"{}\";", config_format.chunk_generation_method),
-            format!("pub const PERLIN_POSITIVE_THRESHOLD: f64 = {};", config_format.chunk_generation_options.perlin_positive_threshold),
-            format!("pub const PERLIN_NEGATIVE_THRESHOLD: f64 = {};", config_format.chunk_generation_options.perlin_negative_threshold),
-            format!("pub const PERLIN_SCALE_FACTOR: f64 = {};", config_format.chunk_generation_options.perlin_scale_factor),
             String::new(),
-            format!("pub const MIP_LEVEL: u32 = {};", mip_level),
-            format!("pub const TEXTURE_LENGTH_WITH_MIPMAPS: usize = {};", texture_length_with_mipmaps),
+            generate_texture_animation_tables(consts_model),
+            generate_atlas_texture_indices(consts_model),
             String::new(),
-            format!("pub const UP_KEY: KeyCode = {};", controls_format.up),
-            format!("pub const DOWN_KEY: KeyCode = {};", controls_format.down),
-            format!("pub const LEFT_KEY: KeyCode = {};", controls_format.left),
-            format!("pub const RIGHT_KEY: KeyCode = {};", controls_format.right),
-            format!("pub const FORWARD_KEY: KeyCode = {};", controls_format.forward),
-            format!("pub const BACKWARD_KEY: KeyCode = {};", controls_format.backward),
             format!("pub const MOUSE_SENSITIVITY_THRESHOLD: f64 = {:.1};", controls_format.mouse_sensitivity_threshold),
             format!("pub const MOUSE_SENSITIVITY: f32 = {:.1};", controls_format.mouse_sensitivity),
             String::new(),
-            format!("pub const NUM_TASKS_PER_THREAD: usize = {};", config_format.num_tasks_per_thread),
-            format!("pub const GENERATE_CHUNK_PRIORITY: u32 = {};", config_format.task_priorities.chunk),
-            format!("pub const UPDATE_CHUNK_PADDING_X_PRIORITY: u32 = {};", config_format.task_priorities.update_chunk_padding_x),
-            format!("pub const UPDATE_CHUNK_PADDING_Y_PRIORITY: u32 = {};", config_format.task_priorities.update_chunk_padding_y),
-            format!("pub const UPDATE_CHUNK_PADDING_Z_PRIORITY: u32 = {};", config_format.task_priorities.update_chunk_padding_z),
-            format!("pub const GENERATE_MESH_PRIORITY: u32 = {};", config_format.task_priorities.mesh),
-            format!("pub const GENERATE_MESH_SIDE_PRIORITY: u32 = {};", config_format.task_priorities.mesh_side),
-            String::new(),
-            format!("pub const MOVEMENT_SPEED: f32 = {:.1};", config_format.movement_speed),
-            format!("pub const MIN_MEMORY_USAGE_MB: u32 = {};", config_format.min_memory_mb),
             format!("pub const MAX_MEMORY_USAGE_MB: u32 = {};", config_format.max_memory_mb),
             format!("pub const MAX_SUBVOXEL_OBJECTS: u64 = {};", config_format.max_subvoxel_objects),
             format!("pub const MAX_SUBVOXELS_IN_MODELS: u64 = {};", config_format.max_subvoxels_in_models),
@@ -97,6 +72,16 @@ pub fn generate_consts(config_format: &ConfigFormat, consts_model: &ConstsModel,
     ).unwrap();
 }
 
+/// Width of the integer that holds every ID below `max_amount_of_blocktypes`.
+pub fn get_block_id_size(max_amount_of_blocktypes: u32) -> u8 {
+    if max_amount_of_blocktypes <= 256 {
+        return 8;
+    } else if max_amount_of_blocktypes <= 65536 {
+        return 16;
+    }
+    32
+}
+
 fn get_palette_type_size(num_colors: usize) -> u8 {
     if num_colors < 256 {
         return 8;
@@ -110,13 +95,40 @@ fn get_palette_type_size(num_colors: usize) -> u8 {
     return 128;
 }
 
-fn generate_texture_length_with_mipmap_level(mip_level: u32, texture_dimension: u32) -> usize {
-    let mut length_sum = 0;
-    for mip in 0..mip_level+1 {
-        length_sum += texture_dimension * texture_dimension / 4_u32.pow(mip as u32);
-    }
+fn generate_texture_animation_tables(consts_model: &ConstsModel) -> String {
+    let frame_ranges = consts_model.texture_frame_ranges.iter().map(|(first, count)| format!("({}, {})", first, count)).collect::<Vec<String>>();
+    let frame_durations = consts_model.texture_frame_durations_ms.iter().map(|durations| format!("&{:?}", durations)).collect::<Vec<String>>();
+    [
+    "// (first frame, frame count) of the animation each texture index belongs to. Static textures are a single frame.",
+    "// The chunk shader picks the frame to show from these and its time uniform.",
+    format!("pub const TEXTURE_FRAME_RANGES: [(usize, usize); NUM_TEXTURES] = [{}];", frame_ranges.join(", ")).as_str(),
+    format!("pub const TEXTURE_FRAME_DURATIONS_MS: [&[u32]; NUM_TEXTURES] = [{}];", frame_durations.join(", ")).as_str(),
+    ].join("\n")
+}
+
+// The block registry resolves block faces against this table when it loads data/blocks.json.
+fn generate_atlas_texture_indices(consts_model: &ConstsModel) -> String {
+    let entries = consts_model.texture_key_to_texture_index.iter().map(|(texture_key, texture_index)| format!("    ({:?}, {}),", texture_key, texture_index)).collect::<Vec<String>>();
+    let uv_rects = consts_model.texture_uv_rects.iter().map(|uv_rect| format!("    {:?},", uv_rect)).collect::<Vec<String>>();
+    [
+    format!("pub const ATLAS_TEXTURE_INDICES: [(&str, usize); {}] = [", entries.len()).as_str(),
+    entries.join("\n").as_str(),
+    "];",
+    format!("pub const ATLAS_WIDTH: u32 = {};", consts_model.atlas_width).as_str(),
+    format!("pub const ATLAS_HEIGHT: u32 = {};", consts_model.atlas_height).as_str(),
+    format!("pub const ATLAS_MIP_LEVEL_COUNT: u32 = {};", consts_model.atlas_mip_level_count).as_str(),
+    "// (u_min, v_min, u_max, v_max) of every texture index in the packed atlas, excluding its padding.",
+    "pub const ATLAS_UV_RECTS: [[f32; 4]; NUM_TEXTURES] = [",
+    uv_rects.join("\n").as_str(),
+    "];",
+    "// Material layers are packed like the albedo atlas; a texture's flags tell which of them hold a map rather than the default.",
+    generate_material_layer_flags().as_str(),
+    format!("pub const ATLAS_MATERIAL_FLAGS: [u32; NUM_TEXTURES] = {:?};", consts_model.texture_material_flags).as_str(),
+    ].join("\n")
+}
 
-    length_sum as usize
+fn generate_material_layer_flags() -> String {
+    MaterialLayer::ALL.iter().map(|layer| format!("pub const MATERIAL_{}: u32 = {};", layer.get_name().to_uppercase(), layer.get_flag())).collect::<Vec<String>>().join("\n")
 }
 
 fn generate_chunk_pos_around_player_fn(config_format: &ConfigFormat) -> Vec<(i32, i32, i32)> {
@@ -206,17 +218,16 @@ fn generate_string_from_position_offsets(vec_of_position_offsets: Vec<(i32,i32,i
     ].join("\n")
 }
 
-fn generate_num_threads(cf: &ConfigFormat) -> usize {
-    if cf.use_all_system_threads {
-        num_cpus::get() - 1
-    } else {
-        cf.num_additional_threads_specified
-    }
-}
-
 pub struct ConstsModel {
-    pub num_block_types: u16,
     pub atlas_max_num_images_width: u32,
     pub atlas_max_num_images_height: u32,
     pub num_textures: usize,
+    pub texture_frame_ranges: Vec<(usize, usize)>,
+    pub texture_frame_durations_ms: Vec<Vec<u32>>,
+    pub texture_key_to_texture_index: Vec<(String, usize)>,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    pub atlas_mip_level_count: u32,
+    pub texture_uv_rects: Vec<[f32; 4]>,
+    pub texture_material_flags: Vec<u32>,
 }
\ No newline at end of file
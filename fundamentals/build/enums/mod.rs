@@ -1,6 +0,0 @@
-use formats::formats::block_format::BlockFormat;
-mod block_type;
-
-pub fn build_enums(block_type_enum_values: &Vec<BlockFormat>) {
-    block_type::build_enum(block_type_enum_values);
-}
\ No newline at end of file
@@ -1,32 +1,87 @@
 use ::formats::formats::{config_format::ConfigFormat, block_format::BlockFormat, controls_format::ControlsFormat};
-use serde_json;
+use ::formats::formats::config_layers::{self, ConfigLayers, PROFILE_ENV_VAR};
+use ::formats::formats::migration;
+use ::formats::formats::block_replacement_format::BlockReplacementFormat;
+use std::path::Path;
 mod atlas_builder;
-mod string_to_type_dict_builders;
-mod enums;
+mod atlas_packer;
+mod atlas_report;
+mod mipmaps;
 mod consts;
 
 fn main() {
     let blocks_json = std::fs::read_to_string("../data/blocks.json").unwrap();
-    let vec_block_format: Vec<BlockFormat> = serde_json::from_str(&blocks_json).unwrap();
+    let migrated_blocks = migration::migrate_vec_str::<BlockFormat>(&blocks_json).unwrap_or_else(|e| panic!("data/blocks.json: {}", e));
+    let vec_block_format: Vec<BlockFormat> = migrated_blocks.value;
+    for block_format in &vec_block_format {
+        if let Err(e) = block_format.validate() {
+            panic!("Invalid data/blocks.json: {}", e);
+        }
+    }
 
-    let config_json = std::fs::read_to_string("../data/config.json").unwrap();
-    let config_format: ConfigFormat = serde_json::from_str(&config_json).unwrap();
+    let block_replacement_json = std::fs::read_to_string("../data/block-replacement.json").unwrap();
+    let migrated_block_replacements = migration::migrate_str::<BlockReplacementFormat>(&block_replacement_json).unwrap_or_else(|e| panic!("data/block-replacement.json: {}", e));
+    let block_replacements = migrated_block_replacements.value;
+    let mut current_names = vec![String::from("AIR")];
+    for block_format in &vec_block_format {
+        current_names.push(block_format.block_type.clone());
+        current_names.extend(block_format.get_state_names());
+    }
+    if let Err(errors) = block_replacements.validate(|name| current_names.iter().any(|current_name| current_name == name)) {
+        panic!("Invalid data/block-replacement.json:\n{}", errors.join("\n"));
+    }
+    for name in block_replacements.replacements.keys().filter(|name| current_names.contains(name)) {
+        println!("cargo:warning=data/block-replacement.json: {} is a current block, so its replacement rule is never used", name);
+    }
 
-    let controls_json = std::fs::read_to_string("../data/controls.json").unwrap();
-    let controls_format: ControlsFormat = serde_json::from_str(&controls_json).unwrap();
+    let profile = std::env::var(PROFILE_ENV_VAR).ok();
+    let config_layers = ConfigLayers::load(Path::new("../data/config.json"), profile.as_deref(), std::env::vars())
+        .unwrap_or_else(|e| panic!("{}", e));
+    let config_format: ConfigFormat = config_layers.to_config_format().unwrap_or_else(|e| panic!("{}", e));
+    if let Err(errors) = config_format.validate(consts::get_block_id_size(config_format.max_amount_of_blocktypes) as u32) {
+        panic!("Invalid config:\n{}", errors.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n"));
+    }
+    // AIR takes state 0.
+    let num_block_states = 1 + vec_block_format.iter().map(|block_format| block_format.get_num_states()).sum::<usize>();
+    if num_block_states as u64 > config_format.max_amount_of_blocktypes as u64 {
+        panic!("data/blocks.json defines {} block states, more than max_amount_of_blocktypes {}", num_block_states, config_format.max_amount_of_blocktypes);
+    }
 
-    let atlas_builder = atlas_builder::AtlasBuilder::build_and_save_atlas(&vec_block_format, &config_format);
+    // Declaring any rerun-if directive replaces cargo's default of rerunning on package changes,
+    // so the inputs outside the package are listed alongside the config overrides.
+    println!("cargo:rerun-if-changed=build");
+    println!("cargo:rerun-if-changed=../data");
+    println!("cargo:rerun-if-changed=../resources");
+    println!("cargo:rerun-if-env-changed={}", PROFILE_ENV_VAR);
+    for field_path in config_layers.field_paths() {
+        println!("cargo:rerun-if-env-changed={}", config_layers::env_var_name(field_path));
+    }
 
-    enums::build_enums(&vec_block_format);
+    let controls_json = std::fs::read_to_string("../data/controls.json").unwrap();
+    let migrated_controls = migration::migrate_str::<ControlsFormat>(&controls_json).unwrap_or_else(|e| panic!("data/controls.json: {}", e));
+    let controls_format: ControlsFormat = migrated_controls.value;
+    if let Err(errors) = controls_format.parse_bindings::<winit::keyboard::KeyCode, winit::event::MouseButton>() {
+        panic!("Invalid data/controls.json:\n{}", errors.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n"));
+    }
 
-    string_to_type_dict_builders::build_string_to_type_dictionaries(&vec_block_format, &atlas_builder.block_string_to_texture_indices);
+    for warning in migrated_blocks.warnings.iter().chain(&migrated_block_replacements.warnings).chain(config_layers.warnings()).chain(migrated_controls.warnings.iter()) {
+        println!("cargo:warning={}", warning);
+    }
+
+    let atlas_builder = atlas_builder::AtlasBuilder::build_and_save_atlas(&vec_block_format, &config_format);
 
-    let num_block_types = (vec_block_format.len()+1) as u16;
     let consts_model = consts::ConstsModel {
-        num_block_types,
         atlas_max_num_images_height: atlas_builder.atlas_index_height,
         atlas_max_num_images_width: atlas_builder.atlas_index_width,
         num_textures: atlas_builder.num_textures,
+        texture_frame_ranges: atlas_builder.get_texture_frame_ranges(),
+        texture_frame_durations_ms: atlas_builder.get_texture_frame_durations_ms(),
+        texture_key_to_texture_index: atlas_builder.texture_key_to_texture_index,
+        atlas_width: atlas_builder.atlas_width,
+        atlas_height: atlas_builder.atlas_height,
+        atlas_mip_level_count: atlas_builder.atlas_mip_level_count,
+        texture_uv_rects: atlas_builder.texture_uv_rects,
+        texture_material_flags: atlas_builder.texture_material_flags,
     };
     consts::generate_consts(&config_format, &consts_model, &controls_format);
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+use formats::formats::texture_format::{MipFilter, MipSettingsFormat};
+use image::{imageops, Rgba, Rgba32FImage, RgbaImage};
+
+/// Levels 1 to `mip_level` of `texture`, each half the size of the one before.
+pub fn generate_mip_levels(texture: &RgbaImage, mip_level: u32, mip_settings: &MipSettingsFormat) -> Vec<RgbaImage> {
+    let base = to_working_space(texture, mip_settings.linear_space);
+    let alpha_cutoff = mip_settings.alpha_cutoff.map(|alpha_cutoff| alpha_cutoff as f32 / 255.0);
+    let base_coverage = alpha_cutoff.map(|alpha_cutoff| get_alpha_coverage(&base, alpha_cutoff, 1.0));
+
+    let mut mip_levels = Vec::new();
+    let mut previous_level = base.clone();
+    for i in 1..mip_level+1 {
+        let width = (texture.width() >> i).max(1);
+        let height = (texture.height() >> i).max(1);
+        // Box halves the previous level; the wider filters resample the full size texture so their support scales with the level.
+        let mut level = match mip_settings.filter {
+            MipFilter::Box => box_downsample(&previous_level, width, height),
+            MipFilter::Triangle => imageops::resize(&base, width, height, imageops::FilterType::Triangle),
+            MipFilter::Lanczos => imageops::resize(&base, width, height, imageops::FilterType::Lanczos3),
+        };
+        previous_level = level.clone();
+        if let (Some(alpha_cutoff), Some(base_coverage)) = (alpha_cutoff, base_coverage) {
+            preserve_alpha_coverage(&mut level, alpha_cutoff, base_coverage);
+        }
+        mip_levels.push(from_working_space(&level, mip_settings.linear_space));
+    }
+    mip_levels
+}
+
+fn box_downsample(image: &Rgba32FImage, width: u32, height: u32) -> Rgba32FImage {
+    Rgba32FImage::from_fn(width, height, |x, y| {
+        let mut sum = [0.0; 4];
+        for (source_x, source_y) in [(2*x, 2*y), (2*x+1, 2*y), (2*x, 2*y+1), (2*x+1, 2*y+1)] {
+            let pixel = image.get_pixel(source_x.min(image.width() - 1), source_y.min(image.height() - 1));
+            for c in 0..4 {
+                sum[c] += pixel[c] / 4.0;
+            }
+        }
+        Rgba(sum)
+    })
+}
+
+fn get_alpha_coverage(image: &Rgba32FImage, alpha_cutoff: f32, alpha_scale: f32) -> f32 {
+    let covered = image.pixels().filter(|pixel| (pixel[3] * alpha_scale).min(1.0) >= alpha_cutoff).count();
+    covered as f32 / (image.width() * image.height()) as f32
+}
+
+// Coverage only grows with the scale, so a bisection finds the scale that matches the full size texture.
+fn preserve_alpha_coverage(image: &mut Rgba32FImage, alpha_cutoff: f32, base_coverage: f32) {
+    let mut low = 0.0;
+    let mut high = 255.0;
+    for _ in 0..24 {
+        let middle = (low + high) / 2.0;
+        if get_alpha_coverage(image, alpha_cutoff, middle) < base_coverage {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    for pixel in image.pixels_mut() {
+        pixel[3] = (pixel[3] * high).min(1.0);
+    }
+}
+
+fn to_working_space(texture: &RgbaImage, linear_space: bool) -> Rgba32FImage {
+    Rgba32FImage::from_fn(texture.width(), texture.height(), |x, y| {
+        let pixel = texture.get_pixel(x, y);
+        let color = |c: u8| if linear_space { srgb_to_linear(c as f32 / 255.0) } else { c as f32 / 255.0 };
+        Rgba([color(pixel[0]), color(pixel[1]), color(pixel[2]), pixel[3] as f32 / 255.0])
+    })
+}
+
+fn from_working_space(image: &Rgba32FImage, linear_space: bool) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y);
+        let to_byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let color = |c: f32| if linear_space { to_byte(linear_to_srgb(c.clamp(0.0, 1.0))) } else { to_byte(c) };
+        Rgba([color(pixel[0]), color(pixel[1]), color(pixel[2]), to_byte(pixel[3])])
+    })
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use formats::formats::block_format::{BlockFormat, BlockProperties};
+use formats::formats::block_id_table_format::BlockIdTableFormat;
+use formats::formats::migration::{self, VersionedFormat};
+
+use crate::consts::{BlockStateSize, BlockTypeSize, ATLAS_TEXTURE_INDICES, ATLAS_UV_RECTS, MAX_AMOUNT_OF_BLOCKTYPES};
+use crate::logw;
+
+pub const AIR: BlockTypeSize = 0;
+pub const AIR_STATE: BlockStateSize = 0;
+pub const AIR_NAME: &str = "AIR";
+
+#[cfg(target_family = "wasm")]
+const BLOCKS_JSON: &str = include_str!("../../data/blocks.json");
+
+static BLOCK_REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+
+/// The registry loaded from `data/blocks.json` on first use.
+pub fn get() -> &'static BlockRegistry {
+    BLOCK_REGISTRY.get_or_init(BlockRegistry::load)
+}
+
+/// Numeric IDs for the blocks in `data/blocks.json`, assigned in file order at startup with AIR as 0.
+/// Every state of a block has its own state ID; a block's states are numbered consecutively from its
+/// default state. Faces resolve to texture indices through the atlas that was compiled in, so a new
+/// block only needs a rebuild when it brings a texture the atlas does not have yet.
+pub struct BlockRegistry {
+    block_names: Vec<String>,
+    block_name_to_id: HashMap<String, BlockTypeSize>,
+    block_properties: Vec<BlockProperties>,
+    block_type_to_first_state: Vec<BlockStateSize>,
+    block_type_to_num_states: Vec<usize>,
+    state_names: Vec<String>,
+    state_name_to_id: HashMap<String, BlockStateSize>,
+    state_to_block_type: Vec<BlockTypeSize>,
+    state_to_texture_indices: Vec<[usize; 6]>,
+}
+
+impl BlockRegistry {
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load() -> Self {
+        let blocks_path = crate::data_directory::get_path("blocks.json");
+        let blocks_json = std::fs::read_to_string(&blocks_path).unwrap_or_else(|e| panic!("{}: {}", blocks_path.display(), e));
+        Self::from_json(&blocks_json).unwrap_or_else(|e| panic!("Invalid data/blocks.json: {}", e))
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn load() -> Self {
+        Self::from_json(BLOCKS_JSON).unwrap_or_else(|e| panic!("Invalid data/blocks.json: {}", e))
+    }
+
+    pub fn from_json(blocks_json: &str) -> Result<Self, String> {
+        let migrated = migration::migrate_vec_str::<BlockFormat>(blocks_json).map_err(|e| e.to_string())?;
+        for warning in &migrated.warnings {
+            logw!("{}", warning);
+        }
+        Self::from_block_formats(&migrated.value, &ATLAS_TEXTURE_INDICES)
+    }
+
+    /// `atlas_texture_indices` maps each face's atlas key to its first texture index.
+    pub fn from_block_formats(vec_block_format: &[BlockFormat], atlas_texture_indices: &[(&str, usize)]) -> Result<Self, String> {
+        let atlas_texture_indices = atlas_texture_indices.iter().copied().collect::<HashMap<&str, usize>>();
+        let max_ids = (MAX_AMOUNT_OF_BLOCKTYPES as u64).min(BlockStateSize::MAX as u64 + 1);
+
+        let mut registry = BlockRegistry {
+            block_names: vec![String::from(AIR_NAME)],
+            block_name_to_id: HashMap::from([(String::from(AIR_NAME), AIR)]),
+            block_properties: vec![BlockProperties::air()],
+            block_type_to_first_state: vec![AIR_STATE],
+            block_type_to_num_states: vec![1],
+            state_names: vec![String::from(AIR_NAME)],
+            state_name_to_id: HashMap::from([(String::from(AIR_NAME), AIR_STATE)]),
+            state_to_block_type: vec![AIR],
+            state_to_texture_indices: vec![[0; 6]],
+        };
+
+        for block_format in vec_block_format {
+            block_format.validate()?;
+            if registry.block_name_to_id.contains_key(&block_format.block_type) {
+                return Err(format!("{} is defined more than once", block_format.block_type));
+            }
+            let num_states = block_format.get_num_states();
+            if (registry.state_names.len() + num_states) as u64 > max_ids {
+                return Err(format!("{} needs state IDs beyond the maximum of {}", block_format.block_type, max_ids));
+            }
+
+            let block_type = registry.block_names.len() as BlockTypeSize;
+            registry.block_names.push(block_format.block_type.clone());
+            registry.block_name_to_id.insert(block_format.block_type.clone(), block_type);
+            registry.block_properties.push(block_format.properties.clone());
+            registry.block_type_to_first_state.push(registry.state_names.len() as BlockStateSize);
+            registry.block_type_to_num_states.push(num_states);
+
+            for state_index in 0..num_states {
+                let mut texture_indices = [0; 6];
+                for (i, texture_key) in block_format.get_state_atlas_keys(state_index).iter().enumerate() {
+                    texture_indices[i] = *atlas_texture_indices.get(texture_key.as_str())
+                        .ok_or_else(|| format!("{}: texture {} is not in the compiled atlas, rebuild to add it", block_format.block_type, texture_key))?;
+                }
+                let state_name = block_format.get_state_name(state_index);
+                registry.state_name_to_id.insert(state_name.clone(), registry.state_names.len() as BlockStateSize);
+                registry.state_names.push(state_name);
+                registry.state_to_block_type.push(block_type);
+                registry.state_to_texture_indices.push(texture_indices);
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub fn get_num_block_types(&self) -> usize {
+        self.block_names.len()
+    }
+
+    pub fn get_num_block_states(&self) -> usize {
+        self.state_names.len()
+    }
+
+    pub fn get_block_type(&self, name: &str) -> Option<BlockTypeSize> {
+        self.block_name_to_id.get(name).copied()
+    }
+
+    pub fn get_block_name(&self, block_type: BlockTypeSize) -> &str {
+        &self.block_names[block_type as usize]
+    }
+
+    /// A uniformly chosen block type other than AIR.
+    pub fn get_random_block_type(&self) -> BlockTypeSize {
+        fastrand::usize(1..self.block_names.len()) as BlockTypeSize
+    }
+
+    pub fn get_properties(&self, block_type: BlockTypeSize) -> &BlockProperties {
+        &self.block_properties[block_type as usize]
+    }
+
+    pub fn get_default_state(&self, block_type: BlockTypeSize) -> BlockStateSize {
+        self.block_type_to_first_state[block_type as usize]
+    }
+
+    pub fn get_num_states(&self, block_type: BlockTypeSize) -> usize {
+        self.block_type_to_num_states[block_type as usize]
+    }
+
+    pub fn get_state(&self, block_type: BlockTypeSize, state_index: usize) -> BlockStateSize {
+        assert!(state_index < self.get_num_states(block_type), "{} has no state {}", self.get_block_name(block_type), state_index);
+        self.get_default_state(block_type) + state_index as BlockStateSize
+    }
+
+    /// Looks up a state by its `LOG[axis=x]` style name; blocks without states go by their plain name.
+    pub fn get_state_by_name(&self, state_name: &str) -> Option<BlockStateSize> {
+        self.state_name_to_id.get(state_name).copied()
+    }
+
+    pub fn get_state_name(&self, block_state: BlockStateSize) -> &str {
+        &self.state_names[block_state as usize]
+    }
+
+    pub fn get_block_type_from_state(&self, block_state: BlockStateSize) -> BlockTypeSize {
+        self.state_to_block_type[block_state as usize]
+    }
+
+    pub fn get_texture_indices(&self, block_state: BlockStateSize) -> [usize; 6] {
+        self.state_to_texture_indices[block_state as usize]
+    }
+
+    /// The packed atlas rectangles of a state's faces, see `consts::ATLAS_UV_RECTS`.
+    pub fn get_uv_rects(&self, block_state: BlockStateSize) -> [[f32; 4]; 6] {
+        self.get_texture_indices(block_state).map(|texture_index| ATLAS_UV_RECTS[texture_index])
+    }
+
+    /// Whether `name` is a current state or block, which is how the replacement rules end.
+    pub fn is_current_name(&self, name: &str) -> bool {
+        self.state_name_to_id.contains_key(name) || self.block_name_to_id.contains_key(name)
+    }
+
+    /// The state ID of a state name, or the default state of a block name.
+    pub fn get_state_by_state_or_block_name(&self, name: &str) -> Option<BlockStateSize> {
+        self.get_state_by_name(name).or_else(|| self.get_block_type(name).map(|block_type| self.get_default_state(block_type)))
+    }
+
+    /// To be written alongside saved block data, see `block_remapping::BlockIdRemapper`.
+    pub fn get_id_table(&self) -> BlockIdTableFormat {
+        BlockIdTableFormat { format_version: <BlockIdTableFormat as VersionedFormat>::CURRENT_VERSION, block_states: self.state_names.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATLAS_TEXTURE_INDICES: [(&str, usize); 3] = [("stone.png", 0), ("log-side.png", 1), ("log-top.png", 2)];
+
+    fn get_block_json(block_type: &str, side: &str, top: &str, states: &str) -> String {
+        format!(
+            r#"{{ "format_version": 5, "block_type": "{}", "texture": {{ "front": "{}", "back": "{}", "left": "{}", "right": "{}", "top": "{}", "bottom": "{}" }}, "states": [{}] }}"#,
+            block_type, side, side, side, side, top, top, states,
+        )
+    }
+
+    fn get_block_registry(blocks_json: &[String]) -> Result<BlockRegistry, String> {
+        let block_formats = migration::migrate_vec_str::<BlockFormat>(&format!("[{}]", blocks_json.join(","))).unwrap().value;
+        BlockRegistry::from_block_formats(&block_formats, &ATLAS_TEXTURE_INDICES)
+    }
+
+    // AIR, STONE, LOG with three axis states, then DIRT.
+    fn get_test_registry() -> BlockRegistry {
+        get_block_registry(&[
+            get_block_json("STONE", "stone.png", "stone.png", ""),
+            get_block_json("LOG", "log-side.png", "log-top.png", r#"{ "name": "axis", "values": ["x", "y", "z"] }"#),
+            get_block_json("DIRT", "stone.png", "stone.png", ""),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn air_comes_first_and_blocks_follow_in_file_order() {
+        let block_registry = get_test_registry();
+
+        assert_eq!(block_registry.get_block_type(AIR_NAME), Some(AIR));
+        assert_eq!(block_registry.get_state_by_name(AIR_NAME), Some(AIR_STATE));
+        assert!(!block_registry.get_properties(AIR).collidable);
+        assert_eq!(["STONE", "LOG", "DIRT"].map(|name| block_registry.get_block_type(name)), [Some(1), Some(2), Some(3)]);
+        assert_eq!(block_registry.get_num_block_types(), 4);
+    }
+
+    #[test]
+    fn each_block_gets_a_consecutive_range_of_states() {
+        let block_registry = get_test_registry();
+
+        assert_eq!([0, 1, 2, 3].map(|block_type| block_registry.get_default_state(block_type)), [0, 1, 2, 5]);
+        assert_eq!([0, 1, 2, 3].map(|block_type| block_registry.get_num_states(block_type)), [1, 1, 3, 1]);
+        assert_eq!(block_registry.get_state(2, 2), 4);
+        assert_eq!(block_registry.get_num_block_states(), 6);
+        assert_eq!(block_registry.get_id_table().block_states, ["AIR", "STONE", "LOG[axis=x]", "LOG[axis=y]", "LOG[axis=z]", "DIRT"]);
+    }
+
+    #[test]
+    fn names_ids_and_states_round_trip() {
+        let block_registry = get_test_registry();
+
+        for block_state in 0..block_registry.get_num_block_states() as BlockStateSize {
+            let state_name = block_registry.get_state_name(block_state);
+            assert_eq!(block_registry.get_state_by_name(state_name), Some(block_state));
+            let block_type = block_registry.get_block_type_from_state(block_state);
+            assert_eq!(block_registry.get_block_type(block_registry.get_block_name(block_type)), Some(block_type));
+        }
+        assert_eq!(block_registry.get_state_by_state_or_block_name("LOG"), Some(2));
+        assert_eq!(block_registry.get_state_by_state_or_block_name("LOG[axis=z]"), Some(4));
+        assert!(block_registry.is_current_name("LOG") && !block_registry.is_current_name("LOG[axis=w]"));
+        assert_eq!(block_registry.get_texture_indices(3), [1, 1, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn a_block_type_defined_twice_is_rejected() {
+        let stone_json = get_block_json("STONE", "stone.png", "stone.png", "");
+
+        assert_eq!(get_block_registry(&[stone_json.clone(), stone_json]).err(), Some(String::from("STONE is defined more than once")));
+    }
+
+    #[test]
+    fn a_texture_missing_from_the_atlas_is_rejected() {
+        let error = get_block_registry(&[get_block_json("GLASS", "glass.png", "glass.png", "")]).err().unwrap();
+
+        assert_eq!(error, "GLASS: texture glass.png is not in the compiled atlas, rebuild to add it");
+    }
+
+    #[test]
+    fn state_ids_stop_at_the_maximum() {
+        // With AIR, 256 * 256 states are one more than u16 IDs can hold.
+        let values = (0..256).map(|value| format!("\"{}\"", value)).collect::<Vec<String>>().join(",");
+        let states = format!(r#"{{ "name": "a", "values": [{}] }}, {{ "name": "b", "values": [{}] }}"#, values, values);
+        let error = get_block_registry(&[get_block_json("STONE", "stone.png", "stone.png", &states)]).err().unwrap();
+
+        assert_eq!(error, "STONE needs state IDs beyond the maximum of 65536");
+        let states = format!(r#"{{ "name": "a", "values": [{}] }}"#, values);
+        assert!(get_block_registry(&[get_block_json("STONE", "stone.png", "stone.png", &states)]).is_ok());
+    }
+}
@@ -0,0 +1,147 @@
+use formats::formats::block_id_table_format::BlockIdTableFormat;
+use formats::formats::block_replacement_format::BlockReplacementFormat;
+use formats::formats::migration;
+
+use crate::block_registry::BlockRegistry;
+use crate::consts::BlockStateSize;
+use crate::logw;
+
+#[cfg(target_family = "wasm")]
+const BLOCK_REPLACEMENT_JSON: &str = include_str!("../../data/block-replacement.json");
+
+pub fn load_block_replacements() -> BlockReplacementFormat {
+    #[cfg(not(target_family = "wasm"))]
+    let block_replacement_json = {
+        let block_replacement_path = crate::data_directory::get_path("block-replacement.json");
+        std::fs::read_to_string(&block_replacement_path).unwrap_or_else(|e| panic!("{}: {}", block_replacement_path.display(), e))
+    };
+    #[cfg(target_family = "wasm")]
+    let block_replacement_json = String::from(BLOCK_REPLACEMENT_JSON);
+
+    let migrated = migration::migrate_str::<BlockReplacementFormat>(&block_replacement_json).unwrap_or_else(|e| panic!("data/block-replacement.json: {}", e));
+    for warning in &migrated.warnings {
+        logw!("{}", warning);
+    }
+    migrated.value
+}
+
+/// Translates the state IDs of saved block data into current ones. Every name in the saved table
+/// has to resolve to a current state, directly or through the replacement rules, or no remapper is
+/// built, so data is never loaded with the wrong blocks.
+pub struct BlockIdRemapper {
+    saved_to_current: Vec<BlockStateSize>,
+    is_identity: bool,
+}
+
+impl BlockIdRemapper {
+    pub fn new(saved_id_table: &BlockIdTableFormat, block_registry: &BlockRegistry, block_replacements: &BlockReplacementFormat) -> Result<Self, Vec<String>> {
+        let mut saved_to_current = Vec::with_capacity(saved_id_table.block_states.len());
+        let mut errors = Vec::new();
+        for (saved_state, state_name) in saved_id_table.block_states.iter().enumerate() {
+            let current_name = match block_replacements.resolve(state_name, |name| block_registry.is_current_name(name)) {
+                Ok(current_name) => current_name,
+                Err(e) => {
+                    errors.push(format!("block_states[{}]: {}", saved_state, e));
+                    continue;
+                }
+            };
+            saved_to_current.push(block_registry.get_state_by_state_or_block_name(&current_name).unwrap());
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let is_identity = saved_to_current.iter().enumerate().all(|(saved_state, current_state)| saved_state == *current_state as usize);
+        Ok(BlockIdRemapper { saved_to_current, is_identity })
+    }
+
+    /// Data written with the current table needs no translation.
+    pub fn is_identity(&self) -> bool {
+        self.is_identity
+    }
+
+    pub fn remap(&self, saved_state: BlockStateSize) -> Result<BlockStateSize, String> {
+        self.saved_to_current.get(saved_state as usize).copied()
+            .ok_or_else(|| format!("block state {} is not in the saved table of {} states", saved_state, self.saved_to_current.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formats::formats::block_format::BlockFormat;
+    use crate::block_registry::AIR_STATE;
+
+    const STONE: BlockStateSize = 1;
+    const LOG_X: BlockStateSize = 2;
+    const LOG_Z: BlockStateSize = 4;
+
+    // AIR, STONE, then LOG[axis=x], LOG[axis=y] and LOG[axis=z].
+    fn get_block_registry() -> BlockRegistry {
+        let blocks_json = r#"[
+            { "format_version": 5, "block_type": "STONE", "texture": { "front": "stone.png", "back": "stone.png", "left": "stone.png", "right": "stone.png", "top": "stone.png", "bottom": "stone.png" } },
+            { "format_version": 5, "block_type": "LOG", "texture": { "front": "stone.png", "back": "stone.png", "left": "stone.png", "right": "stone.png", "top": "stone.png", "bottom": "stone.png" },
+              "states": [{ "name": "axis", "values": ["x", "y", "z"] }] }
+        ]"#;
+        let block_formats = migration::migrate_vec_str::<BlockFormat>(blocks_json).unwrap().value;
+        BlockRegistry::from_block_formats(&block_formats, &[("stone.png", 0)]).unwrap()
+    }
+
+    fn get_remapper(block_states: &[&str], replacements: &[(&str, &str)]) -> Result<BlockIdRemapper, Vec<String>> {
+        let saved_id_table = BlockIdTableFormat { format_version: 1, block_states: block_states.iter().map(|name| name.to_string()).collect() };
+        let block_replacements = BlockReplacementFormat {
+            format_version: 1,
+            replacements: replacements.iter().map(|(name, replacement)| (name.to_string(), replacement.to_string())).collect(),
+        };
+        BlockIdRemapper::new(&saved_id_table, &get_block_registry(), &block_replacements)
+    }
+
+    #[test]
+    fn reordered_and_renamed_states_map_to_their_current_ids() {
+        let remapper = get_remapper(&["AIR", "LOG[axis=z]", "LOG[axis=x]", "ROCK"], &[("ROCK", "STONE")]).unwrap();
+
+        assert!(!remapper.is_identity());
+        assert_eq!([0, 1, 2, 3].map(|saved_state| remapper.remap(saved_state).unwrap()), [0, LOG_Z, LOG_X, STONE]);
+    }
+
+    #[test]
+    fn the_exact_state_rule_wins_and_chains_are_followed() {
+        let replacements = [("OLD_LOG", "LOG"), ("OLD_LOG[axis=z]", "LOG[axis=z]"), ("ROCK", "COBBLE"), ("COBBLE", "STONE")];
+        let remapper = get_remapper(&["AIR", "OLD_LOG[axis=y]", "OLD_LOG[axis=z]", "ROCK"], &replacements).unwrap();
+
+        assert_eq!([1, 2, 3].map(|saved_state| remapper.remap(saved_state).unwrap()), [LOG_X, LOG_Z, STONE]);
+    }
+
+    #[test]
+    fn replacing_with_air_deletes_the_block() {
+        let remapper = get_remapper(&["AIR", "STONE", "FLOWER"], &[("FLOWER", "AIR")]).unwrap();
+
+        assert_eq!(remapper.remap(2), Ok(AIR_STATE));
+    }
+
+    #[test]
+    fn no_remapper_is_built_when_a_saved_state_does_not_resolve() {
+        let errors = get_remapper(&["AIR", "GONE", "STONE", "ROCK"], &[("ROCK", "COBBLE"), ("COBBLE", "ROCK")]).err().unwrap();
+
+        assert_eq!(errors, vec![
+            String::from("block_states[1]: GONE is not a current block and has no replacement"),
+            String::from("block_states[3]: replacements form a cycle: ROCK -> COBBLE -> ROCK"),
+        ]);
+    }
+
+    #[test]
+    fn states_outside_the_saved_table_are_an_error() {
+        let remapper = get_remapper(&["AIR", "STONE"], &[]).unwrap();
+
+        assert!(remapper.remap(2).is_err());
+    }
+
+    #[test]
+    fn the_current_table_needs_no_translation() {
+        let block_registry = get_block_registry();
+        let remapper = BlockIdRemapper::new(&block_registry.get_id_table(), &block_registry, &BlockReplacementFormat { format_version: 1, replacements: Default::default() }).unwrap();
+
+        assert!(remapper.is_identity());
+        assert_eq!(remapper.remap(LOG_Z), Ok(LOG_Z));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::consts::MAX_MEMORY_USAGE_MB;
+use crate::runtime_config::RuntimeConfig;
 
-use super::consts::{NUM_VERTICES_IN_BUCKET, NUMBER_OF_CHUNKS_AROUND_PLAYER, NUM_BUCKETS_PER_CHUNK, MIN_MEMORY_USAGE_MB};
+use super::consts::{NUM_VERTICES_IN_BUCKET, NUMBER_OF_CHUNKS_AROUND_PLAYER, NUM_BUCKETS_PER_CHUNK};
 
 pub struct BufferSizeFunctionReturn {
     pub vertex_bucket_size: usize,
@@ -8,15 +9,18 @@ pub struct BufferSizeFunctionReturn {
     pub num_initial_buffers: usize,
     pub number_of_buckets_per_buffer: usize,
     pub num_max_buffers: usize,
+    pub num_budgeted_buffers: usize,
 }
 
-pub fn return_bucket_buffer_size_and_amount_information(vertex_size_in_bytes: usize) -> BufferSizeFunctionReturn {
+pub fn return_bucket_buffer_size_and_amount_information(vertex_size_in_bytes: usize, runtime_config: &RuntimeConfig) -> BufferSizeFunctionReturn {
     const MAX_BUFFER_SIZE: usize = 1073741824; // 2 GB
 
     const BUFFER_SIZE: usize = 268435456; // Half a GB per buffer
 
-    let num_initial_buffers: usize = MIN_MEMORY_USAGE_MB as usize / 512;
+    let num_initial_buffers: usize = runtime_config.min_memory_mb as usize / 512;
+    // Shader bindings are generated for MAX_MEMORY_USAGE_MB, the runtime budget only limits how many of them get backing vertex and index buffers
     let num_max_buffers: usize = MAX_MEMORY_USAGE_MB as usize / 512;
+    let num_budgeted_buffers: usize = std::cmp::min(runtime_config.max_memory_mb, MAX_MEMORY_USAGE_MB) as usize / 512;
 
     let vertex_bucket_size = vertex_size_in_bytes * NUM_VERTICES_IN_BUCKET as usize;
     let number_of_vertex_buckets_per_buffer = std::cmp::min(BUFFER_SIZE / vertex_bucket_size, NUMBER_OF_CHUNKS_AROUND_PLAYER as usize * NUM_BUCKETS_PER_CHUNK);
@@ -27,5 +31,5 @@ pub fn return_bucket_buffer_size_and_amount_information(vertex_size_in_bytes: us
 
     let number_of_buckets_per_buffer = std::cmp::min(number_of_vertex_buckets_per_buffer, number_of_index_buckets_per_buffer);
 
-    BufferSizeFunctionReturn { vertex_bucket_size, index_bucket_size, num_initial_buffers, num_max_buffers, number_of_buckets_per_buffer }
-}
\ No newline at end of file
+    BufferSizeFunctionReturn { vertex_bucket_size, index_bucket_size, num_initial_buffers, num_max_buffers, num_budgeted_buffers, number_of_buckets_per_buffer }
+}
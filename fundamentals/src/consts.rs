@@ -1,8 +1,9 @@
 use crate::world_position::WorldPosition;
-use winit::keyboard::KeyCode;
 
-pub const NUM_BLOCK_TYPES: u16 = 5;
-pub const NUM_ADDITIONAL_THREADS: usize = 15;
+pub const MAX_AMOUNT_OF_BLOCKTYPES: u32 = 65536;
+// Block states share the ID space, so both fit max_amount_of_blocktypes.
+pub type BlockTypeSize = u16;
+pub type BlockStateSize = BlockTypeSize;
 pub const RENDER_DISTANCE: usize = 5;
 pub const FOV_DISTANCE: usize = 160;
 pub const CHUNK_DIMENSION: i32 = 32;
@@ -13,7 +14,7 @@ pub const CHUNK_PLANE_SIZE_WRAPPED: usize = 1156;
 pub const CHUNK_SIZE_WRAPPED: usize = 39304;
 pub const BITS_PER_POSITION: u32 = 6;
 pub const TEXTURE_DIMENSION: u32 = 16;
-pub const NUM_TEXTURES: usize = 5;
+pub const NUM_TEXTURES: usize = 6;
 pub const TEX_MAX_X: u32 = 4;
 pub const TEX_MAX_Y: u32 = 4;
 pub const BITS_PER_TEX_COORD_X: u32 = 3;
@@ -27,34 +28,40 @@ pub const NUM_VERTICES_IN_BUCKET: u32 = 1024;
 pub const NUM_BUCKETS_PER_CHUNK: usize = 64;
 pub const NUM_BUCKETS_PER_SIDE: u32 = 10;
 pub const NUM_BUCKETS: usize = 39104;
-pub const MESH_METHOD: &str = "greedy";
-pub const CHUNK_GENERATION_METHOD: &str = "empty";
-pub const PERLIN_POSITIVE_THRESHOLD: f64 = 0.2;
-pub const PERLIN_NEGATIVE_THRESHOLD: f64 = -0.2;
-pub const PERLIN_SCALE_FACTOR: f64 = 0.02;
 
-pub const MIP_LEVEL: u32 = 4;
-pub const TEXTURE_LENGTH_WITH_MIPMAPS: usize = 341;
+// (first frame, frame count) of the animation each texture index belongs to. Static textures are a single frame.
+// The chunk shader picks the frame to show from these and its time uniform.
+pub const TEXTURE_FRAME_RANGES: [(usize, usize); NUM_TEXTURES] = [(0, 1), (1, 1), (2, 1), (3, 1), (4, 1), (5, 1)];
+pub const TEXTURE_FRAME_DURATIONS_MS: [&[u32]; NUM_TEXTURES] = [&[], &[], &[], &[], &[], &[]];
+pub const ATLAS_TEXTURE_INDICES: [(&str, usize); 6] = [
+    ("wooden-plank.png", 0),
+    ("dirt.png", 1),
+    ("grass-block-side.png", 2),
+    ("grass-block-top.png", 3),
+    ("White.png", 4),
+    ("Transparent.png", 5),
+];
+pub const ATLAS_WIDTH: u32 = 128;
+pub const ATLAS_HEIGHT: u32 = 64;
+pub const ATLAS_MIP_LEVEL_COUNT: u32 = 3;
+// (u_min, v_min, u_max, v_max) of every texture index in the packed atlas, excluding its padding.
+pub const ATLAS_UV_RECTS: [[f32; 4]; NUM_TEXTURES] = [
+    [0.03125, 0.0625, 0.15625, 0.3125],
+    [0.21875, 0.0625, 0.34375, 0.3125],
+    [0.40625, 0.0625, 0.53125, 0.3125],
+    [0.59375, 0.0625, 0.71875, 0.3125],
+    [0.78125, 0.0625, 0.90625, 0.3125],
+    [0.03125, 0.4375, 0.15625, 0.6875],
+];
+// Material layers are packed like the albedo atlas; a texture's flags tell which of them hold a map rather than the default.
+pub const MATERIAL_NORMAL: u32 = 1;
+pub const MATERIAL_ROUGHNESS_METALNESS: u32 = 2;
+pub const MATERIAL_EMISSIVE: u32 = 4;
+pub const ATLAS_MATERIAL_FLAGS: [u32; NUM_TEXTURES] = [0, 0, 0, 0, 0, 0];
 
-pub const UP_KEY: KeyCode = KeyCode::Space;
-pub const DOWN_KEY: KeyCode = KeyCode::ShiftLeft;
-pub const LEFT_KEY: KeyCode = KeyCode::KeyA;
-pub const RIGHT_KEY: KeyCode = KeyCode::KeyD;
-pub const FORWARD_KEY: KeyCode = KeyCode::KeyW;
-pub const BACKWARD_KEY: KeyCode = KeyCode::KeyS;
 pub const MOUSE_SENSITIVITY_THRESHOLD: f64 = 0.5;
 pub const MOUSE_SENSITIVITY: f32 = 0.8;
 
-pub const NUM_TASKS_PER_THREAD: usize = 15;
-pub const GENERATE_CHUNK_PRIORITY: u32 = 1;
-pub const UPDATE_CHUNK_PADDING_X_PRIORITY: u32 = 2;
-pub const UPDATE_CHUNK_PADDING_Y_PRIORITY: u32 = 2;
-pub const UPDATE_CHUNK_PADDING_Z_PRIORITY: u32 = 2;
-pub const GENERATE_MESH_PRIORITY: u32 = 4;
-pub const GENERATE_MESH_SIDE_PRIORITY: u32 = 3;
-
-pub const MOVEMENT_SPEED: f32 = 2.0;
-pub const MIN_MEMORY_USAGE_MB: u32 = 512;
 pub const MAX_MEMORY_USAGE_MB: u32 = 1024;
 pub const MAX_SUBVOXEL_OBJECTS: u64 = 500;
 pub const MAX_SUBVOXELS_IN_MODELS: u64 = 1250;
@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use formats::formats::config_layers::DATA_DIR_ENV_VAR;
+
+// A directory only counts as the data directory when it holds the base config.
+const MARKER_FILE: &str = "config.json";
+
+static DATA_DIRECTORY: OnceLock<PathBuf> = OnceLock::new();
+
+/// The directory the runtime reads `blocks.json`, `config.json` and the other data files from, resolved on first use.
+/// `WGPU_PLAYGROUND_DATA_DIR` wins when it is set. Otherwise it is the first `data` directory found next to the
+/// executable or one of its parents, which covers both a shipped build and `target/<profile>` inside the repository,
+/// and then in the working directory.
+pub fn get() -> &'static Path {
+    DATA_DIRECTORY.get_or_init(|| {
+        let env_override = std::env::var_os(DATA_DIR_ENV_VAR).map(PathBuf::from);
+        let exe_path = std::env::current_exe().ok();
+        let working_directory = std::env::current_dir().ok();
+        find(env_override, exe_path.as_deref(), working_directory.as_deref()).unwrap_or_else(|| {
+            panic!("Could not find the data directory next to the executable or in the working directory, set {} to it", DATA_DIR_ENV_VAR)
+        })
+    })
+}
+
+/// The path of a file in the data directory.
+pub fn get_path(file_name: &str) -> PathBuf {
+    get().join(file_name)
+}
+
+/// The directory relative paths in the config, such as `world_directory`, are resolved against: the parent of the data directory.
+pub fn get_root() -> &'static Path {
+    get().parent().unwrap_or(get())
+}
+
+pub fn find(env_override: Option<PathBuf>, exe_path: Option<&Path>, working_directory: Option<&Path>) -> Option<PathBuf> {
+    if env_override.is_some() {
+        return env_override;
+    }
+    let exe_ancestors = exe_path.and_then(|exe_path| exe_path.parent()).into_iter().flat_map(|directory| directory.ancestors());
+    exe_ancestors.chain(working_directory)
+        .map(|directory| directory.join("data"))
+        .find(|data_directory| data_directory.join(MARKER_FILE).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPOSITORY_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+
+    #[test]
+    fn env_override_wins() {
+        let data_directory = find(Some(PathBuf::from("/elsewhere/data")), Some(Path::new(REPOSITORY_ROOT).join("target/debug/game").as_path()), None);
+        assert_eq!(data_directory, Some(PathBuf::from("/elsewhere/data")));
+    }
+
+    #[test]
+    fn finds_the_data_directory_above_the_executable() {
+        let exe_path = Path::new(REPOSITORY_ROOT).join("hello-wgpu/target/debug/hello-wgpu");
+        assert_eq!(find(None, Some(&exe_path), None), Some(Path::new(REPOSITORY_ROOT).join("data")));
+    }
+
+    #[test]
+    fn falls_back_to_the_working_directory() {
+        assert_eq!(find(None, Some(Path::new("/")), Some(Path::new(REPOSITORY_ROOT))), Some(Path::new(REPOSITORY_ROOT).join("data")));
+        assert_eq!(find(None, None, Some(Path::new("/"))), None);
+    }
+}
@@ -1,2 +1 @@
-pub mod block_type;
 pub mod block_side;
\ No newline at end of file
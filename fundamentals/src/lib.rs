@@ -3,4 +3,8 @@ pub mod consts;
 pub mod world_position;
 pub mod buffer_size_function;
 pub mod logger;
-pub mod bitpacking_spec;
\ No newline at end of file
+pub mod bitpacking_spec;
+pub mod runtime_config;
+pub mod data_directory;
+pub mod block_registry;
+pub mod block_remapping;
\ No newline at end of file
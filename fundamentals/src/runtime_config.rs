@@ -0,0 +1,154 @@
+use formats::formats::config_format::{ChunkGenerationOptions, ConfigFormat, TaskPriorities};
+use formats::formats::config_layers::ConfigLayers;
+use formats::formats::migration;
+#[cfg(not(target_family = "wasm"))]
+use formats::formats::config_layers::PROFILE_ENV_VAR;
+#[cfg(target_family = "wasm")]
+use formats::formats::config_layers::ConfigSource;
+
+use crate::consts::{self, BlockTypeSize, MAX_MEMORY_USAGE_MB, RENDER_DISTANCE};
+use crate::logw;
+use crate::world_position::WorldPosition;
+use std::path::PathBuf;
+#[cfg(not(target_family = "wasm"))]
+use crate::data_directory;
+
+// The web build has no filesystem, so it ships with the config that was present at compile time.
+#[cfg(target_family = "wasm")]
+const CONFIG_JSON: &str = include_str!("../../data/config.json");
+
+/// Settings read from `data/config.json`, the selected profile and environment overrides at startup. Values that shader codegen depends on
+/// (chunk dimension, texture dimension, buffer layouts) stay in `consts`; the render radius and
+/// memory budget here can only shrink the capacity that was compiled in.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    pub num_additional_threads: usize,
+    pub render_radius: usize,
+    pub num_tasks_per_thread: usize,
+    pub task_priorities: TaskPriorities,
+    pub movement_speed: f32,
+    pub mesh_method: String,
+    pub chunk_generation_method: String,
+    pub chunk_generation_options: ChunkGenerationOptions,
+    /// The absolute `world_directory`, or None when saving is disabled or there is no filesystem.
+    pub world_directory: Option<PathBuf>,
+    pub unload_radius: usize,
+    pub chunk_memory_budget_mb: u32,
+    pub min_memory_mb: u32,
+    pub max_memory_mb: u32,
+}
+
+impl RuntimeConfig {
+    pub fn load() -> Self {
+        let config_layers = Self::load_layers();
+        for warning in config_layers.warnings() {
+            logw!("{}", warning);
+        }
+        let config_format = config_layers.to_config_format().unwrap_or_else(|e| panic!("{}", e));
+        Self::from_validated_config_format(&config_format)
+    }
+
+    /// The merged config sources, with `WGPU_PLAYGROUND_PROFILE` selecting `config.<profile>.json` in the data directory.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load_layers() -> ConfigLayers {
+        let profile = std::env::var(PROFILE_ENV_VAR).ok();
+        ConfigLayers::load(&data_directory::get_path("config.json"), profile.as_deref(), std::env::vars()).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn load_layers() -> ConfigLayers {
+        ConfigLayers::from_base_json(CONFIG_JSON, ConfigSource::Base("data/config.json".into())).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn from_json(config_json: &str) -> Self {
+        let migrated = migration::migrate_str::<ConfigFormat>(config_json).unwrap_or_else(|e| panic!("{}", e));
+        for warning in &migrated.warnings {
+            logw!("{}", warning);
+        }
+        let config_format = migrated.value;
+        Self::from_validated_config_format(&config_format)
+    }
+
+    fn from_validated_config_format(config_format: &ConfigFormat) -> Self {
+        if let Err(errors) = config_format.validate(std::mem::size_of::<BlockTypeSize>() as u32 * 8) {
+            panic!("Invalid config:\n{}", errors.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n"));
+        }
+        Self::from_config_format(config_format)
+    }
+
+    pub fn from_config_format(config_format: &ConfigFormat) -> Self {
+        let num_additional_threads = if config_format.use_all_system_threads {
+            std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) - 1
+        } else {
+            config_format.num_additional_threads_specified
+        };
+
+        let mut render_radius = config_format.render_radius;
+        if render_radius > RENDER_DISTANCE {
+            logw!("render_radius {} is larger than the compiled radius {}, clamping", render_radius, RENDER_DISTANCE);
+            render_radius = RENDER_DISTANCE;
+        }
+
+        let mut max_memory_mb = config_format.max_memory_mb;
+        if max_memory_mb > MAX_MEMORY_USAGE_MB {
+            logw!("max_memory_mb {} is larger than the compiled budget {}, clamping", max_memory_mb, MAX_MEMORY_USAGE_MB);
+            max_memory_mb = MAX_MEMORY_USAGE_MB;
+        }
+
+        RuntimeConfig {
+            num_additional_threads,
+            render_radius,
+            num_tasks_per_thread: config_format.num_tasks_per_thread,
+            task_priorities: config_format.task_priorities.clone(),
+            movement_speed: config_format.movement_speed,
+            mesh_method: config_format.mesh_method.clone(),
+            chunk_generation_method: config_format.chunk_generation_method.clone(),
+            chunk_generation_options: config_format.chunk_generation_options.clone(),
+            world_directory: Self::get_world_directory(&config_format.world_directory),
+            unload_radius: config_format.unload_radius,
+            chunk_memory_budget_mb: config_format.chunk_memory_budget_mb,
+            min_memory_mb: config_format.min_memory_mb,
+            max_memory_mb,
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn get_world_directory(world_directory: &str) -> Option<PathBuf> {
+        if world_directory.is_empty() {
+            return None;
+        }
+        Some(data_directory::get_root().join(world_directory))
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn get_world_directory(_world_directory: &str) -> Option<PathBuf> {
+        None
+    }
+
+    /// The subset of `consts::get_positions_around_player` that lies within the runtime render radius.
+    pub fn get_positions_around_player(&self, pos: WorldPosition) -> Vec<WorldPosition> {
+        consts::get_positions_around_player(pos).into_iter()
+            .filter(|chunk_pos| Self::is_offset_in_radius(chunk_pos.x - pos.x, chunk_pos.y - pos.y, chunk_pos.z - pos.z, self.render_radius as i32))
+            .collect()
+    }
+
+    /// Whether the chunk at `chunk_pos` is rendered while the player is in the chunk at `pos`.
+    pub fn is_in_render_radius(&self, pos: &WorldPosition, chunk_pos: &WorldPosition) -> bool {
+        Self::is_offset_in_radius(chunk_pos.x - pos.x, chunk_pos.y - pos.y, chunk_pos.z - pos.z, self.render_radius as i32)
+    }
+
+    /// Whether the chunk at `chunk_pos` stays loaded while the player is in the chunk at `pos`.
+    pub fn is_in_unload_radius(&self, pos: &WorldPosition, chunk_pos: &WorldPosition) -> bool {
+        Self::is_offset_in_radius(chunk_pos.x - pos.x, chunk_pos.y - pos.y, chunk_pos.z - pos.z, self.unload_radius as i32)
+    }
+
+    // Same test the consts builder uses: every corner of the offset chunk lies within radius+1 of the player chunk's center.
+    fn is_offset_in_radius(x: i32, y: i32, z: i32, radius: i32) -> bool {
+        for (cx, cy, cz) in [(x, y, z), (x, y, z+1), (x, y+1, z), (x, y+1, z+1), (x+1, y, z), (x+1, y, z+1), (x+1, y+1, z), (x+1, y+1, z+1)] {
+            if ((cx as f32 - 0.5).powf(2.0) + (cy as f32 - 0.5).powf(2.0) + (cz as f32 - 0.5).powf(2.0)).sqrt() > radius as f32 + 1.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,45 @@
+// The packer lives in the build script, which cargo never tests, so it is compiled in here.
+#[path = "../build/atlas_packer.rs"]
+mod atlas_packer;
+
+use image::{Rgba, RgbaImage};
+
+fn get_textures(sizes: &[u32]) -> Vec<RgbaImage> {
+    sizes.iter().enumerate().map(|(i, size)| RgbaImage::from_pixel(*size, *size, Rgba([i as u8 * 50, 0, 0, 255]))).collect()
+}
+
+#[test]
+fn mip_levels_stop_when_the_gutter_would_vanish() {
+    let textures = get_textures(&[16, 16, 16]);
+    assert_eq!(atlas_packer::pack(&textures, 0).get_mip_level_count(0), 1);
+    assert_eq!(atlas_packer::pack(&textures, 1).get_mip_level_count(1), 1);
+    assert_eq!(atlas_packer::pack(&textures, 4).get_mip_level_count(4), 3);
+    assert_eq!(atlas_packer::pack(&textures, 8).get_mip_level_count(8), 4);
+}
+
+#[test]
+fn mip_levels_stop_when_a_rectangle_no_longer_halves() {
+    assert_eq!(atlas_packer::pack(&get_textures(&[16, 2]), 8).get_mip_level_count(8), 2);
+}
+
+#[test]
+fn every_mip_level_keeps_textures_inside_their_gutters() {
+    let textures = get_textures(&[16, 16, 8]);
+    let packed_atlas = atlas_packer::pack(&textures, 4);
+    let mip_chains = textures.iter().map(|texture| (0..3).map(|level| RgbaImage::from_pixel(texture.width() >> level, texture.height() >> level, *texture.get_pixel(0, 0))).collect()).collect();
+    let levels = packed_atlas.blit_mip_levels(&mip_chains, 4);
+
+    assert_eq!(levels.len(), 3);
+    for (level, image) in levels.iter().enumerate() {
+        assert_eq!(image.dimensions(), (packed_atlas.width >> level, packed_atlas.height >> level));
+        let gutter = 4 >> level;
+        for (texture, rect) in textures.iter().zip(&packed_atlas.rects) {
+            // The rectangle and its gutter hold nothing but the texture's own color.
+            for y in (rect.y >> level) - gutter..((rect.y + rect.height) >> level) + gutter {
+                for x in (rect.x >> level) - gutter..((rect.x + rect.width) >> level) + gutter {
+                    assert_eq!(image.get_pixel(x, y), texture.get_pixel(0, 0), "level {} at {}, {}", level, x, y);
+                }
+            }
+        }
+    }
+}
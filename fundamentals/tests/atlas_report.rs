@@ -0,0 +1,95 @@
+// The report lives in the build script, which cargo never tests, so it is compiled in here.
+#[path = "../build/atlas_report.rs"]
+mod atlas_report;
+
+use atlas_report::AtlasReport;
+use formats::formats::atlas_manifest_format::{AtlasKeyFormat, AtlasManifestFormat, AtlasTextureFormat};
+
+// Each key is (name, texture index, content hash); every texture index gets a 16x16 texture.
+fn get_manifest(keys: &[(&str, usize, &str)]) -> AtlasManifestFormat {
+    let num_textures = keys.iter().map(|(_, texture_index, _)| texture_index + 1).max().unwrap_or(0);
+    AtlasManifestFormat {
+        format_version: 3,
+        width: 64,
+        height: 32,
+        padding: 4,
+        textures: (0..num_textures).map(|texture_index| get_texture(texture_index, 16, 16)).collect(),
+        keys: keys.iter().map(|(name, texture_index, content_hash)| AtlasKeyFormat {
+            name: name.to_string(),
+            texture_index: *texture_index,
+            frame_count: 1,
+            content_hash: content_hash.to_string(),
+        }).collect(),
+        layers: Vec::new(),
+    }
+}
+
+fn get_texture(texture_index: usize, width: u32, height: u32) -> AtlasTextureFormat {
+    AtlasTextureFormat { texture_index, name: format!("{}.png", texture_index), frame: 0, x: 0, y: 0, width, height, u_min: 0.0, v_min: 0.0, u_max: 0.0, v_max: 0.0, material_flags: 0 }
+}
+
+#[test]
+fn reports_added_removed_and_changed_keys() {
+    let previous = get_manifest(&[("dirt.png", 0, "aa"), ("stone.png", 1, "bb"), ("sand.png", 2, "cc")]);
+    let current = get_manifest(&[("dirt.png", 0, "aa"), ("stone.png", 1, "b2"), ("glass.png", 2, "dd")]);
+
+    let atlas_report = AtlasReport::new(Some(&previous), &current);
+
+    assert!(atlas_report.has_previous && atlas_report.has_changes());
+    assert_eq!(atlas_report.added, ["glass.png"]);
+    assert_eq!(atlas_report.removed, ["sand.png"]);
+    assert_eq!(atlas_report.changed, ["stone.png"]);
+    assert_eq!(atlas_report.get_summary(), "1 added, 1 removed, 1 changed");
+    assert!(!AtlasReport::new(Some(&current), &current).has_changes());
+}
+
+#[test]
+fn manifests_without_keys_are_not_compared_against() {
+    let current = get_manifest(&[("dirt.png", 0, "aa")]);
+    // Before version 2 a manifest had textures but no keys.
+    let mut previous_v1 = get_manifest(&[("dirt.png", 0, "aa")]);
+    previous_v1.keys.clear();
+
+    for atlas_report in [AtlasReport::new(Some(&previous_v1), &current), AtlasReport::new(None, &current)] {
+        assert!(!atlas_report.has_previous && !atlas_report.has_changes());
+        assert!(atlas_report.removed.is_empty() && atlas_report.changed.is_empty());
+        assert!(atlas_report.to_text().contains("No previous atlas.json with content hashes to compare against."));
+    }
+    // An empty atlas has no keys either, but is still a previous build.
+    let atlas_report = AtlasReport::new(Some(&get_manifest(&[])), &current);
+    assert!(atlas_report.has_previous);
+    assert_eq!(atlas_report.added, ["dirt.png"]);
+}
+
+#[test]
+fn reports_keys_that_share_another_keys_textures() {
+    let current = get_manifest(&[("dirt.png", 0, "aa"), ("stone.png", 1, "bb"), ("dirt-copy.png", 0, "aa"), ("stone-copy.png", 1, "bb")]);
+
+    let atlas_report = AtlasReport::new(None, &current);
+
+    let duplicates = [("dirt-copy.png", "dirt.png"), ("stone-copy.png", "stone.png")].map(|(name, first_name)| (name.to_string(), first_name.to_string()));
+    assert_eq!(atlas_report.duplicates, duplicates);
+    assert!(atlas_report.to_text().contains("  dirt-copy.png uses the textures of dirt.png"));
+}
+
+#[test]
+fn fill_is_the_share_of_the_atlas_covered_with_and_without_padding() {
+    let atlas_report = AtlasReport::new(None, &get_manifest(&[("dirt.png", 0, "aa"), ("stone.png", 1, "bb")]));
+
+    // Two 16x16 textures, 24x24 with their padding, in 64x32.
+    assert_eq!(atlas_report.texture_fill, 0.25);
+    assert_eq!(atlas_report.padded_fill, 0.5625);
+    assert!(atlas_report.to_text().contains("Fill: 25.0% textures, 56.2% with padding"));
+}
+
+#[test]
+fn fill_of_an_atlas_larger_than_a_u32_area() {
+    let mut current = get_manifest(&[]);
+    (current.width, current.height, current.padding) = (65536, 65536, 0);
+    current.textures = vec![get_texture(0, 65536, 32768), get_texture(1, 65536, 32768)];
+
+    let atlas_report = AtlasReport::new(None, &current);
+
+    assert_eq!(atlas_report.texture_fill, 1.0);
+    assert_eq!(atlas_report.padded_fill, 1.0);
+}
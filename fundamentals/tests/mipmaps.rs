@@ -0,0 +1,66 @@
+// The mip generation lives in the build script, which cargo never tests, so it is compiled in here.
+#[path = "../build/mipmaps.rs"]
+mod mipmaps;
+
+use formats::formats::texture_format::{MipFilter, MipSettingsFormat};
+use image::RgbaImage;
+
+fn load_fixture(name: &str) -> RgbaImage {
+    image::open(format!("{}/tests/fixtures/mipmaps/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().to_rgba8()
+}
+
+fn get_channel(level: &RgbaImage, channel: usize) -> Vec<Vec<u8>> {
+    level.rows().map(|row| row.map(|pixel| pixel[channel]).collect()).collect()
+}
+
+fn get_coverage(level: &RgbaImage, alpha_cutoff: u8) -> usize {
+    level.pixels().filter(|pixel| pixel[3] >= alpha_cutoff).count()
+}
+
+#[test]
+fn linear_space_keeps_checkerboard_brightness() {
+    // 1 pixel black and white squares, which average to half the light rather than half the sRGB value.
+    let texture = load_fixture("checkerboard.png");
+
+    let linear_settings = MipSettingsFormat { filter: MipFilter::Box, linear_space: true, alpha_cutoff: None };
+    let levels = mipmaps::generate_mip_levels(&texture, 2, &linear_settings);
+    assert_eq!(levels.iter().map(|level| level.dimensions()).collect::<Vec<_>>(), vec![(2, 2), (1, 1)]);
+    for level in &levels {
+        for pixel in level.pixels() {
+            assert_eq!(pixel.0, [188, 188, 188, 255]);
+        }
+    }
+
+    let srgb_settings = MipSettingsFormat { linear_space: false, ..linear_settings };
+    for level in mipmaps::generate_mip_levels(&texture, 2, &srgb_settings) {
+        for pixel in level.pixels() {
+            assert_eq!(pixel.0, [128, 128, 128, 255]);
+        }
+    }
+}
+
+#[test]
+fn alpha_cutoff_keeps_coverage_of_every_level() {
+    // Each 2x2 block has 0 to 3 opaque pixels, so 24 of the 64 pixels pass the test.
+    let texture = load_fixture("cutout.png");
+    let alpha_cutoff = 160;
+    assert_eq!(get_coverage(&texture, alpha_cutoff), 24);
+
+    let plain_settings = MipSettingsFormat { filter: MipFilter::Box, linear_space: true, alpha_cutoff: None };
+    let plain_levels = mipmaps::generate_mip_levels(&texture, 3, &plain_settings);
+    assert_eq!(get_channel(&plain_levels[0], 3), vec![vec![0, 64, 128, 191]; 4]);
+    assert_eq!(get_channel(&plain_levels[1], 3), vec![vec![32, 159]; 2]);
+    assert_eq!(get_channel(&plain_levels[2], 3), vec![vec![96]]);
+    // Plain averaging thins the texture out until nothing passes the test.
+    assert_eq!(plain_levels.iter().map(|level| get_coverage(level, alpha_cutoff)).collect::<Vec<_>>(), vec![4, 0, 0]);
+
+    let cutout_settings = MipSettingsFormat { alpha_cutoff: Some(alpha_cutoff), ..plain_settings };
+    let cutout_levels = mipmaps::generate_mip_levels(&texture, 3, &cutout_settings);
+    assert_eq!(get_channel(&cutout_levels[0], 3), vec![vec![0, 80, 160, 240]; 4]);
+    assert_eq!(get_channel(&cutout_levels[1], 3), vec![vec![32, 160]; 2]);
+    assert_eq!(get_channel(&cutout_levels[2], 3), vec![vec![160]]);
+    // The closest each level gets to the full size texture's 3/8 without falling below it.
+    assert_eq!(cutout_levels.iter().map(|level| get_coverage(level, alpha_cutoff)).collect::<Vec<_>>(), vec![8, 2, 1]);
+    // Colour is filtered the same either way.
+    assert_eq!(get_channel(&cutout_levels[0], 0), get_channel(&plain_levels[0], 0));
+}
@@ -0,0 +1,58 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use derivables::block::Block;
+use fundamentals::block_registry::{self, AIR_STATE};
+use fundamentals::consts::{BlockStateSize, CHUNK_DIMENSION, CHUNK_PLANE_SIZE, CHUNK_SIZE};
+use fundamentals::runtime_config::RuntimeConfig;
+use fundamentals::world_position::WorldPosition;
+use hello_wgpu::voxels::chunk::{Chunk, ChunkCreationIterator};
+
+// Every wrapped position of the chunk interior, in a shuffled order so the lookups don't just stream.
+fn get_wrapped_positions(rng: &mut fastrand::Rng) -> Vec<(usize, usize, usize)> {
+    let dimension = CHUNK_DIMENSION as usize;
+    let mut positions: Vec<(usize, usize, usize)> = (0..CHUNK_SIZE)
+        .map(|index| (index % dimension + 1, (index / dimension) % dimension + 1, index / CHUNK_PLANE_SIZE as usize + 1))
+        .collect();
+    rng.shuffle(&mut positions);
+    positions
+}
+
+fn get_random_chunk(rng: &mut fastrand::Rng, solid_chance: f32) -> Chunk {
+    let num_block_states = block_registry::get().get_num_block_states();
+    let mut cci = ChunkCreationIterator::new(WorldPosition::new(0, 0, 0));
+    for _ in 0..CHUNK_SIZE {
+        if rng.f32() < solid_chance {
+            cci.push_block(Block::from_state(rng.usize(1..num_block_states) as BlockStateSize));
+        } else {
+            cci.push_block(Block::from_state(AIR_STATE));
+        }
+    }
+    cci.return_full_chunk()
+}
+
+fn bench_get_block_offset(c: &mut Criterion) {
+    let mut rng = fastrand::Rng::with_seed(18);
+    let options = RuntimeConfig::load().chunk_generation_options;
+    let positions = get_wrapped_positions(&mut rng);
+    let chunks = [
+        ("perlin", Chunk::perlin(&WorldPosition::new(0, -1, 0), &options)),
+        ("random_half_solid", get_random_chunk(&mut rng, 0.5)),
+        ("random_mostly_solid", get_random_chunk(&mut rng, 0.95)),
+    ];
+
+    let mut group = c.benchmark_group("get_block_offset");
+    for (name, chunk) in &chunks {
+        if chunk.is_uniform() {
+            continue;
+        }
+        group.bench_function(format!("row_rank/{}", name), |b| b.iter(|| {
+            positions.iter().map(|(cx, cy, cz)| chunk.get_block_offset(black_box(*cx), *cy, *cz)).sum::<usize>()
+        }));
+        group.bench_function(format!("plane_walk/{}", name), |b| b.iter(|| {
+            positions.iter().map(|(cx, cy, cz)| chunk.get_block_offset_by_plane_walk(black_box(*cx), *cy, *cz)).sum::<usize>()
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_get_block_offset);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fundamentals::runtime_config::RuntimeConfig;
+use fundamentals::world_position::WorldPosition;
+use hello_wgpu::voxels::chunk::Chunk;
+use hello_wgpu::voxels::chunk_storage::ChunkStorage;
+use hello_wgpu::voxels::mesh::Mesh;
+use hello_wgpu::voxels::palette_chunk::PaletteChunk;
+
+// The Perlin chunks around the spawn that hold both AIR and solid blocks, which are the ones that get meshed.
+fn get_perlin_chunks() -> Vec<Chunk> {
+    let options = RuntimeConfig::load().chunk_generation_options;
+    let mut chunks = Vec::new();
+    for x in -2..2 {
+        for y in -2..2 {
+            for z in -2..2 {
+                let chunk = Chunk::perlin(&WorldPosition::new(x, y, z), &options);
+                if !chunk.is_uniform() {
+                    chunks.push(chunk);
+                }
+            }
+        }
+    }
+    chunks
+}
+
+fn bench_storage(c: &mut Criterion) {
+    let chunks = get_perlin_chunks();
+    let palette_chunks: Vec<PaletteChunk> = chunks.iter().map(PaletteChunk::from_storage).collect();
+
+    // Memory isn't something criterion measures, so it is printed once next to the timings.
+    let chunk_bytes: usize = chunks.iter().map(|chunk| chunk.get_memory_usage()).sum();
+    let palette_bytes: usize = palette_chunks.iter().map(|chunk| chunk.get_memory_usage()).sum();
+    let mut bits_per_index: Vec<u32> = palette_chunks.iter().map(|chunk| chunk.get_bits_per_index()).collect();
+    bits_per_index.sort_unstable();
+    bits_per_index.dedup();
+    println!("{} Perlin chunks take {} bytes with solid_array and blocks", chunks.len(), chunk_bytes);
+    println!("{} Perlin chunks take {} bytes with palettes of {:?} bits per index", palette_chunks.len(), palette_bytes, bits_per_index);
+
+    let mut group = c.benchmark_group("greedy_mesh");
+    // Meshing every chunk takes around a tenth of a second, so fewer samples keep the run short.
+    group.sample_size(20);
+    group.bench_function("solid_array", |b| b.iter(|| {
+        chunks.iter().map(|chunk| Mesh::greedy(black_box(chunk), 0).front.0.len()).sum::<usize>()
+    }));
+    group.bench_function("palette", |b| b.iter(|| {
+        palette_chunks.iter().map(|chunk| Mesh::greedy(black_box(chunk), 0).front.0.len()).sum::<usize>()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_storage);
+criterion_main!(benches);
@@ -3,7 +3,7 @@ use fundamentals::{world_position::WorldPosition, consts::CHUNK_DIMENSION};
 use web_time::Duration;
 use std::f32::consts::FRAC_PI_2;
 
-use crate::state::input_manager::InputState;
+use crate::state::input_manager::{self, InputManager, InputState};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -52,15 +52,20 @@ impl Camera {
     pub fn calc_matrix(&self) -> Matrix4<f32> {
         Matrix4::look_to_rh(
             self.position,
-            Vector3::new(
-                self.yaw.0.cos(),
-                self.pitch.0.sin(),
-                self.yaw.0.sin(),
-            ).normalize(),
+            self.get_look_direction(),
             Vector3::unit_y(),
         )
     }
 
+    /// The direction the view matrix looks in, so rays cast from the camera hit what is on screen.
+    pub fn get_look_direction(&self) -> Vector3<f32> {
+        Vector3::new(
+            self.yaw.0.cos(),
+            self.pitch.0.sin(),
+            self.yaw.0.sin(),
+        ).normalize()
+    }
+
     pub fn get_controller_updates_and_reset_controller(&mut self, controller: &mut CameraController, dt: Duration) {
         let dt = dt.as_secs_f32();
 
@@ -171,6 +176,10 @@ impl Projection {
         }
     }
 
+    pub fn get_znear(&self) -> f32 {
+        self.znear
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
         self.aspect = width as f32 / height as f32;
     }
@@ -212,29 +221,29 @@ impl CameraController {
         }
     }
 
-    pub fn process_keyboard(&mut self, button_state: &InputState) -> bool {
+    pub fn process_keyboard(&mut self, input_manager: &InputManager) -> bool {
         let mut movement = false;
-        if button_state.is_forward_pressed {
+        if input_manager.is_action_active(input_manager::MOVE_FORWARD) {
             self.amount_forward = self.speed;
             movement = true;
         }
-        if button_state.is_backward_pressed {
+        if input_manager.is_action_active(input_manager::MOVE_BACKWARD) {
             self.amount_backward = self.speed;
             movement = true;
         }
-        if button_state.is_left_pressed {
+        if input_manager.is_action_active(input_manager::MOVE_LEFT) {
             self.amount_left = self.speed;
             movement = true;
         }
-        if button_state.is_right_pressed {
+        if input_manager.is_action_active(input_manager::MOVE_RIGHT) {
             self.amount_right = self.speed;
             movement = true;
         }
-        if button_state.is_up_pressed {
+        if input_manager.is_action_active(input_manager::MOVE_UP) {
             self.amount_up = self.speed;
             movement = true;
         }
-        if button_state.is_down_pressed {
+        if input_manager.is_action_active(input_manager::MOVE_DOWN) {
             self.amount_down = self.speed;
             movement = true;
         }
@@ -277,6 +286,9 @@ pub struct CameraUniform {
     view_proj: [[f32; 4]; 4],
     view_proj_inverse: [[f32; 4]; 4],
     position: [f32; 4],
+    // Milliseconds since the start, which the chunk shader animates textures with.
+    time_ms: u32,
+    _padding: [u32; 3],
 }
 
 impl CameraUniform {
@@ -285,9 +297,16 @@ impl CameraUniform {
             view_proj: cgmath::Matrix4::identity().into(), 
             view_proj_inverse: cgmath::Matrix4::identity().into(), 
             position: [0.0, 0.0, 0.0, 0.0],
+            time_ms: 0,
+            _padding: [0; 3],
         }
     }
 
+    pub fn update_time(&mut self, elapsed: web_time::Duration) {
+        // Wraps after 49 days, which only restarts the texture animations.
+        self.time_ms = elapsed.as_millis() as u32;
+    }
+
     pub fn update_view_proj_and_pos(&mut self, camera: &Camera, projection: &Projection) {
         let viewproj = projection.calc_matrix() * camera.calc_matrix();
         self.view_proj = (viewproj).into();
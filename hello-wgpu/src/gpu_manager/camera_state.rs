@@ -1,5 +1,6 @@
 use cgmath::Point3;
 use wgpu::{Device, SurfaceConfiguration, util::DeviceExt};
+use web_time::Instant;
 
 use crate::camera;
 
@@ -10,6 +11,7 @@ pub struct CameraState {
     pub camera_bind_group_layout: wgpu::BindGroupLayout,
     pub camera_bind_group: wgpu::BindGroup,
     pub projection: camera::Projection,
+    pub start_time: Instant,
 }
 
 impl CameraState {
@@ -63,6 +65,7 @@ impl CameraState {
             camera_bind_group_layout,
             camera_bind_group,
             projection,
+            start_time: Instant::now(),
         }
     }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use itertools::Chunk;
 use fundamentals::world_position::WorldPosition;
 use std::collections::HashMap;
 use cgmath::Point3;
-use wgpu::Device;
+use wgpu::{Device, Queue};
 use wgpu::util::DeviceExt;
 
 pub struct ChunkIndexState {
@@ -11,6 +11,8 @@ pub struct ChunkIndexState {
     pub chunk_index_bind_group_layout: wgpu::BindGroupLayout,
     pub chunk_index_bind_group: wgpu::BindGroup,
     pub pos_to_gpu_index: HashMap<WorldPosition, usize>,
+    // Slots of unloaded chunks, handed out again before any other.
+    pub free_gpu_indices: Vec<usize>,
 }
 
 impl ChunkIndexState {
@@ -58,7 +60,27 @@ impl ChunkIndexState {
             chunk_index_buffer,
             chunk_index_bind_group,
             chunk_index_bind_group_layout,
-            pos_to_gpu_index
+            pos_to_gpu_index,
+            free_gpu_indices: Vec::new()
         }
     }
+
+    /// Frees the slot of an unloaded chunk. Returns the slot, or None if the chunk didn't have one.
+    pub fn release_gpu_index(&mut self, chunk_position: &WorldPosition) -> Option<usize> {
+        let gpu_index = self.pos_to_gpu_index.remove(chunk_position)?;
+        self.free_gpu_indices.push(gpu_index);
+        Some(gpu_index)
+    }
+
+    /// Gives the chunk at `chunk_position` a freed slot, or its current one. None if every slot is in use.
+    pub fn assign_gpu_index(&mut self, chunk_position: &WorldPosition, queue: &Queue) -> Option<usize> {
+        if let Some(gpu_index) = self.pos_to_gpu_index.get(chunk_position) {
+            return Some(*gpu_index);
+        }
+        let gpu_index = self.free_gpu_indices.pop()?;
+        self.chunk_index_array[gpu_index] = *chunk_position;
+        self.pos_to_gpu_index.insert(*chunk_position, gpu_index);
+        queue.write_buffer(&self.chunk_index_buffer, (gpu_index * std::mem::size_of::<WorldPosition>()) as u64, bytemuck::cast_slice(&[*chunk_position]));
+        Some(gpu_index)
+    }
 }
\ No newline at end of file
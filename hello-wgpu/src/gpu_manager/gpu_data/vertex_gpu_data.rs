@@ -1,7 +1,7 @@
 use std::{collections::HashMap, hash::Hash, num::NonZeroUsize, sync::{Arc, RwLock}};
 use cgmath::{Point3, Vector3, Deg};
 use derivables::{subvoxel_vertex::{generate_cube_at_center, SubvoxelVertex}, vertex::Vertex};
-use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide, consts};
+use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide, consts, runtime_config::RuntimeConfig};
 use lru::LruCache;
 use wgpu::{Device, util::DeviceExt, BufferUsages, Queue};
 
@@ -55,12 +55,13 @@ pub struct VertexGPUData {
     pub frustum_bucket_data_to_clear: Vec<(WorldPosition, BlockSide, u32)>,
     pub vertex_buckets_used: usize,
     pub vertex_buckets_total: usize,
-    pub chunk_index_state: Arc<RwLock<ChunkIndexState>>
+    pub chunk_index_state: Arc<RwLock<ChunkIndexState>>,
+    pub runtime_config: Arc<RuntimeConfig>
 }
 
 impl VertexGPUData {
-    pub fn new(device: &Device, chunk_index_state: Arc<RwLock<ChunkIndexState>>) -> Self {
-        let buffer_size_fn_return= fundamentals::buffer_size_function::return_bucket_buffer_size_and_amount_information(std::mem::size_of::<Vertex>());
+    pub fn new(device: &Device, chunk_index_state: Arc<RwLock<ChunkIndexState>>, runtime_config: Arc<RuntimeConfig>) -> Self {
+        let buffer_size_fn_return= fundamentals::buffer_size_function::return_bucket_buffer_size_and_amount_information(std::mem::size_of::<Vertex>(), &runtime_config);
 
         let mut vertex_pool_buffers = Vec::new();
         let mut index_pool_buffers = Vec::new();
@@ -226,7 +227,8 @@ impl VertexGPUData {
             vertex_buckets_total,
             occlusion_cube_vertex_buffer,
             occlusion_cube_index_buffer,
-            chunk_index_state
+            chunk_index_state,
+            runtime_config
         }
     }
 
@@ -344,6 +346,8 @@ impl VertexGPUData {
     }   
 
     pub fn add_mesh_data_drain(&mut self, mesh: Mesh, occlusion_cube_mesh: Mesh, mesh_position: &WorldPosition, queue: Arc<RwLock<Queue>>) {
+        // A chunk meshed again replaces its old mesh rather than leaking its buckets.
+        self.remove_mesh(mesh_position, &queue);
         let front_bucket_data_vertices = self.add_vertex_vec(&mesh.front.0, &queue, BlockSide::FRONT, mesh_position);
         let front_bucket_data_indices = self.add_index_vec_and_update_index_count_vec(&mesh.front.1, &queue, BlockSide::FRONT, mesh_position);
         let back_bucket_data_vertices = self.add_vertex_vec(&mesh.back.0, &queue, BlockSide::BACK, mesh_position);
@@ -454,11 +458,11 @@ impl VertexGPUData {
     }
 
     pub fn allocate_new_buffer(&mut self, device: Arc<RwLock<Device>>) {
-        let buffer_size_fn_return= fundamentals::buffer_size_function::return_bucket_buffer_size_and_amount_information(std::mem::size_of::<Vertex>());
+        let buffer_size_fn_return= fundamentals::buffer_size_function::return_bucket_buffer_size_and_amount_information(std::mem::size_of::<Vertex>(), &self.runtime_config);
 
         let buffer_num = self.vertex_pool_buffers.len() + 1;
 
-        if buffer_num > buffer_size_fn_return.num_max_buffers {
+        if buffer_num > buffer_size_fn_return.num_budgeted_buffers {
             panic!("Ran out of memory!");
         }
 
@@ -491,6 +495,33 @@ impl VertexGPUData {
         self.index_pool_buffers.push(pool_index_buffer);
     }
 
+    /// Hands the buckets of the mesh at `mesh_position` back to be used first and stops drawing them.
+    pub fn remove_mesh(&mut self, mesh_position: &WorldPosition, queue: &Arc<RwLock<Queue>>) {
+        let mesh_bucket_data = match self.pool_position_to_mesh_bucket_data.remove(mesh_position) {
+            Some(mesh_bucket_data) => mesh_bucket_data,
+            None => return
+        };
+        let sides = [
+            (BlockSide::FRONT, mesh_bucket_data.front_bucket_data_vertices, mesh_bucket_data.front_bucket_data_indices),
+            (BlockSide::BACK, mesh_bucket_data.back_bucket_data_vertices, mesh_bucket_data.back_bucket_data_indices),
+            (BlockSide::LEFT, mesh_bucket_data.left_bucket_data_vertices, mesh_bucket_data.left_bucket_data_indices),
+            (BlockSide::RIGHT, mesh_bucket_data.right_bucket_data_vertices, mesh_bucket_data.right_bucket_data_indices),
+            (BlockSide::TOP, mesh_bucket_data.top_bucket_data_vertices, mesh_bucket_data.top_bucket_data_indices),
+            (BlockSide::BOTTOM, mesh_bucket_data.bottom_bucket_data_vertices, mesh_bucket_data.bottom_bucket_data_indices),
+        ];
+        for (side, vertex_buckets, index_buckets) in sides {
+            for bucket_position in &vertex_buckets {
+                self.lru_vertex_buffer_bucket_index.demote(bucket_position);
+            }
+            self.vertex_buckets_used = self.vertex_buckets_used.saturating_sub(vertex_buckets.len());
+            for (i, bucket_position) in index_buckets.iter().enumerate() {
+                self.frustum_bucket_data_to_clear.push((*mesh_position, side, i as u32));
+                Self::update_indirect_index_count(&self.indirect_pool_buffers, bucket_position.buffer_number as usize, bucket_position.bucket_number as usize, 0, queue);
+                self.lru_index_buffer_bucket_index.demote(bucket_position);
+            }
+        }
+    }
+
     pub fn has_meshed_position(&self, mesh_position: &WorldPosition) -> bool {
         self.pool_position_to_mesh_bucket_data.contains_key(mesh_position)
     }
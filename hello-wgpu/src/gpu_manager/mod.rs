@@ -23,7 +23,7 @@ use surface_state::SurfaceState;
 use texture_state::TextureState;
 use gpu_data::vertex_gpu_data::VertexGPUData;
 
-use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide, logi, consts::{self, NUMBER_OF_CHUNKS_AROUND_PLAYER}};
+use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide, logi, consts::{self, NUMBER_OF_CHUNKS_AROUND_PLAYER}, runtime_config::RuntimeConfig};
 use wgpu::{Device, Instance, Queue, Surface, SurfaceConfiguration};
 use winit::{dpi::PhysicalSize, window::Window};
 
@@ -44,13 +44,14 @@ pub struct GPUManager<'a> {
     pub camera_state: CameraState,
     pub flag_state: FlagState,
     pub subvoxel_state: SubvoxelState,
-   // pub vertex_gpu_data: Arc<RwLock<VertexGPUData>>,
+    pub vertex_gpu_data: Arc<RwLock<VertexGPUData>>,
     pub chunk_index_state: Arc<RwLock<ChunkIndexState>>,
+    pub runtime_config: Arc<RuntimeConfig>,
     pub is_surface_configured: bool
 }
 
 impl<'a> GPUManager<'a> {
-    pub fn new(surface: Surface<'a>, size: PhysicalSize<u32>, device: Device, queue: Queue, config: SurfaceConfiguration, is_surface_configured: bool) -> Self {
+    pub fn new(surface: Surface<'a>, size: PhysicalSize<u32>, device: Device, queue: Queue, config: SurfaceConfiguration, is_surface_configured: bool, runtime_config: Arc<RuntimeConfig>) -> Self {
         let screen_color = wgpu::Color {
             r: 0.0,
             g: 0.5,
@@ -64,7 +65,7 @@ impl<'a> GPUManager<'a> {
 
         let chunk_index_state = Arc::new(RwLock::new(ChunkIndexState::new(camera_state.camera.position, &device)));
 
-        //let vertex_gpu_data = Arc::new(RwLock::new(VertexGPUData::new(&device, chunk_index_state.clone())));
+        let vertex_gpu_data = Arc::new(RwLock::new(VertexGPUData::new(&device, chunk_index_state.clone(), runtime_config.clone())));
 
         //let compute_state = ComputeState::new(camera_state.camera.position, &device, &camera_state.camera_buffer, &vertex_gpu_data.read().unwrap().indirect_pool_buffers, &vertex_gpu_data.read().unwrap().visibility_buffer);
 
@@ -140,8 +141,9 @@ impl<'a> GPUManager<'a> {
                 render_wireframe: false,
             },
             subvoxel_state,
-           // vertex_gpu_data,
+            vertex_gpu_data,
             chunk_index_state,
+            runtime_config,
             is_surface_configured
         }
     }
@@ -169,6 +171,12 @@ impl<'a> GPUManager<'a> {
         self.queue.read().unwrap().write_buffer(&self.camera_state.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_state.camera_uniform]));
     }
 
+    /// Advances the time the shaders animate textures with. Runs every frame, moving or not.
+    pub fn update_time(&mut self) {
+        self.camera_state.camera_uniform.update_time(self.camera_state.start_time.elapsed());
+        self.queue.read().unwrap().write_buffer(&self.camera_state.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_state.camera_uniform]));
+    }
+
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let now = Instant::now();
 
@@ -320,43 +328,49 @@ impl<'a> GPUManager<'a> {
 
 
     
-    // pub fn create_generate_chunk_mesh_task(&self, chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>) -> Task {
-    //     Task::GenerateChunkMesh { 
-    //         chunk_position, 
-    //         chunk, 
-    //         vertex_gpu_data: self.vertex_gpu_data.clone(),
-    //         queue: self.queue.clone(),
-    //         chunk_index_state: self.chunk_index_state.clone()
-    //     }
-    // }
-
-    // pub fn create_generate_chunk_side_mesh_task(&self, chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>, side: BlockSide) -> Task {
-    //     Task::GenerateChunkSideMeshes { 
-    //         chunk_position, 
-    //         chunk, 
-    //         vertex_gpu_data: self.vertex_gpu_data.clone(),
-    //         queue: self.queue.clone(),
-    //         sides: vec![side],
-    //         chunk_index_state: self.chunk_index_state.clone()
-    //     }
-    // }
-
-    // pub fn process_generate_chunk_mesh_task_result(&mut self) {
-    //     self.flag_state.should_calculate_frustum = true;
-    //     while self.vertex_gpu_data.read().unwrap().should_allocate_new_buffer() {
-    //         self.vertex_gpu_data.write().unwrap().allocate_new_buffer(self.device.clone());
-    //     }
-    // }
-
-    // pub fn process_update_chunk_side_mesh_result(&mut self) {
-    //     while self.vertex_gpu_data.read().unwrap().should_allocate_new_buffer() {
-    //         self.vertex_gpu_data.write().unwrap().allocate_new_buffer(self.device.clone());
-    //     }
-    // }
-
-    // pub fn allocate_new_buffer(&mut self) { 
-    //     self.vertex_gpu_data.write().unwrap().allocate_new_buffer(self.device.clone());
-    // }
+    pub fn create_generate_chunk_mesh_task(&self, chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>) -> Task {
+        Task::GenerateChunkMesh { 
+            chunk_position, 
+            chunk, 
+            vertex_gpu_data: self.vertex_gpu_data.clone(),
+            queue: self.queue.clone(),
+            chunk_index_state: self.chunk_index_state.clone()
+        }
+    }
+
+    pub fn create_generate_chunk_side_mesh_task(&self, chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>, side: BlockSide) -> Task {
+        Task::GenerateChunkSideMeshes { 
+            chunk_position, 
+            chunk, 
+            vertex_gpu_data: self.vertex_gpu_data.clone(),
+            queue: self.queue.clone(),
+            sides: vec![side],
+            chunk_index_state: self.chunk_index_state.clone()
+        }
+    }
+
+    pub fn process_generate_chunk_mesh_task_result(&mut self) {
+        self.flag_state.should_calculate_frustum = true;
+        while self.vertex_gpu_data.read().unwrap().should_allocate_new_buffer() {
+            self.vertex_gpu_data.write().unwrap().allocate_new_buffer(self.device.clone());
+        }
+    }
+
+    pub fn process_update_chunk_side_mesh_result(&mut self) {
+        while self.vertex_gpu_data.read().unwrap().should_allocate_new_buffer() {
+            self.vertex_gpu_data.write().unwrap().allocate_new_buffer(self.device.clone());
+        }
+    }
+
+    pub fn allocate_new_buffer(&mut self) { 
+        self.vertex_gpu_data.write().unwrap().allocate_new_buffer(self.device.clone());
+    }
+
+    /// Frees what the GPU holds for a chunk that was unloaded.
+    pub fn release_chunk(&mut self, chunk_position: &WorldPosition) {
+        self.vertex_gpu_data.write().unwrap().remove_mesh(chunk_position, &self.queue);
+        self.chunk_index_state.write().unwrap().release_gpu_index(chunk_position);
+    }
 
     pub fn rotate_subvoxel_object(&mut self, id: usize) {
         self.subvoxel_state.rotate(id, Vector3{ x: Deg(1.0), y: Deg(0.0), z: Deg(0.0) });
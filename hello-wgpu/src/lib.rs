@@ -1,6 +1,7 @@
 mod texture;
 mod camera;
-mod voxels;
+// Public for the benchmarks in benches/.
+pub mod voxels;
 mod state;
 mod tasks;
 cfg_if::cfg_if! {
@@ -1,6 +1,15 @@
 use hello_wgpu::run;
+use fundamentals::runtime_config::RuntimeConfig;
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--print-config") {
+        let config_layers = RuntimeConfig::load_layers();
+        println!("{}", config_layers.describe());
+        for warning in config_layers.warnings() {
+            eprintln!("warning: {}", warning);
+        }
+        return;
+    }
     std::env::set_var("RUST_BACKTRACE", "1");
     run();
 }
@@ -1,5 +1,8 @@
 struct CameraUniform {
     view_proj: mat4x4<f32>,
+    view_proj_inverse: mat4x4<f32>,
+    position: vec4<f32>,
+    time_ms: u32,
 };
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
@@ -22,6 +25,28 @@ struct VertexOutput {
     @location(1) tex_coords: vec2<f32>,
 };
 
+var<private> texture_frame_ranges: array<vec2<u32>, 6> = array<vec2<u32>, 6>(vec2<u32>(0u, 1u), vec2<u32>(1u, 1u), vec2<u32>(2u, 1u), vec2<u32>(3u, 1u), vec2<u32>(4u, 1u), vec2<u32>(5u, 1u));
+var<private> texture_frame_durations_ms: array<u32, 6> = array<u32, 6>(0u, 0u, 0u, 0u, 0u, 0u);
+fn get_texture_frame_index(texture_index: u32, time_ms: u32) -> u32 {
+    let frame_range = texture_frame_ranges[texture_index];
+    if (frame_range.y == 1u) {
+        return texture_index;
+    }
+    let last_frame = frame_range.x + frame_range.y;
+    var loop_duration_ms = 0u;
+    for (var frame = frame_range.x; frame < last_frame; frame++) {
+        loop_duration_ms += texture_frame_durations_ms[frame];
+    }
+    var time_in_loop = time_ms % loop_duration_ms;
+    for (var frame = frame_range.x; frame < last_frame; frame++) {
+        if (time_in_loop < texture_frame_durations_ms[frame]) {
+            return frame;
+        }
+        time_in_loop -= texture_frame_durations_ms[frame];
+    }
+    return frame_range.x;
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
@@ -30,18 +55,35 @@ fn vs_main(
     let chunk_index = (model.data1 & 16368u) >> 4u;
     visibility_array[chunk_index]=0u;
     out.clip_position = camera.view_proj * vec4<f32>(f32((model.data0 & 63u)) + f32(chunkPositions.chunk_positions[3u*chunk_index]*32), f32((model.data0 & 4032u) >> 6u) + f32(chunkPositions.chunk_positions[3u*chunk_index+1u]*32), f32((model.data0 & 258048u) >> 12u) + f32(chunkPositions.chunk_positions[3u*chunk_index+2u]*32), 1.0);
-    out.tex_index = (model.data0 & 16515072u) >> 18u;
+    out.tex_index = get_texture_frame_index((model.data0 & 16515072u) >> 18u, camera.time_ms);
     out.tex_coords = vec2<f32>(f32((model.data0 & 1056964608u) >> 24u), f32(((model.data0 & 3221225472u) >> 30u) | ((model.data1 & 15u) << 2u)));
     return out;
 }
 
 @group(1) @binding(0)
-var diffuse_texture_array: binding_array<texture_2d<f32>>;
+var diffuse_atlas: texture_2d<f32>;
 @group(1) @binding(1)
 var sampler_diffuse: sampler;
+@group(1) @binding(2)
+var emissive_atlas: texture_2d<f32>;
+
+var<private> atlas_uv_rects: array<vec4<f32>, 6> = array<vec4<f32>, 6>(vec4<f32>(0.03125, 0.0625, 0.15625, 0.3125), vec4<f32>(0.21875, 0.0625, 0.34375, 0.3125), vec4<f32>(0.40625, 0.0625, 0.53125, 0.3125), vec4<f32>(0.59375, 0.0625, 0.71875, 0.3125), vec4<f32>(0.78125, 0.0625, 0.90625, 0.3125), vec4<f32>(0.03125, 0.4375, 0.15625, 0.6875));
+
+const MATERIAL_EMISSIVE: u32 = 4u;
+var<private> atlas_material_flags: array<u32, 6> = array<u32, 6>(0u, 0u, 0u, 0u, 0u, 0u);
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-   var tex_color = textureSample(diffuse_texture_array[in.tex_index], sampler_diffuse, in.tex_coords);
+    let uv_rect = atlas_uv_rects[in.tex_index];
+    let uv_size = uv_rect.zw - uv_rect.xy;
+    // Greedy meshed faces span several blocks, so the texture repeats inside its rectangle. The gradients
+    // come from the unwrapped coordinates, or the mip level would jump at every block edge.
+    let uv = uv_rect.xy + fract(in.tex_coords) * uv_size;
+    let uv_dx = dpdx(in.tex_coords) * uv_size;
+    let uv_dy = dpdy(in.tex_coords) * uv_size;
+    var tex_color = textureSampleGrad(diffuse_atlas, sampler_diffuse, uv, uv_dx, uv_dy);
+    if ((atlas_material_flags[in.tex_index] & MATERIAL_EMISSIVE) != 0u) {
+        tex_color += vec4<f32>(textureSampleGrad(emissive_atlas, sampler_diffuse, uv, uv_dx, uv_dy).rgb, 0.0);
+    }
     return tex_color;
 }
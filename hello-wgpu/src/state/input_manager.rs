@@ -1,117 +1,257 @@
-use winit::{event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent}, keyboard::{KeyCode, PhysicalKey}};
+use std::collections::{HashMap, HashSet};
+use formats::formats::controls_format::{Binding, BindingInput, ControlsFormat, Modifier};
+use formats::formats::migration;
+use fundamentals::logw;
+use winit::{event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent}, keyboard::{KeyCode, ModifiersState, PhysicalKey}};
+
+pub const MOVE_UP: &str = "move_up";
+pub const MOVE_DOWN: &str = "move_down";
+pub const MOVE_LEFT: &str = "move_left";
+pub const MOVE_RIGHT: &str = "move_right";
+pub const MOVE_FORWARD: &str = "move_forward";
+pub const MOVE_BACKWARD: &str = "move_backward";
+pub const LOOK: &str = "look";
+pub const TOGGLE_WIREFRAME: &str = "toggle_wireframe";
+pub const ROTATE_SUBVOXEL: &str = "rotate_subvoxel";
+pub const BREAK_BLOCK: &str = "break_block";
+pub const PLACE_BLOCK: &str = "place_block";
+pub const SELECT_NEXT_BLOCK: &str = "select_next_block";
+
+#[cfg(target_family = "wasm")]
+const CONTROLS_JSON: &str = include_str!("../../../data/controls.json");
+
+pub fn load_controls_format() -> ControlsFormat {
+    #[cfg(not(target_family = "wasm"))]
+    let controls_json = {
+        let controls_path = fundamentals::data_directory::get_path("controls.json");
+        std::fs::read_to_string(&controls_path).unwrap_or_else(|e| panic!("{}: {}", controls_path.display(), e))
+    };
+    #[cfg(target_family = "wasm")]
+    let controls_json = String::from(CONTROLS_JSON);
+
+    let migrated = migration::migrate_str::<ControlsFormat>(&controls_json).unwrap_or_else(|e| panic!("{}", e));
+    for warning in &migrated.warnings {
+        logw!("{}", warning);
+    }
+    migrated.value
+}
 
 pub struct InputState {
-    pub is_up_pressed: bool,
-    pub is_down_pressed: bool,
-    pub is_left_pressed: bool,
-    pub is_right_pressed: bool,
-    pub is_forward_pressed: bool,
-    pub is_backward_pressed: bool,
-    pub is_mouse_pressed: bool,
     pub mouse_delta_x: f64,
     pub mouse_delta_y: f64,
     pub mouse_scroll_delta: MouseScrollDelta,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BoundInput {
+    Key(KeyCode),
+    MouseButton(MouseButton),
+}
+
+struct ActionBinding {
+    modifiers: ModifiersState,
+    input: BoundInput,
+}
+
 pub struct InputManager {
-    pub input_state: InputState
+    pub input_state: InputState,
+    action_bindings: HashMap<String, Vec<ActionBinding>>,
+    pressed_inputs: HashSet<BoundInput>,
+    modifiers: ModifiersState,
+    triggered_actions: HashSet<String>,
 }
 
 impl InputManager {
-    pub fn new() -> Self {
+    pub fn new(controls_format: &ControlsFormat) -> Self {
+        let parsed_bindings = match controls_format.parse_bindings::<KeyCode, MouseButton>() {
+            Ok(parsed_bindings) => parsed_bindings,
+            Err(errors) => panic!("Invalid controls:\n{}", errors.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n")),
+        };
+        let action_bindings = parsed_bindings.into_iter().map(|(action, bindings)| {
+            (action, bindings.into_iter().map(Self::resolve_binding).collect())
+        }).collect();
+
         Self {
             input_state: InputState {
-                is_up_pressed: false,
-                is_down_pressed: false,
-                is_left_pressed: false,
-                is_right_pressed: false,
-                is_forward_pressed: false,
-                is_backward_pressed: false,
-                is_mouse_pressed: false,
                 mouse_delta_x: 0.0,
                 mouse_delta_y: 0.0,
                 mouse_scroll_delta: MouseScrollDelta::LineDelta(0.0, 0.0)
             },
+            action_bindings,
+            pressed_inputs: HashSet::new(),
+            modifiers: ModifiersState::empty(),
+            triggered_actions: HashSet::new(),
+        }
+    }
+
+    fn resolve_binding(binding: Binding<KeyCode, MouseButton>) -> ActionBinding {
+        let mut modifiers = ModifiersState::empty();
+        for modifier in &binding.modifiers {
+            modifiers |= match modifier {
+                Modifier::Shift => ModifiersState::SHIFT,
+                Modifier::Ctrl => ModifiersState::CONTROL,
+                Modifier::Alt => ModifiersState::ALT,
+                Modifier::Super => ModifiersState::SUPER,
+            };
         }
+        let input = match binding.input {
+            BindingInput::Key(key) => BoundInput::Key(key),
+            BindingInput::MouseButton(button) => BoundInput::MouseButton(button),
+        };
+        ActionBinding { modifiers, input }
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> (bool, bool, bool) {
+    pub fn is_action_active(&self, action: &str) -> bool {
+        match self.action_bindings.get(action) {
+            Some(bindings) => bindings.iter().any(|binding| self.pressed_inputs.contains(&binding.input) && self.is_binding_matched(binding)),
+            None => false,
+        }
+    }
+
+    /// Whether one of the action's bindings was pressed since the last `end_frame`.
+    pub fn was_action_triggered(&self, action: &str) -> bool {
+        self.triggered_actions.contains(action)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.triggered_actions.clear();
+    }
+
+    /// Returns whether the event was consumed by a binding.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
                         state,
                         physical_key: PhysicalKey::Code(key),
+                        repeat,
                         ..
                     },
                 ..
-            } => 
-            {
-                let mut input_movement_character = false;
-                let mut should_switch_wireframe = false;
-                let mut should_rotate_subvoxel = false;
-                if key == &fundamentals::consts::FORWARD_KEY {
-                    self.input_state.is_forward_pressed = state == &ElementState::Pressed;
-                    input_movement_character = true;
-                }
-                if key == &fundamentals::consts::BACKWARD_KEY {
-                    self.input_state.is_backward_pressed = state == &ElementState::Pressed;
-                    input_movement_character = true;
-                }
-                if key == &fundamentals::consts::LEFT_KEY {
-                    self.input_state.is_left_pressed = state == &ElementState::Pressed;
-                    input_movement_character = true;
-                }
-                if key == &fundamentals::consts::RIGHT_KEY {
-                    self.input_state.is_right_pressed = state == &ElementState::Pressed;
-                    input_movement_character = true;
-                }
-                if key == &fundamentals::consts::UP_KEY {
-                    self.input_state.is_up_pressed = state == &ElementState::Pressed;
-                    input_movement_character = true;
-                }
-                if key == &fundamentals::consts::DOWN_KEY {
-                    self.input_state.is_down_pressed = state == &ElementState::Pressed;
-                    input_movement_character = true;
-                }
-                if key == &KeyCode::ControlLeft {
-                    if state == &ElementState::Pressed {
-                        should_switch_wireframe = true;
-                    }
-                }
-                if key == &KeyCode::KeyR {
-                    if state == &ElementState::Pressed {
-                        should_rotate_subvoxel = true;
-                    }
-                }
-                (input_movement_character, should_switch_wireframe, should_rotate_subvoxel)
+            } => {
+                self.update_input(BoundInput::Key(*key), *state, *repeat)
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
             }
             WindowEvent::MouseWheel { delta, .. } => {
                 self.input_state.mouse_scroll_delta = *delta;
-                (true, false, false)
+                true
             }
             WindowEvent::MouseInput {
-                button: MouseButton::Left,
+                button,
                 state,
                 ..
             } => {
-                if *state == ElementState::Pressed {
-                    self.input_state.is_mouse_pressed = true;
-                } else {
-                    self.input_state.is_mouse_pressed = false;
+                let has_taken_input = self.update_input(BoundInput::MouseButton(*button), *state, false);
+                if !self.is_action_active(LOOK) {
                     self.input_state.mouse_delta_x = 0.0;
                     self.input_state.mouse_delta_y = 0.0;
                 }
-                (true, false, false)
+                has_taken_input
             }
-            _ => (false, false, false)
+            _ => false
         }
     }
 
+    fn update_input(&mut self, input: BoundInput, state: ElementState, repeat: bool) -> bool {
+        if state == ElementState::Pressed {
+            self.pressed_inputs.insert(input);
+        } else {
+            self.pressed_inputs.remove(&input);
+        }
+
+        let mut has_taken_input = false;
+        for (action, bindings) in self.action_bindings.iter() {
+            for binding in bindings.iter().filter(|binding| binding.input == input) {
+                has_taken_input = true;
+                if state == ElementState::Pressed && !repeat && self.is_binding_matched(binding) {
+                    self.triggered_actions.insert(action.clone());
+                }
+            }
+        }
+        has_taken_input
+    }
+
+    // Of the bindings on an input whose modifiers are all held, those with the most modifiers win, so
+    // Ctrl+S can be bound apart from S while S still works with Shift held for another action.
+    fn is_binding_matched(&self, binding: &ActionBinding) -> bool {
+        if !self.modifiers.contains(binding.modifiers) {
+            return false;
+        }
+        let most_modifiers = self.action_bindings.values().flatten()
+            .filter(|other| other.input == binding.input && self.modifiers.contains(other.modifiers))
+            .map(|other| other.modifiers.bits().count_ones())
+            .max();
+        most_modifiers == Some(binding.modifiers.bits().count_ones())
+    }
+
     pub fn handle_mouse_motion(&mut self, delta: (f64, f64)) {
-        if self.input_state.is_mouse_pressed {
+        if self.is_action_active(LOOK) {
             self.input_state.mouse_delta_x = delta.0;
             self.input_state.mouse_delta_y = delta.1;
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formats::formats::input_names::{KEY_CODE_NAMES, MOUSE_BUTTON_NAMES};
+
+    fn get_input_manager(actions: &str) -> InputManager {
+        let controls_json = format!(r#"{{ "format_version": 3, "actions": {}, "mouse_sensitivity_threshold": 0.5, "mouse_sensitivity": 0.8 }}"#, actions);
+        InputManager::new(&migration::migrate_str::<ControlsFormat>(&controls_json).unwrap().value)
+    }
+
+    fn press(input_manager: &mut InputManager, key: KeyCode, modifiers: ModifiersState) {
+        input_manager.modifiers = modifiers;
+        input_manager.update_input(BoundInput::Key(key), ElementState::Pressed, false);
+    }
+
+    #[test]
+    fn every_suggested_name_is_a_key_or_mouse_button() {
+        for name in KEY_CODE_NAMES {
+            assert!(serde_json::from_value::<KeyCode>(serde_json::Value::from(name)).is_ok(), "KeyCode::{}", name);
+        }
+        for name in MOUSE_BUTTON_NAMES {
+            assert!(serde_json::from_value::<MouseButton>(serde_json::Value::from(name)).is_ok(), "MouseButton::{}", name);
+        }
+    }
+
+    #[test]
+    fn the_binding_with_the_most_held_modifiers_wins() {
+        let mut input_manager = get_input_manager(r#"{ "move_backward": ["KeyCode::KeyS"], "save": ["Ctrl+KeyCode::KeyS"], "save_as": ["Ctrl+Shift+KeyCode::KeyS"] }"#);
+
+        press(&mut input_manager, KeyCode::KeyS, ModifiersState::CONTROL);
+        assert!(input_manager.was_action_triggered("save"));
+        assert!(!input_manager.was_action_triggered("move_backward"));
+        assert!(input_manager.is_action_active("save"));
+        assert!(!input_manager.is_action_active("move_backward"));
+        input_manager.end_frame();
+
+        press(&mut input_manager, KeyCode::KeyS, ModifiersState::CONTROL | ModifiersState::SHIFT);
+        assert!(input_manager.was_action_triggered("save_as"));
+        assert!(!input_manager.was_action_triggered("save"));
+        input_manager.end_frame();
+
+        press(&mut input_manager, KeyCode::KeyS, ModifiersState::empty());
+        assert!(input_manager.was_action_triggered("move_backward"));
+        assert!(!input_manager.was_action_triggered("save"));
+    }
+
+    #[test]
+    fn unmodified_bindings_still_fire_with_unbound_modifiers_held() {
+        // Shift descends while moving, so it must not stop the movement keys.
+        let mut input_manager = get_input_manager(r#"{ "move_down": ["KeyCode::ShiftLeft"], "move_forward": ["KeyCode::KeyW"], "save": ["Ctrl+KeyCode::KeyS"] }"#);
+
+        press(&mut input_manager, KeyCode::KeyW, ModifiersState::SHIFT);
+        assert!(input_manager.was_action_triggered("move_forward"));
+        assert!(input_manager.is_action_active("move_forward"));
+
+        press(&mut input_manager, KeyCode::KeyS, ModifiersState::SHIFT);
+        assert!(!input_manager.was_action_triggered("save"));
+    }
+}
@@ -5,6 +5,11 @@ mod task_manager;
 use fundamentals::consts::MOUSE_SENSITIVITY;
 use flag_state::FlagState;
 use fundamentals::loge;
+use fundamentals::logi;
+use fundamentals::block_registry;
+use fundamentals::block_registry::AIR_STATE;
+use fundamentals::consts::BlockTypeSize;
+use derivables::block::Block;
 use log::error;
 use log::info;
 use pollster::FutureExt;
@@ -28,12 +33,15 @@ use crate::gpu_manager::GPUManager;
 
 use crate::camera;
 use crate::tasks::Task;
-use crate::voxels::world::World;
+use crate::voxels::world::{RaycastHit, World};
+use crate::voxels::chunk_storage::ChunkStorage;
 use std::future::Future;
 use std::sync::Arc;
 use std::sync::RwLock;
 
-use fundamentals::consts::MOVEMENT_SPEED;
+use fundamentals::runtime_config::RuntimeConfig;
+use fundamentals::world_position::WorldPosition;
+use cgmath::Point3;
 
 use winit::{
     event::*,
@@ -43,6 +51,16 @@ use winit::{
 use self::input_manager::InputManager;
 use self::task_manager::TaskManager;
 
+// How far away, in blocks, the camera can break and place blocks.
+const BLOCK_REACH: f32 = 8.0;
+
+// Whether any point of the block at `block_position` is within `distance` of `point`.
+fn is_block_within(block_position: &WorldPosition, point: Point3<f32>, distance: f32) -> bool {
+    let offset = [(point.x, block_position.x), (point.y, block_position.y), (point.z, block_position.z)]
+        .map(|(coordinate, block_coordinate)| (block_coordinate as f32 - coordinate).max(coordinate - (block_coordinate + 1) as f32).max(0.0));
+    offset[0] * offset[0] + offset[1] * offset[1] + offset[2] * offset[2] < distance * distance
+}
+
 pub struct AppState<'a> {
     pub state: Option<State<'a>>,
     pub window: Option<Rc<Window>>,
@@ -56,6 +74,13 @@ pub struct State<'a> {
     task_manager: TaskManager,
     camera_controller: camera::CameraController,
     pub world: Arc<RwLock<World>>,
+    // The chunk the camera was in when chunks were last unloaded.
+    player_chunk: WorldPosition,
+    // The block type placed by PLACE_BLOCK.
+    selected_block_type: BlockTypeSize,
+    // The block the camera is looking at, within BLOCK_REACH.
+    pub target_block: Option<RaycastHit>,
+    num_block_edits: u64,
     pub last_render_time: web_time::Instant
 }
 
@@ -227,25 +252,42 @@ impl GraphicsBuilder {
 
 impl<'a> State<'a> {
     pub fn new(surface: Surface<'a>, size: PhysicalSize<u32>, device: Device, queue: Queue, config: SurfaceConfiguration, is_surface_configured: bool) -> Self {
-        let gpu_manager = GPUManager::new(surface, size, device, queue, config, is_surface_configured);
-
-        let camera_controller = camera::CameraController::new(MOVEMENT_SPEED, MOUSE_SENSITIVITY);
-        let world = Arc::new(RwLock::new(World::new()));
-
-        let mut task_manager = TaskManager::new();
-        for pos in gpu_manager.chunk_index_state.read().unwrap().chunk_index_array.iter().rev() {
-            task_manager.push_task(Task::GenerateChunk { chunk_position: *pos, world: world.clone() });
+        let runtime_config = Arc::new(RuntimeConfig::load());
+        // Load the blocks before any chunk is generated so a bad data/blocks.json fails at startup.
+        let num_block_types = block_registry::get().get_num_block_types();
+        let num_block_states = block_registry::get().get_num_block_states();
+        logi!("Registered {} block types with {} block states", num_block_types, num_block_states);
+
+        let gpu_manager = GPUManager::new(surface, size, device, queue, config, is_surface_configured, runtime_config.clone());
+
+        let camera_controller = camera::CameraController::new(runtime_config.movement_speed, MOUSE_SENSITIVITY);
+        let world = Arc::new(RwLock::new(World::new(runtime_config.clone())));
+        let player_chunk = gpu_manager.camera_state.camera.get_chunk_coordinates();
+        // The chunks around spawn stay loaded, so coming back never waits on them.
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    world.write().unwrap().pin_chunk(&player_chunk.get_position_incremented_by(x, y, z));
+                }
+            }
         }
 
+        let mut task_manager = TaskManager::new(runtime_config.clone());
+        Self::request_chunks_around(&mut task_manager, &world, &player_chunk);
+
         Self {
             gpu_manager,
             flag_state: FlagState {
                 should_render_wireframe: false,
                 has_moved: false,
             },
-            input_manager: InputManager::new(),
+            input_manager: InputManager::new(&input_manager::load_controls_format()),
             task_manager,
             world, 
+            player_chunk,
+            selected_block_type: 1,
+            target_block: None,
+            num_block_edits: 0,
             camera_controller,
             last_render_time: web_time::Instant::now(),
         }
@@ -256,14 +298,7 @@ impl<'a> State<'a> {
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
-        let (has_taken_input, should_switch_wireframe, should_rotate_subvoxel) = self.input_manager.input(event);
-        if should_switch_wireframe {
-            self.flag_state.should_render_wireframe = !self.flag_state.should_render_wireframe;
-        }
-        if (should_rotate_subvoxel) {
-            self.gpu_manager.rotate_subvoxel_object(0);
-        }
-        has_taken_input
+        self.input_manager.input(event)
     }
 
     pub fn handle_mouse_motion(&mut self, delta: (f64, f64)) {
@@ -271,19 +306,134 @@ impl<'a> State<'a> {
     }
 
     pub fn process_input(&mut self) {
+        if self.input_manager.was_action_triggered(input_manager::TOGGLE_WIREFRAME) {
+            self.flag_state.should_render_wireframe = !self.flag_state.should_render_wireframe;
+        }
+        if self.input_manager.was_action_triggered(input_manager::ROTATE_SUBVOXEL) {
+            self.gpu_manager.rotate_subvoxel_object(0);
+        }
         self.flag_state.has_moved = self.camera_controller.process_mouse(&mut self.input_manager.input_state);
-        self.flag_state.has_moved = self.camera_controller.process_keyboard(&self.input_manager.input_state) || self.flag_state.has_moved;
+        self.flag_state.has_moved = self.camera_controller.process_keyboard(&self.input_manager) || self.flag_state.has_moved;
+        self.process_block_input();
         self.gpu_manager.process_input(&self.flag_state);
+        self.input_manager.end_frame();
+    }
+
+    fn process_block_input(&mut self) {
+        if self.input_manager.was_action_triggered(input_manager::SELECT_NEXT_BLOCK) {
+            // AIR is type 0 and is never selected.
+            let num_block_types = block_registry::get().get_num_block_types();
+            if num_block_types > 1 {
+                self.selected_block_type = (self.selected_block_type as usize % (num_block_types - 1) + 1) as BlockTypeSize;
+                let selected_block_type = self.selected_block_type;
+                logi!("Selected block type {}", selected_block_type)
+            }
+        }
+
+        // The world only changes under a still camera through edits, and every edit raycasts again first.
+        let should_break = self.input_manager.was_action_triggered(input_manager::BREAK_BLOCK);
+        let should_place = self.input_manager.was_action_triggered(input_manager::PLACE_BLOCK);
+        if !should_break && !should_place && !self.flag_state.has_moved {
+            return;
+        }
+        let camera_position = self.gpu_manager.camera_state.camera.position;
+        let look_direction = self.gpu_manager.camera_state.camera.get_look_direction();
+        self.target_block = self.world.read().unwrap().raycast(camera_position, look_direction, BLOCK_REACH);
+        let Some(target_block) = self.target_block else {
+            return;
+        };
+        if should_break {
+            self.push_set_block_task(target_block.position, Block::from_state(AIR_STATE));
+        }
+        if should_place {
+            let block = Block::new(self.selected_block_type);
+            // The camera is a point, but a block closer to it than the near plane would be clipped open.
+            let znear = self.gpu_manager.camera_state.projection.get_znear();
+            if !block.is_air() && !is_block_within(&target_block.previous_position, camera_position, znear) {
+                self.push_set_block_task(target_block.previous_position, block);
+            }
+        }
+    }
+
+    fn push_set_block_task(&mut self, block_position: WorldPosition, block: Block) {
+        self.num_block_edits += 1;
+        self.task_manager.push_task(Task::SetBlock { block_position, block, world: self.world.clone(), edit_number: self.num_block_edits });
     }
 
     pub fn update(&mut self, dt: web_time::Duration) {
         if (self.camera_controller.has_updates()) {
             self.gpu_manager.update_camera_and_reset_conroller(&mut self.camera_controller, dt);
         }
+        self.gpu_manager.update_time();
     }
 
     pub fn process_tasks(&mut self) {
         self.task_manager.process_tasks(self.world.clone(), &mut self.gpu_manager);
+
+        let player_chunk = self.gpu_manager.camera_state.camera.get_chunk_coordinates();
+        if player_chunk != self.player_chunk {
+            self.player_chunk = player_chunk;
+            self.unload_far_chunks();
+            self.update_rendered_chunks();
+            Self::request_chunks_around(&mut self.task_manager, &self.world, &self.player_chunk);
+        }
+    }
+
+    // Chunks that left the render radius give their mesh and chunk slot back, and loaded chunks that
+    // came back into it are meshed again.
+    fn update_rendered_chunks(&mut self) {
+        let runtime_config = self.world.read().unwrap().runtime_config.clone();
+        let meshed_positions: Vec<WorldPosition> = self.gpu_manager.chunk_index_state.read().unwrap().pos_to_gpu_index.keys().copied().collect();
+        for position in meshed_positions.iter().filter(|position| !runtime_config.is_in_render_radius(&self.player_chunk, position)) {
+            self.gpu_manager.release_chunk(position);
+        }
+        for position in runtime_config.get_positions_around_player(self.player_chunk) {
+            if self.gpu_manager.chunk_index_state.read().unwrap().pos_to_gpu_index.contains_key(&position) {
+                continue;
+            }
+            let Some(chunk) = self.world.read().unwrap().get_chunk_at(&position) else {
+                continue;
+            };
+            // All-air chunks have no faces to mesh.
+            if !chunk.read().unwrap().get_uniform_block().is_some_and(|block| block.is_air()) {
+                self.task_manager.push_task(self.gpu_manager.create_generate_chunk_mesh_task(position, chunk));
+            }
+        }
+    }
+
+    // Queues the chunks in the render radius of `player_chunk` that aren't loaded or queued already,
+    // nearest first.
+    fn request_chunks_around(task_manager: &mut TaskManager, world: &Arc<RwLock<World>>, player_chunk: &WorldPosition) {
+        let runtime_config = world.read().unwrap().runtime_config.clone();
+        for pos in runtime_config.get_positions_around_player(*player_chunk).iter().rev() {
+            if world.write().unwrap().request_chunk(pos) {
+                task_manager.push_task(Task::GenerateChunk { chunk_position: *pos, world: world.clone() });
+            }
+        }
+    }
+
+    fn unload_far_chunks(&mut self) {
+        let unloaded_positions = World::evict_chunks(&self.world, &self.player_chunk);
+        for position in &unloaded_positions {
+            self.gpu_manager.release_chunk(position);
+        }
+        if !unloaded_positions.is_empty() {
+            let num_unloaded = unloaded_positions.len();
+            logi!("Unloaded {} chunks", num_unloaded)
+        }
+    }
+
+    /// Writes the chunks changed since the last save to the configured world directory.
+    pub fn save_world(&mut self) {
+        let Some(world_directory) = self.world.read().unwrap().runtime_config.world_directory.clone() else { return };
+        match World::save_dirty(&self.world, &world_directory) {
+            Ok(num_saved) => {
+                logi!("Saved {} chunks", num_saved)
+            }
+            Err(e) => {
+                loge!("Saving the world failed: {}", e)
+            }
+        }
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -401,6 +551,12 @@ impl<'a> ApplicationHandler<GraphicsResources> for AppState<'a> {
         }
     }
 
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(state) = self.state.as_mut() {
+            state.save_world();
+        }
+    }
+
     fn user_event(&mut self, _event_loop: &ActiveEventLoop, graphics: GraphicsResources) {
         error!("{:?}", graphics.device.limits());
         let window_size = graphics.window.inner_size();
@@ -410,3 +566,20 @@ impl<'a> ApplicationHandler<GraphicsResources> for AppState<'a> {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_block_within_measures_to_the_nearest_point_of_the_block() {
+        let block_position = WorldPosition::new(1, 0, -1);
+        // Inside the block, next to a face, past an edge and past a corner.
+        assert!(is_block_within(&block_position, Point3::new(1.5, 0.5, -0.5), 0.1));
+        assert!(is_block_within(&block_position, Point3::new(0.95, 0.5, -0.5), 0.1));
+        assert!(!is_block_within(&block_position, Point3::new(0.85, 0.5, -0.5), 0.1));
+        assert!(is_block_within(&block_position, Point3::new(2.05, 1.05, -0.5), 0.1));
+        assert!(!is_block_within(&block_position, Point3::new(2.08, 1.08, -0.5), 0.1));
+        assert!(!is_block_within(&block_position, Point3::new(2.06, 1.06, 0.06), 0.1));
+    }
+}
@@ -2,6 +2,7 @@ use std::sync::{Arc, RwLock};
 
 use bitvec::mem;
 use fundamentals::logi;
+use fundamentals::runtime_config::RuntimeConfig;
 
 use crate::{voxels::world::World, gpu_manager::GPUManager, tasks::{Task, TaskResult, ChunkUpdateTaskIdentifyingInfo, TaskError}};
 
@@ -12,25 +13,7 @@ cfg_if::cfg_if! {
         }
 
         impl TaskManager {
-            pub fn new() -> Self {
-                TaskManager { }
-            }
-
-            pub fn push_task(&mut self, task: Task) {
-                logi!("Pushed task!");
-            }
-
-            pub fn process_tasks(&mut self, world: Arc<RwLock<World>>, gpu_manager: &mut GPUManager) {
-                logi!("Processed tasks!");
-            }
-        }
-    } else if #[cfg(not(target_arch = "wasm32"))] {
-        pub struct TaskManager {
-
-        }
-
-        impl TaskManager {
-            pub fn new() -> Self {
+            pub fn new(_runtime_config: Arc<RuntimeConfig>) -> Self {
                 TaskManager { }
             }
 
@@ -44,14 +27,15 @@ cfg_if::cfg_if! {
         }
     } else {
         use crate::thread_task_manager::ThreadTaskManager;
+        use crate::voxels::chunk_storage::ChunkStorage;
 
         pub struct TaskManager {
             thread_task_manager: ThreadTaskManager,
         }
 
         impl TaskManager {
-            pub fn new() -> Self {
-                TaskManager { thread_task_manager: ThreadTaskManager::new() }
+            pub fn new(runtime_config: Arc<RuntimeConfig>) -> Self {
+                TaskManager { thread_task_manager: ThreadTaskManager::new(runtime_config) }
             }
 
             pub fn push_task(&mut self, task: Task) {
@@ -69,9 +53,15 @@ cfg_if::cfg_if! {
                         TaskResult::GenerateChunk { chunk_position } => {
                             logi!("Generated chunk {}!", chunks_generated);
                             chunks_generated += 1;
-                            self.thread_task_manager.push_task(gpu_manager.create_generate_chunk_mesh_task(chunk_position, world.read().unwrap().get_chunk_at(&chunk_position).unwrap()));
-                            
-                            let chunk_generated = world.read().unwrap().get_chunk_at(&chunk_position).unwrap();
+                            // The chunk may have been unloaded while it was generated.
+                            let chunk_generated = match world.read().unwrap().get_chunk_at(&chunk_position) {
+                                Some(chunk_generated) => chunk_generated,
+                                None => continue
+                            };
+                            // All-air chunks have no faces to mesh.
+                            if !chunk_generated.read().unwrap().get_uniform_block().is_some_and(|block| block.is_air()) {
+                                self.thread_task_manager.push_task(gpu_manager.create_generate_chunk_mesh_task(chunk_position, chunk_generated.clone()));
+                            }
 
                             let upper_position = chunk_position.get_position_incremented_by(0, 1, 0);
                             match world.read().unwrap().get_chunk_at(&upper_position) {
@@ -182,12 +172,26 @@ cfg_if::cfg_if! {
                         }
                         TaskResult::UpdateChunkPadding { chunk_positions } => {
                             for (chunk_position, side) in chunk_positions {
-                                self.thread_task_manager.push_task(gpu_manager.create_generate_chunk_side_mesh_task(chunk_position, world.read().unwrap().get_chunk_at(&chunk_position).unwrap(), side));
+                                if let Some(chunk) = world.read().unwrap().get_chunk_at(&chunk_position) {
+                                    self.thread_task_manager.push_task(gpu_manager.create_generate_chunk_side_mesh_task(chunk_position, chunk, side));
+                                }
                             }
                         }
                         TaskResult::UpdateChunkSideMesh {  } => {
                             gpu_manager.process_update_chunk_side_mesh_result();
                         }
+                        TaskResult::SetBlock { chunk_sides, chunk_positions } => {
+                            for chunk_position in chunk_positions {
+                                if let Some(chunk) = world.read().unwrap().get_chunk_at(&chunk_position) {
+                                    self.thread_task_manager.push_task(gpu_manager.create_generate_chunk_mesh_task(chunk_position, chunk));
+                                }
+                            }
+                            for (chunk_position, side) in chunk_sides {
+                                if let Some(chunk) = world.read().unwrap().get_chunk_at(&chunk_position) {
+                                    self.thread_task_manager.push_task(gpu_manager.create_generate_chunk_side_mesh_task(chunk_position, chunk, side));
+                                }
+                            }
+                        }
                         TaskResult::Requeue { task, error } => {
                             match error {
                                 Some(error) => {
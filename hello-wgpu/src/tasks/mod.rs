@@ -5,10 +5,11 @@ use crate::gpu_manager::chunk_index_state::ChunkIndexState;
 use crate::gpu_manager::gpu_data::vertex_gpu_data::{VertexGPUData, MemoryInfo};
 use crate::voxels::chunk::Chunk;
 use crate::voxels::world::World;
+use derivables::block::Block;
 use fundamentals::enums::block_side::BlockSide;
 use fundamentals::world_position::WorldPosition;
 use wgpu::Queue;
-use fundamentals::consts::{GENERATE_CHUNK_PRIORITY, GENERATE_MESH_PRIORITY, GENERATE_MESH_SIDE_PRIORITY, UPDATE_CHUNK_PADDING_X_PRIORITY, UPDATE_CHUNK_PADDING_Y_PRIORITY, UPDATE_CHUNK_PADDING_Z_PRIORITY};
+use formats::formats::config_format::TaskPriorities;
 
 pub mod tasks_processors;
 
@@ -19,7 +20,9 @@ pub enum Task {
     UpdateYAxisChunkPadding { chunk_below: Arc<RwLock<Chunk>>, chunk_above: Arc<RwLock<Chunk>>, additional_data_to_identify_and_hash: ChunkUpdateTaskIdentifyingInfo},
     UpdateXAxisChunkPadding { chunk_front: Arc<RwLock<Chunk>>, chunk_back: Arc<RwLock<Chunk>>, additional_data_to_identify_and_hash: ChunkUpdateTaskIdentifyingInfo},
     UpdateZAxisChunkPadding { chunk_left: Arc<RwLock<Chunk>>, chunk_right: Arc<RwLock<Chunk>>, additional_data_to_identify_and_hash: ChunkUpdateTaskIdentifyingInfo},
-    GenerateChunkSideMeshes { chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>, vertex_gpu_data: Arc<RwLock<VertexGPUData>>, queue: Arc<RwLock<Queue>>, sides: Vec<BlockSide>, chunk_index_state: Arc<RwLock<ChunkIndexState>> }
+    GenerateChunkSideMeshes { chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>, vertex_gpu_data: Arc<RwLock<VertexGPUData>>, queue: Arc<RwLock<Queue>>, sides: Vec<BlockSide>, chunk_index_state: Arc<RwLock<ChunkIndexState>> },
+    // edit_number tells edits to the same block apart, so none of them replaces an earlier one still in the queue.
+    SetBlock { block_position: WorldPosition, block: Block, world: Arc<RwLock<World>>, edit_number: u64 }
 }
 
 pub struct ChunkUpdateTaskIdentifyingInfo {
@@ -104,6 +107,15 @@ impl PartialEq for Task {
                     _ => false
                 }
             }
+
+            Task::SetBlock { edit_number: self_edit_number, .. } => {
+                match other {
+                    Task::SetBlock { edit_number, .. } => {
+                        *self_edit_number == *edit_number
+                    }
+                    _ => false
+                }
+            }
         }
     }
 }
@@ -134,21 +146,26 @@ impl Hash for Task {
             Task::GenerateChunkSideMeshes { chunk_position, .. } => {
                 chunk_position.hash(state);
             }
+            Task::SetBlock { edit_number, .. } => {
+                4.hash(state);
+                edit_number.hash(state);
+            }
             Task::StopThread => {}
         }
     }
 }
 
-pub fn get_task_priority(task: &Task) -> u32 {
-    match task {
+pub fn get_task_priority(task: &Task, task_priorities: &TaskPriorities) -> u32 {
+    (match task {
         Task::StopThread => 0,
-        Task::GenerateChunk { .. } => GENERATE_CHUNK_PRIORITY,
-        Task::UpdateYAxisChunkPadding { .. } => UPDATE_CHUNK_PADDING_Y_PRIORITY,
-        Task::UpdateXAxisChunkPadding { .. } => UPDATE_CHUNK_PADDING_X_PRIORITY,
-        Task::UpdateZAxisChunkPadding { .. } => UPDATE_CHUNK_PADDING_Z_PRIORITY,
-        Task::GenerateChunkMesh { .. } => GENERATE_MESH_PRIORITY,
-        Task::GenerateChunkSideMeshes { .. } => GENERATE_MESH_SIDE_PRIORITY,
-    }
+        Task::GenerateChunk { .. } => task_priorities.chunk,
+        Task::UpdateYAxisChunkPadding { .. } => task_priorities.update_chunk_padding_y,
+        Task::UpdateXAxisChunkPadding { .. } => task_priorities.update_chunk_padding_x,
+        Task::UpdateZAxisChunkPadding { .. } => task_priorities.update_chunk_padding_z,
+        Task::GenerateChunkMesh { .. } => task_priorities.mesh,
+        Task::GenerateChunkSideMeshes { .. } => task_priorities.mesh_side,
+        Task::SetBlock { .. } => task_priorities.set_block,
+    }) as u32
 }
 
 pub enum TaskResult {
@@ -156,7 +173,8 @@ pub enum TaskResult {
     GenerateChunkMesh { },
     GenerateChunk { chunk_position: WorldPosition },
     UpdateChunkPadding { chunk_positions: Vec<(WorldPosition, BlockSide)> },
-    UpdateChunkSideMesh { }
+    UpdateChunkSideMesh { },
+    SetBlock { chunk_sides: Vec<(WorldPosition, BlockSide)>, chunk_positions: Vec<WorldPosition> }
 }
 
 pub enum TaskError {
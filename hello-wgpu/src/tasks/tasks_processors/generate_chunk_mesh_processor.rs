@@ -1,20 +1,29 @@
 use core::time;
 use std::sync::{Arc, RwLock};
 
-use crate::{gpu_manager::{chunk_index_state::ChunkIndexState, gpu_data::vertex_gpu_data::VertexGPUData}, tasks::{Task, TaskError, TaskResult}, voxels::{chunk::Chunk, mesh::Mesh}};
-use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide, consts::MESH_METHOD};
+use crate::{gpu_manager::{chunk_index_state::ChunkIndexState, gpu_data::vertex_gpu_data::VertexGPUData}, tasks::{Task, TaskError, TaskResult}, voxels::{chunk::Chunk, chunk_storage::ChunkStorage, mesh::Mesh}};
+use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide};
 
 pub struct GenerateChunkMeshProcessor {}
 
 impl GenerateChunkMeshProcessor {
-    pub fn process_task(chunk_position: &WorldPosition, chunk: Arc<RwLock<Chunk>>, vertex_gpu_data: Arc<RwLock<VertexGPUData>>, queue: Arc<RwLock<wgpu::Queue>>, chunk_index_state: Arc<RwLock<ChunkIndexState>>) -> TaskResult {
-        let chunk_index = *chunk_index_state.read().unwrap().pos_to_gpu_index.get(chunk_position).unwrap() as u32;
+    pub fn process_task(chunk_position: &WorldPosition, chunk: Arc<RwLock<Chunk>>, vertex_gpu_data: Arc<RwLock<VertexGPUData>>, queue: Arc<RwLock<wgpu::Queue>>, chunk_index_state: Arc<RwLock<ChunkIndexState>>, mesh_method: &str) -> TaskResult {
+        // A chunk loaded again after being unloaded takes a freed slot. Once every slot is taken it
+        // waits for an unloaded chunk to free one.
+        let gpu_index = chunk_index_state.write().unwrap().assign_gpu_index(chunk_position, &queue.read().unwrap());
+        let chunk_index = match gpu_index {
+            Some(chunk_index) => chunk_index as u32,
+            None => return TaskResult::Requeue { task: Task::GenerateChunkMesh { chunk_position: *chunk_position, chunk, vertex_gpu_data, queue, chunk_index_state }, error: None }
+        };
         
         let mut mesh = Mesh::new();
 
-        match MESH_METHOD {
-            "greedy" => mesh = Mesh::greedy(&chunk.read().unwrap(), chunk_index),
-            "cull" => mesh = Mesh::cull(&chunk.read().unwrap(), chunk_index),
+        // All-air chunks have no faces.
+        let is_air = chunk.read().unwrap().get_uniform_block().is_some_and(|block| block.is_air());
+        match mesh_method {
+            _ if is_air => {}
+            "greedy" => mesh = Mesh::greedy(&*chunk.read().unwrap(), chunk_index),
+            "cull" => mesh = Mesh::cull(&*chunk.read().unwrap(), chunk_index),
             _ => {}
         }
 
@@ -45,15 +54,19 @@ impl GenerateChunkMeshProcessor {
 pub struct GenerateChunkSideMeshesProcessor {}
 
 impl GenerateChunkSideMeshesProcessor {
-    pub fn process_task(chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>, vertex_gpu_data: Arc<RwLock<VertexGPUData>>, queue: Arc<RwLock<wgpu::Queue>>, sides: Vec<BlockSide>, chunk_index_state: Arc<RwLock<ChunkIndexState>>) -> TaskResult {
+    pub fn process_task(chunk_position: WorldPosition, chunk: Arc<RwLock<Chunk>>, vertex_gpu_data: Arc<RwLock<VertexGPUData>>, queue: Arc<RwLock<wgpu::Queue>>, sides: Vec<BlockSide>, chunk_index_state: Arc<RwLock<ChunkIndexState>>, mesh_method: &str) -> TaskResult {
+        // Without a slot the chunk isn't rendered, and the full mesh it gets when it is will have the change.
+        if !chunk_index_state.read().unwrap().pos_to_gpu_index.contains_key(&chunk_position) {
+            return TaskResult::UpdateChunkSideMesh {  };
+        }
         if vertex_gpu_data.read().unwrap().has_meshed_position(&chunk_position) {
             let chunk_index = *chunk_index_state.read().unwrap().pos_to_gpu_index.get(&chunk_position).unwrap() as u32;
 
             let mut mesh = Mesh::new();
 
-            match MESH_METHOD {
-                "greedy" => mesh = Mesh::greedy_sided(&chunk.read().unwrap(), chunk_index, &sides),
-                "cull" => mesh = Mesh::cull_side(&chunk.read().unwrap(), chunk_index, &sides),
+            match mesh_method {
+                "greedy" => mesh = Mesh::greedy_sided(&*chunk.read().unwrap(), chunk_index, &sides),
+                "cull" => mesh = Mesh::cull_side(&*chunk.read().unwrap(), chunk_index, &sides),
                 _ => {}
             }
 
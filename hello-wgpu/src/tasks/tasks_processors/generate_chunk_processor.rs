@@ -2,14 +2,37 @@ use std::sync::{Arc, RwLock};
 
 use crate::tasks::TaskResult;
 use crate::{voxels::world::World};
+use fundamentals::loge;
 use fundamentals::world_position::WorldPosition;
 
 pub struct GenerateChunkProcessor {}
 
 impl GenerateChunkProcessor {
     pub fn process_task(chunk_position: &WorldPosition, world: Arc<RwLock<World>>) -> TaskResult {
-        let chunk = World::generate_chunk_at(&chunk_position);
-        world.write().unwrap().add_chunk(chunk);
+        let (runtime_config, block_replacements) = {
+            let world = world.read().unwrap();
+            (world.runtime_config.clone(), world.block_replacements.clone())
+        };
+        let stored_chunk = match &runtime_config.world_directory {
+            Some(world_directory) => World::load_chunk_at(chunk_position, world_directory, &block_replacements),
+            None => Ok(None),
+        };
+        match stored_chunk {
+            Ok(Some(chunk)) => world.write().unwrap().add_chunk(chunk),
+            Ok(None) => {
+                // Generation can be random, so a generated chunk is saved to come back the same.
+                let chunk = World::generate_chunk_at(&chunk_position, &runtime_config);
+                let mut world = world.write().unwrap();
+                world.add_chunk(chunk);
+                world.mark_dirty(chunk_position);
+            }
+            Err(e) => {
+                // Not marked dirty, so the stored chunk isn't overwritten before it can be looked at.
+                loge!("{}", e);
+                let chunk = World::generate_chunk_at(&chunk_position, &runtime_config);
+                world.write().unwrap().add_chunk(chunk);
+            }
+        }
         TaskResult::GenerateChunk { chunk_position: *chunk_position }
     }
-}
\ No newline at end of file
+}
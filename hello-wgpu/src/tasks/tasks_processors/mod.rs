@@ -1,3 +1,4 @@
 pub mod generate_chunk_mesh_processor;
 pub mod generate_chunk_processor;
+pub mod set_block_processor;
 pub mod update_chunk_padding_processors;
\ No newline at end of file
@@ -0,0 +1,81 @@
+use std::sync::{Arc, RwLock};
+
+use derivables::block::Block;
+use fundamentals::consts::CHUNK_DIMENSION;
+use fundamentals::enums::block_side::BlockSide;
+use fundamentals::world_position::WorldPosition;
+use strum::IntoEnumIterator;
+
+use crate::tasks::TaskResult;
+use crate::tasks::tasks_processors::update_chunk_padding_processors::copy_padding;
+use crate::voxels::chunk_storage::{self, ChunkStorage};
+use crate::voxels::world::{self, World};
+
+pub struct SetBlockProcessor {}
+
+impl SetBlockProcessor {
+    /// Places `block` and updates the padding of the neighbouring chunks it borders. Returns the
+    /// sides to remesh, or the whole chunk when it was all air and so had no mesh.
+    pub fn process_task(block_position: &WorldPosition, block: Block, world: Arc<RwLock<World>>) -> TaskResult {
+        let (chunk_position, (x, y, z)) = world::get_chunk_and_local_position(block_position);
+        let mut chunk_sides = Vec::new();
+        let mut chunk_positions = Vec::new();
+        // The chunk may have been unloaded since the edit was made.
+        let Some(chunk) = world.read().unwrap().get_chunk_at(&chunk_position) else {
+            return TaskResult::SetBlock { chunk_sides, chunk_positions };
+        };
+
+        let (was_air, block_edit) = {
+            let mut chunk = chunk.write().unwrap();
+            if chunk.get_block(x, y, z).block_state == block.block_state {
+                return TaskResult::SetBlock { chunk_sides, chunk_positions };
+            }
+            let was_air = chunk.get_uniform_block().is_some_and(|block| block.is_air());
+            (was_air, chunk.set_block(x, y, z, block))
+        };
+        world.write().unwrap().mark_dirty(&chunk_position);
+
+        if was_air {
+            // All-air chunks keep no padding, so it is copied in before the chunk's first mesh.
+            for side in BlockSide::iter() {
+                let (dx, dy, dz) = chunk_storage::get_side_offset(side);
+                let Some(neighbour) = world.read().unwrap().get_chunk_at(&chunk_position.get_position_incremented_by(dx, dy, dz)) else {
+                    continue;
+                };
+                // The lower chunk is locked first, as in the padding processors.
+                if dx + dy + dz < 0 {
+                    let neighbour = neighbour.read().unwrap();
+                    copy_padding(&mut *chunk.write().unwrap(), side, &*neighbour);
+                } else {
+                    let mut chunk = chunk.write().unwrap();
+                    copy_padding(&mut *chunk, side, &*neighbour.read().unwrap());
+                }
+            }
+            chunk_positions.push(chunk_position);
+        } else {
+            for side in block_edit.get_sides() {
+                chunk_sides.push((chunk_position, side));
+            }
+        }
+
+        let (cx, cy, cz) = chunk_storage::to_wrapped(x, y, z);
+        let solid_value = block.occludes_neighbours();
+        for neighbour_position in block_edit.neighbour_chunks {
+            let Some(neighbour) = world.read().unwrap().get_chunk_at(&neighbour_position) else {
+                continue;
+            };
+            let (dx, dy, dz) = (neighbour_position.x - chunk_position.x, neighbour_position.y - chunk_position.y, neighbour_position.z - chunk_position.z);
+            // The edited block as the neighbour's padding sees it, which is only whether it occludes.
+            let (nx, ny, nz) = ((cx as i32 - dx * CHUNK_DIMENSION) as usize, (cy as i32 - dy * CHUNK_DIMENSION) as usize, (cz as i32 - dz * CHUNK_DIMENSION) as usize);
+            let mut neighbour = neighbour.write().unwrap();
+            if neighbour.get_uniform_block().is_some_and(|block| block.is_air()) || neighbour.is_block_solid(nx, ny, nz) == solid_value {
+                continue;
+            }
+            neighbour.update_padding(nx, ny, nz, solid_value);
+            let side = BlockSide::iter().find(|side| chunk_storage::get_side_offset(*side) == (-dx, -dy, -dz)).unwrap();
+            chunk_sides.push((neighbour_position, side));
+        }
+
+        TaskResult::SetBlock { chunk_sides, chunk_positions }
+    }
+}
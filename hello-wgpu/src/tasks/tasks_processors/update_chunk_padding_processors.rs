@@ -2,61 +2,83 @@ use std::sync::{Arc, RwLock};
 
 use fundamentals::{consts::CHUNK_DIMENSION, enums::block_side::BlockSide};
 
-use crate::{voxels::chunk::Chunk, tasks::TaskResult};
+use crate::{voxels::chunk_storage::{self, ChunkStorage}, tasks::TaskResult};
 
 pub struct UpdateYAxisChunkPaddingProcessor {}
 
 impl UpdateYAxisChunkPaddingProcessor {
-    pub fn process_task(chunk_below: Arc<RwLock<Chunk>>, chunk_above: Arc<RwLock<Chunk>>) -> TaskResult {
+    pub fn process_task<C: ChunkStorage>(chunk_below: Arc<RwLock<C>>, chunk_above: Arc<RwLock<C>>) -> TaskResult {
         let mut chunk_below = chunk_below.write().unwrap();
         let mut chunk_above = chunk_above.write().unwrap();
-        let chunk_below_position = chunk_below.position;
-        let chunk_above_position = chunk_above.position;
-        for j in 0..CHUNK_DIMENSION as usize {
-            for i in 0..CHUNK_DIMENSION as usize {
-                chunk_below.update_solid_array(i+1, CHUNK_DIMENSION as usize+1, j+1, chunk_above.is_block_solid(i+1, 1, j+1));
-                chunk_above.update_solid_array(i+1, 0, j+1, chunk_below.is_block_solid(i+1, CHUNK_DIMENSION as usize, j+1));
-            }
+        let mut chunk_positions = Vec::new();
+        if copy_padding(&mut *chunk_below, BlockSide::TOP, &*chunk_above) {
+            chunk_positions.push((chunk_below.get_position(), BlockSide::TOP));
+        }
+        if copy_padding(&mut *chunk_above, BlockSide::BOTTOM, &*chunk_below) {
+            chunk_positions.push((chunk_above.get_position(), BlockSide::BOTTOM));
         }
 
-        TaskResult::UpdateChunkPadding { chunk_positions: vec![(chunk_below_position, BlockSide::TOP), (chunk_above_position, BlockSide::BOTTOM)] }
+        TaskResult::UpdateChunkPadding { chunk_positions }
     }
 }
 
 pub struct UpdateXAxisChunkPaddingProcessor {}
 
 impl UpdateXAxisChunkPaddingProcessor {
-    pub fn process_task(chunk_front: Arc<RwLock<Chunk>>, chunk_back: Arc<RwLock<Chunk>>) -> TaskResult {
+    pub fn process_task<C: ChunkStorage>(chunk_front: Arc<RwLock<C>>, chunk_back: Arc<RwLock<C>>) -> TaskResult {
         let mut chunk_front = chunk_front.write().unwrap();
         let mut chunk_back = chunk_back.write().unwrap();
-        let chunk_front_position = chunk_front.position;
-        let chunk_back_position = chunk_back.position;
-        for j in 0..CHUNK_DIMENSION as usize {
-            for i in 0..CHUNK_DIMENSION as usize {
-                chunk_front.update_solid_array(CHUNK_DIMENSION as usize+1, i+1, j+1, chunk_back.is_block_solid(1, i+1, j+1));
-                chunk_back.update_solid_array(0, i+1, j+1, chunk_front.is_block_solid(CHUNK_DIMENSION as usize, i+1, j+1));
-            }
+        let mut chunk_positions = Vec::new();
+        if copy_padding(&mut *chunk_front, BlockSide::BACK, &*chunk_back) {
+            chunk_positions.push((chunk_front.get_position(), BlockSide::BACK));
+        }
+        if copy_padding(&mut *chunk_back, BlockSide::FRONT, &*chunk_front) {
+            chunk_positions.push((chunk_back.get_position(), BlockSide::FRONT));
         }
 
-        TaskResult::UpdateChunkPadding { chunk_positions: vec![(chunk_front_position, BlockSide::BACK), (chunk_back_position, BlockSide::FRONT)] }
+        TaskResult::UpdateChunkPadding { chunk_positions }
     }
 }
 
 pub struct UpdateZAxisChunkPaddingProcessor {}
 
 impl UpdateZAxisChunkPaddingProcessor {
-    pub fn process_task(chunk_left: Arc<RwLock<Chunk>>, chunk_right: Arc<RwLock<Chunk>>) -> TaskResult {
+    pub fn process_task<C: ChunkStorage>(chunk_left: Arc<RwLock<C>>, chunk_right: Arc<RwLock<C>>) -> TaskResult {
         let mut chunk_left = chunk_left.write().unwrap();
         let mut chunk_right = chunk_right.write().unwrap();
-        let chunk_left_position = chunk_left.position;
-        let chunk_right_position = chunk_right.position;
-        for j in 0..CHUNK_DIMENSION as usize {
-            for i in 0..CHUNK_DIMENSION as usize {
-                chunk_left.update_solid_array(i+1, j+1, CHUNK_DIMENSION as usize+1, chunk_right.is_block_solid(i+1, j+1, 1));
-                chunk_right.update_solid_array(i+1, j+1, 0, chunk_left.is_block_solid(i+1, j+1, CHUNK_DIMENSION as usize));
-            }
+        let mut chunk_positions = Vec::new();
+        if copy_padding(&mut *chunk_left, BlockSide::RIGHT, &*chunk_right) {
+            chunk_positions.push((chunk_left.get_position(), BlockSide::RIGHT));
+        }
+        if copy_padding(&mut *chunk_right, BlockSide::LEFT, &*chunk_left) {
+            chunk_positions.push((chunk_right.get_position(), BlockSide::LEFT));
         }
 
-        TaskResult::UpdateChunkPadding { chunk_positions: vec![(chunk_left_position, BlockSide::RIGHT), (chunk_right_position, BlockSide::LEFT)] }
+        TaskResult::UpdateChunkPadding { chunk_positions }
     }
-}
\ No newline at end of file
+}
+
+// Copies whether the border blocks of `neighbour` occlude into the padding on `side` of `chunk` and
+// returns whether any changed, so that side of `chunk` has to be remeshed. All-air chunks have no
+// mesh to update, and a uniform neighbour's border is known without reading it.
+pub(crate) fn copy_padding<C: ChunkStorage>(chunk: &mut C, side: BlockSide, neighbour: &C) -> bool {
+    if chunk.get_uniform_block().is_some_and(|block| block.is_air()) {
+        return false;
+    }
+    let mut has_changed = false;
+    let uniform_occluding = neighbour.get_uniform_block().map(|block| block.occludes_neighbours());
+    for j in 1..=CHUNK_DIMENSION as usize {
+        for i in 1..=CHUNK_DIMENSION as usize {
+            let (cx, cy, cz) = chunk_storage::get_padding_position(side, i, j);
+            let solid_value = uniform_occluding.unwrap_or_else(|| {
+                let (nx, ny, nz) = chunk_storage::get_padding_position(chunk_storage::get_opposite_side(side), i, j);
+                neighbour.is_block_occluding(nx.clamp(1, CHUNK_DIMENSION as usize), ny.clamp(1, CHUNK_DIMENSION as usize), nz.clamp(1, CHUNK_DIMENSION as usize))
+            });
+            if chunk.is_block_solid(cx, cy, cz) != solid_value {
+                chunk.update_padding(cx, cy, cz, solid_value);
+                has_changed = true;
+            }
+        }
+    }
+    has_changed
+}
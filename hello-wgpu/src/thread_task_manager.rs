@@ -1,8 +1,10 @@
 use crossbeam::channel::{Sender, Receiver};
 use fundamentals::enums::block_side::BlockSide;
 use priority_queue::PriorityQueue;
-use fundamentals::consts::{NUM_ADDITIONAL_THREADS, NUM_TASKS_PER_THREAD};
+use fundamentals::runtime_config::RuntimeConfig;
+use std::sync::Arc;
 use crate::tasks::tasks_processors::generate_chunk_mesh_processor::GenerateChunkSideMeshesProcessor;
+use crate::tasks::tasks_processors::set_block_processor::SetBlockProcessor;
 use crate::tasks::tasks_processors::update_chunk_padding_processors::{UpdateXAxisChunkPaddingProcessor, UpdateZAxisChunkPaddingProcessor};
 use crate::tasks::{Task, TaskResult, get_task_priority};
 use crate::tasks::
@@ -19,15 +21,17 @@ struct ThreadInfo {
 
 pub struct ThreadTaskManager {
     threads: Vec<ThreadInfo>,
-    task_queue: PriorityQueue<Task, u32>
+    task_queue: PriorityQueue<Task, u32>,
+    runtime_config: Arc<RuntimeConfig>
 }
 
 impl ThreadTaskManager {
-    pub fn new() -> Self {
+    pub fn new(runtime_config: Arc<RuntimeConfig>) -> Self {
         let mut threads = Vec::new();
-        for _ in 0..NUM_ADDITIONAL_THREADS {
-            let (s_task, r_task) = crossbeam::channel::bounded(NUM_TASKS_PER_THREAD);
-            let (s_task_result, r_task_result) = crossbeam::channel::bounded(NUM_TASKS_PER_THREAD*2);
+        for _ in 0..runtime_config.num_additional_threads {
+            let (s_task, r_task) = crossbeam::channel::bounded(runtime_config.num_tasks_per_thread);
+            let (s_task_result, r_task_result) = crossbeam::channel::bounded(runtime_config.num_tasks_per_thread*2);
+            let thread_runtime_config = runtime_config.clone();
             let builder = std::thread::Builder::new();
             let _ = builder.spawn(move || {
                 let mut should_run = true;
@@ -43,7 +47,7 @@ impl ThreadTaskManager {
                                     }
                                 },
                                 Task::GenerateChunkMesh { chunk_position, chunk, vertex_gpu_data, queue, chunk_index_state} => {
-                                    match s_task_result.send(GenerateChunkMeshProcessor::process_task(&chunk_position, chunk, vertex_gpu_data, queue, chunk_index_state)) {
+                                    match s_task_result.send(GenerateChunkMeshProcessor::process_task(&chunk_position, chunk, vertex_gpu_data, queue, chunk_index_state, &thread_runtime_config.mesh_method)) {
                                         Ok(_) => {}
                                         Err(_) => should_run = false
                                     }
@@ -67,7 +71,13 @@ impl ThreadTaskManager {
                                     }
                                 }
                                 Task::GenerateChunkSideMeshes { chunk_position, chunk, vertex_gpu_data, queue, sides, chunk_index_state } => {
-                                    match s_task_result.send(GenerateChunkSideMeshesProcessor::process_task(chunk_position, chunk, vertex_gpu_data, queue, sides, chunk_index_state)) {
+                                    match s_task_result.send(GenerateChunkSideMeshesProcessor::process_task(chunk_position, chunk, vertex_gpu_data, queue, sides, chunk_index_state, &thread_runtime_config.mesh_method)) {
+                                        Ok(_) => {}
+                                        Err(_) => should_run = false
+                                    }
+                                }
+                                Task::SetBlock { block_position, block, world, .. } => {
+                                    match s_task_result.send(SetBlockProcessor::process_task(&block_position, block, world)) {
                                         Ok(_) => {}
                                         Err(_) => should_run = false
                                     }
@@ -86,7 +96,8 @@ impl ThreadTaskManager {
 
         Self {
            threads,
-           task_queue: PriorityQueue::new() 
+           task_queue: PriorityQueue::new(),
+           runtime_config
         }
     }
 
@@ -117,14 +128,14 @@ impl ThreadTaskManager {
             _ => {}
         }
 
-        let task_priority = get_task_priority(&task);
+        let task_priority = get_task_priority(&task, &self.runtime_config.task_priorities);
         self.task_queue.push(task, task_priority);
     }
 
     pub fn process_tasks(&mut self) -> Vec<TaskResult> {
         let mut result_vec = Vec::new();
         let mut full_threads = 0;
-        while full_threads < NUM_ADDITIONAL_THREADS && !self.task_queue.is_empty() {
+        while full_threads < self.threads.len() && !self.task_queue.is_empty() {
             full_threads = 0;
             for thread_info in self.threads.iter() {
                 if !thread_info.sender.is_full() && !self.task_queue.is_empty() {
@@ -153,4 +164,53 @@ impl Drop for ThreadTaskManager {
     fn drop(&mut self) {
         self.kill_threads()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::RwLock;
+    use derivables::block::Block;
+    use fundamentals::block_registry::AIR_STATE;
+    use fundamentals::world_position::WorldPosition;
+    use crate::voxels::chunk::Chunk;
+    use crate::voxels::world::World;
+
+    // Hands the queued tasks to the threads until they return `count` results.
+    fn wait_for_results(thread_task_manager: &mut ThreadTaskManager, count: usize) -> Vec<TaskResult> {
+        let mut task_results = Vec::new();
+        let start = std::time::Instant::now();
+        while task_results.len() < count {
+            assert!(start.elapsed() < std::time::Duration::from_secs(10), "timed out waiting for task results");
+            task_results.extend(thread_task_manager.process_tasks());
+            std::thread::yield_now();
+        }
+        task_results
+    }
+
+    #[test]
+    fn set_block_runs_on_a_thread_and_reports_the_sides_to_remesh() {
+        let runtime_config = Arc::new(RuntimeConfig { num_additional_threads: 2, ..RuntimeConfig::load() });
+        let world = Arc::new(RwLock::new(World::new(runtime_config.clone())));
+        world.write().unwrap().add_chunk(Chunk::solid(&WorldPosition::new(0, 0, 0)));
+        let mut thread_task_manager = ThreadTaskManager::new(runtime_config);
+
+        thread_task_manager.push_task(Task::SetBlock { block_position: WorldPosition::new(0, 0, 0), block: Block::from_state(AIR_STATE), world: world.clone(), edit_number: 1 });
+        // Whichever edit runs second finds the block already air, so it has nothing to remesh.
+        thread_task_manager.push_task(Task::SetBlock { block_position: WorldPosition::new(0, 0, 0), block: Block::from_state(AIR_STATE), world: world.clone(), edit_number: 2 });
+        let task_results = wait_for_results(&mut thread_task_manager, 2);
+
+        assert!(world.read().unwrap().get_block(&WorldPosition::new(0, 0, 0)).unwrap().is_air());
+        let mut remeshed_sides = task_results.into_iter().map(|task_result| match task_result {
+            TaskResult::SetBlock { chunk_sides, chunk_positions } => {
+                assert!(chunk_positions.is_empty());
+                assert!(chunk_sides.iter().all(|(chunk_position, _)| *chunk_position == WorldPosition::new(0, 0, 0)));
+                chunk_sides.len()
+            }
+            _ => panic!("expected a SetBlock result"),
+        }).collect::<Vec<usize>>();
+        remeshed_sides.sort();
+        assert_eq!(remeshed_sides[0], 0);
+        assert!(remeshed_sides[1] > 0);
+    }
+}
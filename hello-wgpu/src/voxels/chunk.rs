@@ -1,47 +1,68 @@
-use fundamentals::consts::PERLIN_NEGATIVE_THRESHOLD;
-use fundamentals::consts::PERLIN_POSITIVE_THRESHOLD;
-use fundamentals::consts::PERLIN_SCALE_FACTOR;
+use formats::formats::config_format::ChunkGenerationOptions;
 use fundamentals::world_position::WorldPosition;
 use derivables::block::Block;
-use fundamentals::enums::block_type::BlockType;
+use fundamentals::block_registry::{self, AIR, AIR_STATE};
+use fundamentals::block_remapping::BlockIdRemapper;
+use fundamentals::consts::BlockTypeSize;
 use fundamentals::consts;
 use noise::Perlin;
 use noise::NoiseFn;
 use bitvec::prelude::BitVec;
+use fundamentals::enums::block_side::BlockSide;
+use strum::IntoEnumIterator;
+use super::chunk_storage::{self, BlockEdit, ChunkStorage};
 use consts::{CHUNK_DIMENSION, CHUNK_PLANE_SIZE, CHUNK_SIZE, CHUNK_DIMENSION_WRAPPED, CHUNK_PLANE_SIZE_WRAPPED, CHUNK_SIZE_WRAPPED};
 
 pub struct Chunk {
     pub position: WorldPosition,
     pub solid_array: BitVec,
     pub offsets_at_plane: Vec<u32>,
+    /// The number of solid blocks before each row, indexed by y + z * CHUNK_DIMENSION. A block's index
+    /// in `blocks` is its row's entry plus the solid bits before it in the row.
+    pub offsets_at_row: Vec<u32>,
     pub blocks: Vec<Block>,
+    /// Set while every block in the chunk is this one. `solid_array`, the offsets and `blocks` stay
+    /// empty until the first edit that breaks the uniformity fills them in.
+    pub uniform_block: Option<Block>,
+    /// The padding of a uniform solid chunk, in the layout of `chunk_storage::get_padding_index`, from
+    /// the first neighbouring occluding block copied in. Uniform AIR chunks have no faces and ignore their
+    /// padding, since an edit on their border updates the padding on that side again.
+    pub uniform_padding: Option<BitVec>,
 }
 
 impl Chunk {
 
     pub fn empty(position: &WorldPosition) -> Self {
-        let mut cci = ChunkCreationIterator::new(*position);
+        Self::uniform(position, Block::from_state(AIR_STATE))
+    }
 
-        for _ in 0..CHUNK_SIZE {
-            cci.push_block_type(BlockType::AIR);
+    /// A chunk filled with `block` that allocates nothing until it is edited.
+    pub fn uniform(position: &WorldPosition, block: Block) -> Self {
+        Chunk {
+            position: *position,
+            solid_array: BitVec::new(),
+            offsets_at_plane: Vec::new(),
+            offsets_at_row: Vec::new(),
+            blocks: Vec::new(),
+            uniform_block: Some(block),
+            uniform_padding: None,
         }
-
-        cci.return_chunk()
     }
 
-    pub fn perlin(position: &WorldPosition) -> Self {
+    pub fn perlin(position: &WorldPosition, options: &ChunkGenerationOptions) -> Self {
         let perlin = Perlin::new(0);
+        let block_registry = block_registry::get();
         let mut cci = ChunkCreationIterator::new(*position);
 
         for k in 0..CHUNK_DIMENSION as i32 {
             for j in 0..CHUNK_DIMENSION as i32 {
                 for i in 0..CHUNK_DIMENSION as i32 {
                     let bposition = WorldPosition::new(i + CHUNK_DIMENSION*position.x, j + CHUNK_DIMENSION*position.y, k + CHUNK_DIMENSION*position.z);
-                    let perlin_sample = perlin.get(bposition.to_perlin_pos(PERLIN_SCALE_FACTOR));
-                    if perlin_sample < PERLIN_NEGATIVE_THRESHOLD|| perlin_sample > PERLIN_POSITIVE_THRESHOLD {
-                        cci.push_block_type(BlockType::get_random_type());
+                    let perlin_sample = perlin.get(bposition.to_perlin_pos(options.perlin_scale_factor as f64));
+                    if perlin_sample < options.perlin_negative_threshold as f64 || perlin_sample > options.perlin_positive_threshold as f64 {
+                        cci.push_block_type(block_registry.get_random_block_type());
                     } else {
-                        cci.push_block_type(BlockType::AIR);
+                        cci.push_block_type(AIR);
                     }
                 }
             }
@@ -51,24 +72,20 @@ impl Chunk {
     }
 
     pub fn solid(position: &WorldPosition) -> Self {
-        let mut cci = ChunkCreationIterator::new(*position);
-
-        for _ in 0..CHUNK_SIZE as i32 {
-            cci.push_block_type(BlockType::WOOD);
-        }
-
-        cci.return_chunk()
+        let wood = block_registry::get().get_block_type("WOOD").expect("data/blocks.json has no WOOD block");
+        Self::uniform(position, Block::new(wood))
     }
 
     pub fn checkerboard(position: &WorldPosition) -> Self {
         let mut push_air = false;
+        let wood = block_registry::get().get_block_type("WOOD").expect("data/blocks.json has no WOOD block");
 
         let mut cci = ChunkCreationIterator::new(*position);
         for i in 0..CHUNK_SIZE as i32 {
             if push_air {
-                cci.push_block_type(BlockType::AIR);
+                cci.push_block_type(AIR);
             } else {
-                cci.push_block_type(BlockType::WOOD);
+                cci.push_block_type(wood);
             }
 
             push_air = !push_air;
@@ -85,25 +102,106 @@ impl Chunk {
         cci.return_chunk()
     }
 
-    pub fn _get_block_at(&self, cx: usize, cy: usize, cz: usize) -> &Block{
-        let mut offset = self.offsets_at_plane[cz] as usize;
-        for j in 0..cy {
-            for i in 0..CHUNK_DIMENSION as usize {
+    pub fn is_uniform(&self) -> bool {
+        self.uniform_block.is_some()
+    }
+
+    /// Gives a uniform chunk its own `solid_array` and `blocks`, keeping the padding it was given.
+    fn make_full(&mut self) {
+        let Some(block) = self.uniform_block else {
+            return;
+        };
+        let mut cci = ChunkCreationIterator::new(self.position);
+        for _ in 0..CHUNK_SIZE {
+            cci.push_block(block);
+        }
+        let mut chunk = cci.return_full_chunk();
+        if let Some(uniform_padding) = &self.uniform_padding {
+            for side in BlockSide::iter() {
+                for j in 1..=CHUNK_DIMENSION as usize {
+                    for i in 1..=CHUNK_DIMENSION as usize {
+                        let (cx, cy, cz) = chunk_storage::get_padding_position(side, i, j);
+                        if uniform_padding[chunk_storage::get_padding_index(cx, cy, cz).unwrap()] {
+                            chunk.update_solid_array(cx, cy, cz, true);
+                        }
+                    }
+                }
+            }
+        }
+        *self = chunk;
+    }
+
+    /// Index into `blocks` of the position, or where a block there would be inserted: the number of
+    /// solid blocks before it in creation order.
+    pub fn get_block_offset(&self, cx: usize, cy: usize, cz: usize) -> usize {
+        let row_start = CHUNK_DIMENSION_WRAPPED*cy + CHUNK_PLANE_SIZE_WRAPPED*cz;
+        self.offsets_at_row[(cy - 1) + (cz - 1) * CHUNK_DIMENSION as usize] as usize + self.solid_array[row_start + 1..row_start + cx].count_ones()
+    }
+
+    /// `get_block_offset` as it was before `offsets_at_row`, walking the plane from `offsets_at_plane`.
+    /// Kept for the benchmarks and tests to compare against.
+    pub fn get_block_offset_by_plane_walk(&self, cx: usize, cy: usize, cz: usize) -> usize {
+        let mut offset = if cz > 1 { self.offsets_at_plane[cz - 2] as usize } else { 0 };
+        for j in 1..cy {
+            for i in 1..CHUNK_DIMENSION_WRAPPED - 1 {
                 if self.is_block_solid(i, j, cz) {
                     offset += 1;
                 }
             }
         }
-        for i in 0..cx {
+        for i in 1..cx {
             if self.is_block_solid(i, cy, cz) {
                 offset += 1;
             }
         }
-        &self.blocks[offset]
+        offset
     }
 
-    pub fn is_block_solid(&self, cx: usize, cy: usize, cz: usize) -> bool{
-        self.solid_array[cx+(CHUNK_DIMENSION_WRAPPED as usize)*cy+(CHUNK_PLANE_SIZE_WRAPPED as usize)*cz]
+    /// Translates block states loaded from saved data into the current registry's states. Blocks
+    /// replaced by AIR are removed. Fails without changing the chunk if any state can't be translated.
+    pub fn remap_blocks(&mut self, remapper: &BlockIdRemapper) -> Result<(), String> {
+        if remapper.is_identity() {
+            return Ok(());
+        }
+        if let Some(block) = self.uniform_block {
+            let block = Block::from_state(remapper.remap(block.block_state)?);
+            if block.is_air() {
+                self.uniform_padding = None;
+            }
+            self.uniform_block = Some(block);
+            return Ok(());
+        }
+
+        let mut remapped_blocks = Vec::with_capacity(self.blocks.len());
+        for block in &self.blocks {
+            remapped_blocks.push(Block::from_state(remapper.remap(block.block_state)?));
+        }
+
+        let mut blocks = Vec::with_capacity(remapped_blocks.len());
+        let mut offsets_at_plane = Vec::with_capacity(self.offsets_at_plane.len());
+        let mut offsets_at_row = Vec::with_capacity(self.offsets_at_row.len());
+        let mut remapped_blocks = remapped_blocks.into_iter();
+        for k in 1..CHUNK_DIMENSION_WRAPPED - 1 {
+            for j in 1..CHUNK_DIMENSION_WRAPPED - 1 {
+                offsets_at_row.push(blocks.len() as u32);
+                for i in 1..CHUNK_DIMENSION_WRAPPED - 1 {
+                    if !self.is_block_solid(i, j, k) {
+                        continue;
+                    }
+                    let block = remapped_blocks.next().unwrap();
+                    if block.is_air() {
+                        self.update_solid_array(i, j, k, false);
+                    } else {
+                        blocks.push(block);
+                    }
+                }
+            }
+            offsets_at_plane.push(blocks.len() as u32);
+        }
+        self.blocks = blocks;
+        self.offsets_at_plane = offsets_at_plane;
+        self.offsets_at_row = offsets_at_row;
+        Ok(())
     }
 
     pub fn update_solid_array(&mut self, cx: usize, cy: usize, cz: usize, solid_value: bool) {
@@ -111,10 +209,109 @@ impl Chunk {
     }
 }
 
-struct ChunkCreationIterator {
+impl ChunkStorage for Chunk {
+    type SolidBlocks<'a> = ChunkBlockIterator<'a>;
+
+    fn get_position(&self) -> WorldPosition {
+        self.position
+    }
+
+    fn is_block_solid(&self, cx: usize, cy: usize, cz: usize) -> bool {
+        if let Some(block) = self.uniform_block {
+            let dimension = CHUNK_DIMENSION as usize;
+            return match chunk_storage::get_padding_index(cx, cy, cz) {
+                Some(padding_index) => self.uniform_padding.as_ref().is_some_and(|uniform_padding| uniform_padding[padding_index]),
+                None => !block.is_air() && [cx, cy, cz].iter().all(|c| (1..=dimension).contains(c)),
+            };
+        }
+        self.solid_array[cx+(CHUNK_DIMENSION_WRAPPED as usize)*cy+(CHUNK_PLANE_SIZE_WRAPPED as usize)*cz]
+    }
+
+    fn update_padding(&mut self, cx: usize, cy: usize, cz: usize, solid_value: bool) {
+        match self.uniform_block {
+            Some(block) if block.is_air() => {}
+            Some(_) => {
+                let Some(padding_index) = chunk_storage::get_padding_index(cx, cy, cz) else {
+                    return;
+                };
+                if solid_value || self.uniform_padding.is_some() {
+                    self.uniform_padding.get_or_insert_with(|| BitVec::repeat(false, 6 * CHUNK_PLANE_SIZE as usize)).set(padding_index, solid_value);
+                }
+            }
+            None => self.update_solid_array(cx, cy, cz, solid_value),
+        }
+    }
+
+    fn get_block(&self, x: usize, y: usize, z: usize) -> Block {
+        let (cx, cy, cz) = chunk_storage::to_wrapped(x, y, z);
+        if let Some(block) = self.uniform_block {
+            return block;
+        }
+        if !self.is_block_solid(cx, cy, cz) {
+            return Block::from_state(AIR_STATE);
+        }
+        self.blocks[self.get_block_offset(cx, cy, cz)]
+    }
+
+    /// Keeps `solid_array`, `blocks`, `offsets_at_plane` and `offsets_at_row` in step. A uniform
+    /// chunk becomes full on its first edit that changes a block.
+    fn set_block(&mut self, x: usize, y: usize, z: usize, block: Block) -> BlockEdit {
+        let (cx, cy, cz) = chunk_storage::to_wrapped(x, y, z);
+        let previous = self.get_block(x, y, z);
+        if previous.block_state == block.block_state {
+            return BlockEdit::default();
+        }
+        self.make_full();
+        let offset = self.get_block_offset(cx, cy, cz);
+        match (!previous.is_air(), !block.is_air()) {
+            (false, false) => {}
+            (true, true) => self.blocks[offset] = block,
+            (false, true) => {
+                self.blocks.insert(offset, block);
+                for plane_offset in &mut self.offsets_at_plane[z..] {
+                    *plane_offset += 1;
+                }
+                for row_offset in &mut self.offsets_at_row[y + z * CHUNK_DIMENSION as usize + 1..] {
+                    *row_offset += 1;
+                }
+                self.update_solid_array(cx, cy, cz, true);
+            }
+            (true, false) => {
+                self.blocks.remove(offset);
+                for plane_offset in &mut self.offsets_at_plane[z..] {
+                    *plane_offset -= 1;
+                }
+                for row_offset in &mut self.offsets_at_row[y + z * CHUNK_DIMENSION as usize + 1..] {
+                    *row_offset -= 1;
+                }
+                self.update_solid_array(cx, cy, cz, false);
+            }
+        }
+        chunk_storage::get_block_edit(self, x, y, z, previous, block)
+    }
+
+    fn get_uniform_block(&self) -> Option<Block> {
+        self.uniform_block
+    }
+
+    fn solid_blocks(&self) -> ChunkBlockIterator<'_> {
+        ChunkBlockIterator::new(self)
+    }
+
+    fn get_memory_usage(&self) -> usize {
+        std::mem::size_of::<Chunk>()
+            + self.solid_array.capacity() / 8
+            + (self.offsets_at_plane.capacity() + self.offsets_at_row.capacity()) * std::mem::size_of::<u32>()
+            + self.blocks.capacity() * std::mem::size_of::<Block>()
+            + self.uniform_padding.as_ref().map_or(0, |uniform_padding| uniform_padding.capacity() / 8)
+    }
+}
+
+pub struct ChunkCreationIterator {
     position: WorldPosition,
     solid_array: BitVec,
     offsets_at_plane: Vec<u32>,
+    offsets_at_row: Vec<u32>,
     blocks: Vec<Block>,
     local_x: usize,
     local_y: usize,
@@ -128,18 +325,49 @@ impl ChunkCreationIterator {
         for _ in 0..(CHUNK_PLANE_SIZE_WRAPPED + CHUNK_DIMENSION_WRAPPED + 1) {
             solid_array.push(false);
         }
-        ChunkCreationIterator { position, solid_array, offsets_at_plane: Vec::new(), blocks: Vec::new(), local_x: 1, local_y: 1, local_z: 1, block_offset: 0 }
+        ChunkCreationIterator { position, solid_array, offsets_at_plane: Vec::new(), offsets_at_row: Vec::with_capacity(CHUNK_PLANE_SIZE as usize), blocks: Vec::new(), local_x: 1, local_y: 1, local_z: 1, block_offset: 0 }
     }
 
+    /// The chunk, as a uniform chunk if every block pushed was the same.
     pub fn return_chunk(self) -> Chunk {
-        Chunk { position: self.position, solid_array: self.solid_array, offsets_at_plane: self.offsets_at_plane, blocks: self.blocks }
+        if self.blocks.is_empty() {
+            return Chunk::uniform(&self.position, Block::from_state(AIR_STATE));
+        }
+        if self.blocks.len() == CHUNK_SIZE && self.blocks.iter().all(|block| block.block_state == self.blocks[0].block_state) {
+            return Chunk::uniform(&self.position, self.blocks[0]);
+        }
+        self.return_full_chunk()
+    }
+
+    pub fn return_full_chunk(self) -> Chunk {
+        Chunk { position: self.position, solid_array: self.solid_array, offsets_at_plane: self.offsets_at_plane, offsets_at_row: self.offsets_at_row, blocks: self.blocks, uniform_block: None, uniform_padding: None }
+    }
+
+    pub fn push_block_type(&mut self, block_type: BlockTypeSize) {
+        if block_type == AIR {
+            self.push_block(Block::from_state(AIR_STATE));
+        } else {
+            self.push_block(Block::new(block_type));
+        }
     }
 
-    pub fn push_block_type(&mut self, block_type: BlockType) {
-        let is_solid = block_type != BlockType::AIR;
-        self.solid_array.push(is_solid);
-        if is_solid {
-            self.blocks.push(Block::new(block_type));
+    pub fn push_block(&mut self, block: Block) {
+        if block.is_air() {
+            self.push_saved_block(None);
+        } else {
+            self.push_saved_block(Some(block));
+        }
+    }
+
+    /// Pushes a block loaded from saved data, whose state may not be valid until it is remapped, so
+    /// whether it is solid is given instead of looked up.
+    pub fn push_saved_block(&mut self, block: Option<Block>) {
+        if self.local_x == 1 {
+            self.offsets_at_row.push(self.block_offset);
+        }
+        self.solid_array.push(block.is_some());
+        if let Some(block) = block {
+            self.blocks.push(block);
             self.block_offset += 1;
         }
         self.local_x += 1;
@@ -181,7 +409,11 @@ impl<'a> ChunkBlockIterator<'a> {
         ChunkBlockIterator { chunk_ref, current_solid_offset: 0, current_block_offset: 0, has_started_iteration: false, local_x: 0, local_y: 0, local_z: 0 }
     }
 
-    pub fn get_next_block(&mut self) -> Option<((usize, usize, usize), &Block)> {
+    pub fn get_next_block(&mut self) -> Option<((usize, usize, usize), &'a Block)> {
+        if let Some(block) = self.chunk_ref.uniform_block.as_ref() {
+            return self.get_next_uniform_block(block);
+        }
+
         if self.has_started_iteration {
             self.current_block_offset += 1;
             self.current_solid_offset += 1;
@@ -241,4 +473,313 @@ impl<'a> ChunkBlockIterator<'a> {
 
         Some(((self.local_x, self.local_y, self.local_z), &self.chunk_ref.blocks[self.current_block_offset as usize]))
     }
-}
\ No newline at end of file
+
+    // Uniform chunks have no `solid_array` to walk, so `current_block_offset` counts the positions.
+    fn get_next_uniform_block(&mut self, block: &'a Block) -> Option<((usize, usize, usize), &'a Block)> {
+        if block.is_air() || self.current_block_offset >= CHUNK_SIZE {
+            return None;
+        }
+        let dimension = CHUNK_DIMENSION as usize;
+        let index = self.current_block_offset;
+        self.current_block_offset += 1;
+        Some(((index % dimension + 1, (index / dimension) % dimension + 1, index / CHUNK_PLANE_SIZE as usize + 1), block))
+    }
+}
+
+impl<'a> Iterator for ChunkBlockIterator<'a> {
+    type Item = ((usize, usize, usize), &'a Block);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.get_next_block()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use formats::formats::block_replacement_format::BlockReplacementFormat;
+    use fundamentals::consts::BlockStateSize;
+    use crate::tasks::tasks_processors::update_chunk_padding_processors::copy_padding;
+
+    const AIR_BLOCK: Block = Block { block_state: AIR_STATE };
+
+    fn get_dense_index(x: usize, y: usize, z: usize) -> usize {
+        x + y * CHUNK_DIMENSION as usize + z * CHUNK_PLANE_SIZE as usize
+    }
+
+    fn get_local_position(index: usize) -> (usize, usize, usize) {
+        let dimension = CHUNK_DIMENSION as usize;
+        (index % dimension, (index / dimension) % dimension, index / CHUNK_PLANE_SIZE as usize)
+    }
+
+    fn get_random_block(rng: &mut fastrand::Rng) -> Block {
+        let num_block_states = block_registry::get().get_num_block_states();
+        if rng.bool() {
+            AIR_BLOCK
+        } else {
+            Block::from_state(rng.usize(1..num_block_states) as BlockStateSize)
+        }
+    }
+
+    // On the heap, as a few dense chunks of wider block IDs don't fit on a test thread's stack.
+    fn get_dense(block: Block) -> Box<[Block; CHUNK_SIZE]> {
+        vec![block; CHUNK_SIZE].into_boxed_slice().try_into().unwrap()
+    }
+
+    fn get_random_chunk(rng: &mut fastrand::Rng, dense: &mut [Block; CHUNK_SIZE]) -> Chunk {
+        let mut cci = ChunkCreationIterator::new(WorldPosition::new(0, 0, 0));
+        for block in dense.iter_mut() {
+            *block = get_random_block(rng);
+            cci.push_block(*block);
+        }
+        cci.return_chunk()
+    }
+
+    // The faces of an edit, worked out from the dense blocks after it. The padding is empty, so
+    // faces towards other chunks are always visible. A face shows unless its neighbour occludes.
+    fn get_dense_faces(dense: &[Block; CHUNK_SIZE], x: usize, y: usize, z: usize, previous: Block) -> Vec<((usize, usize, usize), BlockSide)> {
+        let block = dense[get_dense_index(x, y, z)];
+        let mut faces = Vec::new();
+        for side in BlockSide::iter() {
+            let (dx, dy, dz) = chunk_storage::get_side_offset(side);
+            let (nx, ny, nz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+            let neighbour = if [nx, ny, nz].iter().all(|n| (0..CHUNK_DIMENSION).contains(n)) {
+                Some(dense[get_dense_index(nx as usize, ny as usize, nz as usize)])
+            } else {
+                None
+            };
+            let is_neighbour_occluding = neighbour.is_some_and(|neighbour| neighbour.occludes_neighbours());
+            let was_visible = !previous.is_air() && !is_neighbour_occluding;
+            let is_visible = !block.is_air() && !is_neighbour_occluding;
+            if was_visible != is_visible || (is_visible && previous.block_state != block.block_state) {
+                faces.push(((x, y, z), side));
+            }
+            if neighbour.is_some_and(|neighbour| !neighbour.is_air()) && previous.occludes_neighbours() != block.occludes_neighbours() {
+                faces.push(((nx as usize, ny as usize, nz as usize), chunk_storage::get_opposite_side(side)));
+            }
+        }
+        faces
+    }
+
+    fn assert_matches_dense(chunk: &Chunk, dense: &[Block; CHUNK_SIZE]) {
+        for (index, block) in dense.iter().enumerate() {
+            let (x, y, z) = get_local_position(index);
+            assert_eq!(chunk.get_block(x, y, z).block_state, block.block_state, "block at ({}, {}, {})", x, y, z);
+        }
+        if chunk.uniform_block.is_some() {
+            return;
+        }
+        let solid_blocks: Vec<BlockStateSize> = dense.iter().filter(|block| !block.is_air()).map(|block| block.block_state).collect();
+        assert_eq!(chunk.blocks.len(), solid_blocks.len());
+        assert_eq!(chunk.blocks.iter().map(|block| block.block_state).collect::<Vec<_>>(), solid_blocks);
+
+        let mut offsets_at_plane = Vec::new();
+        let mut offsets_at_row = Vec::new();
+        let mut num_solid = 0;
+        for (index, block) in dense.iter().enumerate() {
+            if index % CHUNK_DIMENSION as usize == 0 {
+                offsets_at_row.push(num_solid);
+            }
+            if !block.is_air() {
+                num_solid += 1;
+            }
+            if (index + 1) % CHUNK_PLANE_SIZE as usize == 0 {
+                offsets_at_plane.push(num_solid);
+            }
+        }
+        assert_eq!(chunk.offsets_at_plane, offsets_at_plane);
+        assert_eq!(chunk.offsets_at_row, offsets_at_row);
+    }
+
+    #[test]
+    fn incremental_row_offsets_match_rebuilt_index() {
+        let mut rng = fastrand::Rng::with_seed(18);
+        let mut dense = get_dense(AIR_BLOCK);
+        let mut chunk = get_random_chunk(&mut rng, &mut dense);
+        for _ in 0..20 {
+            for _ in 0..200 {
+                let (x, y, z) = (rng.usize(0..CHUNK_DIMENSION as usize), rng.usize(0..CHUNK_DIMENSION as usize), rng.usize(0..CHUNK_DIMENSION as usize));
+                chunk.set_block(x, y, z, get_random_block(&mut rng));
+            }
+
+            let mut cci = ChunkCreationIterator::new(chunk.position);
+            for index in 0..CHUNK_SIZE {
+                let (x, y, z) = get_local_position(index);
+                cci.push_block(chunk.get_block(x, y, z));
+            }
+            let rebuilt = cci.return_full_chunk();
+            assert_eq!(chunk.offsets_at_row, rebuilt.offsets_at_row);
+            assert_eq!(chunk.offsets_at_plane, rebuilt.offsets_at_plane);
+            // The plane walk is slow, so a sample of positions is compared with it.
+            for _ in 0..500 {
+                let (x, y, z) = get_local_position(rng.usize(0..CHUNK_SIZE));
+                let (cx, cy, cz) = chunk_storage::to_wrapped(x, y, z);
+                assert_eq!(chunk.get_block_offset(cx, cy, cz), chunk.get_block_offset_by_plane_walk(cx, cy, cz), "offset at ({}, {}, {})", x, y, z);
+            }
+        }
+    }
+
+    #[test]
+    fn set_block_matches_dense_model() {
+        let mut rng = fastrand::Rng::with_seed(17);
+        let mut dense = get_dense(AIR_BLOCK);
+        let solid = Chunk::solid(&WorldPosition::new(0, 0, 0));
+        let solid_block = solid.uniform_block.unwrap();
+        let chunks = [
+            (get_random_chunk(&mut rng, &mut dense), dense),
+            (Chunk::empty(&WorldPosition::new(0, 0, 0)), get_dense(AIR_BLOCK)),
+            (solid, get_dense(solid_block)),
+        ];
+        for (mut chunk, mut dense) in chunks {
+            assert_matches_dense(&chunk, &dense);
+            for edit in 0..2000 {
+                let (x, y, z) = (rng.usize(0..CHUNK_DIMENSION as usize), rng.usize(0..CHUNK_DIMENSION as usize), rng.usize(0..CHUNK_DIMENSION as usize));
+                let block = get_random_block(&mut rng);
+                let previous = dense[get_dense_index(x, y, z)];
+                dense[get_dense_index(x, y, z)] = block;
+
+                let block_edit = chunk.set_block(x, y, z, block);
+
+                let mut faces = block_edit.faces.clone();
+                let mut dense_faces = get_dense_faces(&dense, x, y, z, previous);
+                faces.sort_by_key(|(position, side)| (*position, *side as usize));
+                dense_faces.sort_by_key(|(position, side)| (*position, *side as usize));
+                assert_eq!(faces, dense_faces, "edit {} at ({}, {}, {})", edit, x, y, z);
+                if edit % 100 == 0 {
+                    assert_matches_dense(&chunk, &dense);
+                }
+            }
+            assert_matches_dense(&chunk, &dense);
+        }
+    }
+
+    fn get_full(block: Block) -> Chunk {
+        let mut cci = ChunkCreationIterator::new(WorldPosition::new(0, 0, 0));
+        for _ in 0..CHUNK_SIZE {
+            cci.push_block(block);
+        }
+        cci.return_full_chunk()
+    }
+
+    // Every padding bit, in the order of `chunk_storage::get_padding_index`.
+    fn get_padding(chunk: &Chunk) -> Vec<bool> {
+        let dimension = CHUNK_DIMENSION as usize;
+        BlockSide::iter()
+            .flat_map(|side| (1..=dimension).flat_map(move |j| (1..=dimension).map(move |i| chunk_storage::get_padding_position(side, i, j))))
+            .map(|(cx, cy, cz)| chunk.is_block_solid(cx, cy, cz))
+            .collect()
+    }
+
+    #[test]
+    fn uniform_chunks_answer_like_full_ones() {
+        let dimension = CHUNK_DIMENSION as usize;
+        let solid = Chunk::solid(&WorldPosition::new(0, 0, 0));
+        let solid_block = solid.uniform_block.unwrap();
+        let empty = Chunk::empty(&WorldPosition::new(0, 0, 0));
+        for (x, y, z) in [(0, 0, 0), (dimension - 1, dimension - 1, dimension - 1), (3, 17, 9)] {
+            let (cx, cy, cz) = chunk_storage::to_wrapped(x, y, z);
+            assert_eq!(solid.get_block(x, y, z).block_state, solid_block.block_state);
+            assert!(solid.is_block_solid(cx, cy, cz));
+            assert!(empty.get_block(x, y, z).is_air());
+            assert!(!empty.is_block_solid(cx, cy, cz));
+        }
+        // Padding, edges and corners stay empty until a neighbour is copied in.
+        for (cx, cy, cz) in [(0, 1, 1), (1, dimension + 1, 1), (0, 0, 0), (dimension + 1, dimension + 1, dimension + 1)] {
+            assert!(!solid.is_block_solid(cx, cy, cz), "({}, {}, {})", cx, cy, cz);
+        }
+        assert_eq!(solid.solid_blocks().count(), CHUNK_SIZE);
+        assert_eq!(empty.solid_blocks().count(), 0);
+        assert_eq!(solid.solid_blocks().map(|(position, _)| position).collect::<Vec<_>>(), get_full(solid_block).solid_blocks().map(|(position, _)| position).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn the_first_edit_makes_a_full_chunk_that_keeps_its_padding() {
+        let dimension = CHUNK_DIMENSION as usize;
+        let mut chunk = Chunk::solid(&WorldPosition::new(0, 0, 0));
+        let solid_block = chunk.uniform_block.unwrap();
+        let padding_positions = [
+            chunk_storage::get_padding_position(BlockSide::FRONT, 3, 4),
+            chunk_storage::get_padding_position(BlockSide::TOP, 1, dimension),
+            chunk_storage::get_padding_position(BlockSide::RIGHT, dimension, dimension),
+        ];
+        for (cx, cy, cz) in padding_positions {
+            chunk.update_padding(cx, cy, cz, true);
+        }
+
+        chunk.set_block(0, 0, 0, AIR_BLOCK);
+
+        assert!(!chunk.is_uniform());
+        let mut dense = get_dense(solid_block);
+        dense[0] = AIR_BLOCK;
+        assert_matches_dense(&chunk, &dense);
+        let mut expected = get_full(solid_block);
+        expected.set_block(0, 0, 0, AIR_BLOCK);
+        for (cx, cy, cz) in padding_positions {
+            expected.update_solid_array(cx, cy, cz, true);
+        }
+        assert_eq!(chunk.solid_array, expected.solid_array);
+        assert_eq!(get_padding(&chunk).iter().filter(|is_solid| **is_solid).count(), padding_positions.len());
+    }
+
+    #[test]
+    fn return_chunk_collapses_chunks_of_one_block() {
+        let solid_block = Chunk::solid(&WorldPosition::new(0, 0, 0)).uniform_block.unwrap();
+        for block in [AIR_BLOCK, solid_block] {
+            let mut cci = ChunkCreationIterator::new(WorldPosition::new(0, 0, 0));
+            for _ in 0..CHUNK_SIZE {
+                cci.push_block(block);
+            }
+            let chunk = cci.return_chunk();
+            assert_eq!(chunk.uniform_block.map(|block| block.block_state), Some(block.block_state));
+            assert!(chunk.blocks.is_empty() && chunk.solid_array.is_empty());
+        }
+
+        let mut dense = get_dense(solid_block);
+        dense[CHUNK_SIZE - 1] = AIR_BLOCK;
+        let mut cci = ChunkCreationIterator::new(WorldPosition::new(0, 0, 0));
+        for block in dense.iter() {
+            cci.push_block(*block);
+        }
+        let chunk = cci.return_chunk();
+        assert!(!chunk.is_uniform());
+        assert_matches_dense(&chunk, &dense);
+    }
+
+    #[test]
+    fn copying_padding_from_a_uniform_neighbour_matches_reading_it() {
+        let solid_block = Chunk::solid(&WorldPosition::new(0, 0, 0)).uniform_block.unwrap();
+        let get_targets = || {
+            let mut dense = get_dense(AIR_BLOCK);
+            [get_random_chunk(&mut fastrand::Rng::with_seed(19), &mut dense), Chunk::solid(&WorldPosition::new(0, 0, 0))]
+        };
+        let (mut targets, mut expected_targets) = (get_targets(), get_targets());
+        // Solid neighbours fill the padding in, then AIR ones clear it again.
+        for block in [solid_block, AIR_BLOCK] {
+            let (uniform_neighbour, full_neighbour) = (Chunk::uniform(&WorldPosition::new(0, 0, 0), block), get_full(block));
+            for (target, expected_target) in targets.iter_mut().zip(expected_targets.iter_mut()) {
+                for side in BlockSide::iter() {
+                    let has_changed = copy_padding(target, side, &uniform_neighbour);
+                    assert_eq!(has_changed, copy_padding(expected_target, side, &full_neighbour), "{:?} next to {}", side, block.block_state);
+                }
+                assert_eq!(get_padding(target), get_padding(expected_target));
+            }
+        }
+    }
+
+    #[test]
+    fn a_uniform_chunk_remapped_to_air_drops_its_padding() {
+        let block_registry = block_registry::get();
+        let mut chunk = Chunk::solid(&WorldPosition::new(0, 0, 0));
+        let solid_state = chunk.uniform_block.unwrap().block_state;
+        chunk.update_padding(0, 1, 1, true);
+        let mut saved_id_table = block_registry.get_id_table();
+        saved_id_table.block_states[solid_state as usize] = String::from("GONE");
+        let block_replacements = BlockReplacementFormat { format_version: 1, replacements: [(String::from("GONE"), String::from("AIR"))].into() };
+        let remapper = BlockIdRemapper::new(&saved_id_table, block_registry, &block_replacements).unwrap();
+
+        chunk.remap_blocks(&remapper).unwrap();
+
+        assert!(chunk.uniform_block.is_some_and(|block| block.is_air()));
+        assert!(chunk.uniform_padding.is_none());
+        assert!(!chunk.is_block_solid(0, 1, 1));
+    }
+}
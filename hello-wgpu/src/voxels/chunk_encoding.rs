@@ -0,0 +1,393 @@
+use std::fmt;
+use derivables::block::Block;
+use formats::formats::checksum::crc32;
+use fundamentals::block_registry::AIR_STATE;
+use fundamentals::consts::{BlockStateSize, CHUNK_DIMENSION, CHUNK_PLANE_SIZE, CHUNK_SIZE};
+use fundamentals::world_position::WorldPosition;
+use super::chunk::{Chunk, ChunkCreationIterator};
+use super::chunk_storage::ChunkStorage;
+
+pub const CHUNK_MAGIC: [u8; 4] = *b"WCHK";
+pub const CHUNK_ENCODING_VERSION: u32 = 1;
+
+const FULL_LAYOUT: u8 = 0;
+const UNIFORM_LAYOUT: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkCompression {
+    None = 0,
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+impl ChunkCompression {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ChunkCompression::None),
+            1 => Some(ChunkCompression::Lz4),
+            2 => Some(ChunkCompression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkEncodingError {
+    BadMagic { found: Vec<u8> },
+    UnsupportedVersion { version: u32 },
+    UnknownCompression { compression: u8 },
+    InvalidBodyLength { length: usize },
+    Truncated { offset: usize, needed: usize, length: usize },
+    ChecksumMismatch { expected: u32, found: u32 },
+    Decompression { message: String },
+    UnknownLayout { layout: u8 },
+    BlockCountMismatch { expected: u32, found: u32 },
+    AirInBlocks { block_index: usize },
+    PlaneOffsetMismatch { plane: usize, expected: u32, found: u32 },
+    TrailingBytes { count: usize },
+}
+
+impl fmt::Display for ChunkEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkEncodingError::BadMagic { found } =>
+                write!(f, "not a chunk, expected magic {:?} but found {:?}", CHUNK_MAGIC, found),
+            ChunkEncodingError::UnsupportedVersion { version } =>
+                write!(f, "chunk version {} is not supported, this build reads version {}", version, CHUNK_ENCODING_VERSION),
+            ChunkEncodingError::UnknownCompression { compression } =>
+                write!(f, "unknown chunk compression {}", compression),
+            ChunkEncodingError::InvalidBodyLength { length } =>
+                write!(f, "chunk body of {} bytes is longer than any chunk", length),
+            ChunkEncodingError::Truncated { offset, needed, length } =>
+                write!(f, "chunk is truncated, {} bytes needed at offset {} but there are {}", needed, offset, length),
+            ChunkEncodingError::ChecksumMismatch { expected, found } =>
+                write!(f, "chunk is corrupt, checksum {:08x} does not match {:08x}", found, expected),
+            ChunkEncodingError::Decompression { message } =>
+                write!(f, "chunk could not be decompressed: {}", message),
+            ChunkEncodingError::UnknownLayout { layout } =>
+                write!(f, "unknown chunk layout {}", layout),
+            ChunkEncodingError::BlockCountMismatch { expected, found } =>
+                write!(f, "chunk has {} solid positions but {} blocks", found, expected),
+            ChunkEncodingError::AirInBlocks { block_index } =>
+                write!(f, "chunk block {} is AIR at a solid position", block_index),
+            ChunkEncodingError::PlaneOffsetMismatch { plane, expected, found } =>
+                write!(f, "chunk plane {} ends at block {} but the solid positions put it at {}", plane, expected, found),
+            ChunkEncodingError::TrailingBytes { count } =>
+                write!(f, "chunk has {} unexpected bytes after its blocks", count),
+        }
+    }
+}
+
+impl Chunk {
+    /// Encodes the chunk for saving.
+    ///
+    /// Layout, little endian: the magic `WCHK`, then u32 version, u8 compression and the u32 length of
+    /// the uncompressed body. The body follows, compressed as given, and a CRC-32 of everything before
+    /// it ends the encoding. The body holds the position as three i32 and a u8 layout. A uniform chunk
+    /// stores its one block state. A full chunk stores a u32 block count, the CHUNK_SIZE solid bits of
+    /// its interior in z, y, x order from the lowest bit of each byte, `offsets_at_plane` as u32 and
+    /// the states of `blocks`. The padding isn't stored; the padding tasks copy it in again once the
+    /// neighbouring chunks are loaded.
+    pub fn to_bytes(&self, compression: ChunkCompression) -> Vec<u8> {
+        let mut body = Vec::new();
+        for value in [self.position.x, self.position.y, self.position.z] {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        match self.uniform_block {
+            Some(block) => {
+                body.push(UNIFORM_LAYOUT);
+                body.extend_from_slice(&block.block_state.to_le_bytes());
+            }
+            None => {
+                body.push(FULL_LAYOUT);
+                body.extend_from_slice(&(self.blocks.len() as u32).to_le_bytes());
+                let mut solid_bits = vec![0u8; CHUNK_SIZE / 8];
+                for index in 0..CHUNK_SIZE {
+                    let (cx, cy, cz) = get_wrapped_position(index);
+                    if self.is_block_solid(cx, cy, cz) {
+                        solid_bits[index / 8] |= 1 << (index % 8);
+                    }
+                }
+                body.extend_from_slice(&solid_bits);
+                for plane_offset in &self.offsets_at_plane {
+                    body.extend_from_slice(&plane_offset.to_le_bytes());
+                }
+                for block in &self.blocks {
+                    body.extend_from_slice(&block.block_state.to_le_bytes());
+                }
+            }
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&CHUNK_MAGIC);
+        bytes.extend_from_slice(&CHUNK_ENCODING_VERSION.to_le_bytes());
+        bytes.push(compression as u8);
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        match compression {
+            ChunkCompression::None => bytes.extend_from_slice(&body),
+            ChunkCompression::Lz4 => bytes.extend_from_slice(&lz4_flex::block::compress(&body)),
+            ChunkCompression::Zstd => bytes.extend_from_slice(&ruzstd::encoding::compress_to_vec(&body[..], ruzstd::encoding::CompressionLevel::Fastest)),
+        }
+        bytes.extend_from_slice(&crc32(&bytes).to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a chunk written by `to_bytes`. Its block states are the ones it was saved with, so it
+    /// has to go through `remap_blocks` before use.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChunkEncodingError> {
+        if bytes.len() < 4 {
+            return Err(ChunkEncodingError::Truncated { offset: 0, needed: 4, length: bytes.len() });
+        }
+        let (stored, checksum) = bytes.split_at(bytes.len() - 4);
+        let mut reader = ByteReader { bytes: stored, offset: 0 };
+
+        let magic = reader.read_bytes(4)?;
+        if magic != CHUNK_MAGIC {
+            return Err(ChunkEncodingError::BadMagic { found: magic.to_vec() });
+        }
+        let version = reader.read_u32()?;
+        if version != CHUNK_ENCODING_VERSION {
+            return Err(ChunkEncodingError::UnsupportedVersion { version });
+        }
+        let expected_checksum = u32::from_le_bytes(checksum.try_into().unwrap());
+        let found_checksum = crc32(stored);
+        if found_checksum != expected_checksum {
+            return Err(ChunkEncodingError::ChecksumMismatch { expected: expected_checksum, found: found_checksum });
+        }
+        let compression = reader.read_u8()?;
+        let compression = ChunkCompression::from_u8(compression).ok_or(ChunkEncodingError::UnknownCompression { compression })?;
+        let body_length = reader.read_u32()? as usize;
+        if body_length > get_max_body_length() {
+            return Err(ChunkEncodingError::InvalidBodyLength { length: body_length });
+        }
+
+        let stored_body = reader.read_bytes(stored.len() - reader.offset)?;
+        let body = match compression {
+            ChunkCompression::None => stored_body.to_vec(),
+            ChunkCompression::Lz4 => lz4_flex::block::decompress(stored_body, body_length)
+                .map_err(|e| ChunkEncodingError::Decompression { message: e.to_string() })?,
+            ChunkCompression::Zstd => {
+                let mut body = Vec::with_capacity(body_length);
+                ruzstd::decoding::FrameDecoder::new().decode_all_to_vec(stored_body, &mut body)
+                    .map_err(|e| ChunkEncodingError::Decompression { message: e.to_string() })?;
+                body
+            }
+        };
+        if body.len() != body_length {
+            return Err(ChunkEncodingError::Decompression { message: format!("expected {} bytes but got {}", body_length, body.len()) });
+        }
+
+        let mut reader = ByteReader { bytes: &body, offset: 0 };
+        let position = WorldPosition::new(reader.read_i32()?, reader.read_i32()?, reader.read_i32()?);
+        let chunk = match reader.read_u8()? {
+            UNIFORM_LAYOUT => Chunk::uniform(&position, Block::from_state(reader.read_block_state()?)),
+            FULL_LAYOUT => read_full_chunk(&mut reader, position)?,
+            layout => return Err(ChunkEncodingError::UnknownLayout { layout }),
+        };
+        if reader.offset != body.len() {
+            return Err(ChunkEncodingError::TrailingBytes { count: body.len() - reader.offset });
+        }
+        Ok(chunk)
+    }
+}
+
+fn read_full_chunk(reader: &mut ByteReader, position: WorldPosition) -> Result<Chunk, ChunkEncodingError> {
+    let num_blocks = reader.read_u32()?;
+    let solid_bits = reader.read_bytes(CHUNK_SIZE / 8)?;
+    let num_solid = solid_bits.iter().map(|byte| byte.count_ones()).sum::<u32>();
+    if num_solid != num_blocks {
+        return Err(ChunkEncodingError::BlockCountMismatch { expected: num_blocks, found: num_solid });
+    }
+    let mut offsets_at_plane = Vec::with_capacity(CHUNK_DIMENSION as usize);
+    for _ in 0..CHUNK_DIMENSION {
+        offsets_at_plane.push(reader.read_u32()?);
+    }
+
+    let mut cci = ChunkCreationIterator::new(position);
+    let mut block_index = 0;
+    for index in 0..CHUNK_SIZE {
+        if solid_bits[index / 8] & (1 << (index % 8)) == 0 {
+            cci.push_saved_block(None);
+        } else {
+            let block_state = reader.read_block_state()?;
+            if block_state == AIR_STATE {
+                return Err(ChunkEncodingError::AirInBlocks { block_index });
+            }
+            cci.push_saved_block(Some(Block::from_state(block_state)));
+            block_index += 1;
+        }
+        if (index + 1) % CHUNK_PLANE_SIZE as usize == 0 {
+            let plane = index / CHUNK_PLANE_SIZE as usize;
+            if offsets_at_plane[plane] != block_index as u32 {
+                return Err(ChunkEncodingError::PlaneOffsetMismatch { plane, expected: offsets_at_plane[plane], found: block_index as u32 });
+            }
+        }
+    }
+    Ok(cci.return_chunk())
+}
+
+fn get_wrapped_position(index: usize) -> (usize, usize, usize) {
+    let dimension = CHUNK_DIMENSION as usize;
+    (index % dimension + 1, (index / dimension) % dimension + 1, index / CHUNK_PLANE_SIZE as usize + 1)
+}
+
+// The body of a full chunk with every position solid.
+fn get_max_body_length() -> usize {
+    3 * 4 + 1 + 4 + CHUNK_SIZE / 8 + CHUNK_DIMENSION as usize * 4 + CHUNK_SIZE * std::mem::size_of::<BlockStateSize>()
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], ChunkEncodingError> {
+        if self.bytes.len() - self.offset < count {
+            return Err(ChunkEncodingError::Truncated { offset: self.offset, needed: count, length: self.bytes.len() });
+        }
+        let bytes = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ChunkEncodingError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ChunkEncodingError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, ChunkEncodingError> {
+        Ok(i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_block_state(&mut self) -> Result<BlockStateSize, ChunkEncodingError> {
+        Ok(BlockStateSize::from_le_bytes(self.read_bytes(std::mem::size_of::<BlockStateSize>())?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fundamentals::block_registry;
+    use fundamentals::runtime_config::RuntimeConfig;
+
+    const COMPRESSIONS: [ChunkCompression; 3] = [ChunkCompression::None, ChunkCompression::Lz4, ChunkCompression::Zstd];
+    const HEADER_LENGTH: usize = 4 + 4 + 1 + 4;
+
+    fn get_random_position(rng: &mut fastrand::Rng) -> WorldPosition {
+        WorldPosition::new(rng.i32(-1000..1000), rng.i32(-1000..1000), rng.i32(-1000..1000))
+    }
+
+    // A chunk with each block solid with probability `solid_chance`, in any registered state.
+    fn get_random_chunk(rng: &mut fastrand::Rng, solid_chance: f32) -> Chunk {
+        let num_block_states = block_registry::get().get_num_block_states();
+        let mut cci = ChunkCreationIterator::new(get_random_position(rng));
+        for _ in 0..CHUNK_SIZE {
+            if rng.f32() < solid_chance {
+                cci.push_block(Block::from_state(rng.usize(1..num_block_states) as BlockStateSize));
+            } else {
+                cci.push_block(Block::from_state(AIR_STATE));
+            }
+        }
+        cci.return_chunk()
+    }
+
+    fn get_test_chunks() -> Vec<Chunk> {
+        let mut rng = fastrand::Rng::with_seed(21);
+        let options = RuntimeConfig::load().chunk_generation_options;
+        let mut chunks = Vec::new();
+        for _ in 0..4 {
+            chunks.push(Chunk::perlin(&get_random_position(&mut rng), &options));
+            chunks.push(Chunk::checkerboard(&get_random_position(&mut rng)));
+            chunks.push(Chunk::solid(&get_random_position(&mut rng)));
+            chunks.push(Chunk::empty(&get_random_position(&mut rng)));
+            let solid_chance = rng.f32();
+            chunks.push(get_random_chunk(&mut rng, solid_chance));
+        }
+        chunks.push(get_random_chunk(&mut rng, 1.0));
+        chunks.push(get_random_chunk(&mut rng, 0.001));
+        chunks
+    }
+
+    fn assert_chunks_equal(chunk: &Chunk, decoded: &Chunk) {
+        assert_eq!(chunk.position, decoded.position);
+        assert_eq!(chunk.uniform_block.map(|block| block.block_state), decoded.uniform_block.map(|block| block.block_state));
+        assert_eq!(chunk.offsets_at_plane, decoded.offsets_at_plane);
+        assert_eq!(chunk.offsets_at_row, decoded.offsets_at_row);
+        assert_eq!(chunk.blocks.iter().map(|block| block.block_state).collect::<Vec<_>>(), decoded.blocks.iter().map(|block| block.block_state).collect::<Vec<_>>());
+        for index in 0..CHUNK_SIZE {
+            let (cx, cy, cz) = get_wrapped_position(index);
+            assert_eq!(chunk.is_block_solid(cx, cy, cz), decoded.is_block_solid(cx, cy, cz), "solid bit at ({}, {}, {})", cx, cy, cz);
+        }
+    }
+
+    // Rewrites an uncompressed encoding with `change` applied to its body, with the length and
+    // checksum fixed up so only the body is wrong.
+    fn with_changed_body(bytes: &[u8], change: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let mut body = bytes[HEADER_LENGTH..bytes.len() - 4].to_vec();
+        change(&mut body);
+        let mut changed = bytes[..HEADER_LENGTH - 4].to_vec();
+        changed.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        changed.extend_from_slice(&body);
+        changed.extend_from_slice(&crc32(&changed).to_le_bytes());
+        changed
+    }
+
+    #[test]
+    fn round_trips_chunks_with_every_compression() {
+        for chunk in get_test_chunks() {
+            for compression in COMPRESSIONS {
+                let decoded = Chunk::from_bytes(&chunk.to_bytes(compression)).unwrap_or_else(|e| panic!("{:?} chunk {}: {}", compression, chunk.position, e));
+                assert_chunks_equal(&chunk, &decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_corrupted_checksums() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        for chunk in get_test_chunks() {
+            for compression in COMPRESSIONS {
+                let mut bytes = chunk.to_bytes(compression);
+                // Past the magic and version, which are checked before the checksum.
+                let index = rng.usize(8..bytes.len());
+                bytes[index] ^= 1 << rng.u8(0..8);
+                assert!(matches!(Chunk::from_bytes(&bytes), Err(ChunkEncodingError::ChecksumMismatch { .. })), "{:?} byte {}", compression, index);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_chunks() {
+        for chunk in get_test_chunks() {
+            for compression in COMPRESSIONS {
+                let bytes = chunk.to_bytes(compression);
+                for length in [0, 3, 7, HEADER_LENGTH, bytes.len() / 2, bytes.len() - 1] {
+                    assert!(Chunk::from_bytes(&bytes[..length]).is_err(), "{:?} cut to {} of {} bytes", compression, length, bytes.len());
+                }
+            }
+        }
+        let bytes = Chunk::empty(&WorldPosition::new(0, 0, 0)).to_bytes(ChunkCompression::None);
+        let truncated = with_changed_body(&bytes, |body| { body.pop(); });
+        assert!(matches!(Chunk::from_bytes(&truncated), Err(ChunkEncodingError::Truncated { .. })));
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = Chunk::solid(&WorldPosition::new(1, 2, 3)).to_bytes(ChunkCompression::Lz4);
+        bytes[0] = b'X';
+        assert_eq!(Chunk::from_bytes(&bytes).err(), Some(ChunkEncodingError::BadMagic { found: b"XCHK".to_vec() }));
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut rng = fastrand::Rng::with_seed(3);
+        for chunk in [Chunk::solid(&WorldPosition::new(0, 0, 0)), get_random_chunk(&mut rng, 0.5)] {
+            let bytes = with_changed_body(&chunk.to_bytes(ChunkCompression::None), |body| body.extend_from_slice(&[1, 2, 3]));
+            assert_eq!(Chunk::from_bytes(&bytes).err(), Some(ChunkEncodingError::TrailingBytes { count: 3 }));
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use derivables::block::Block;
+use fundamentals::consts::{CHUNK_DIMENSION, CHUNK_PLANE_SIZE};
+use fundamentals::enums::block_side::BlockSide;
+use fundamentals::world_position::WorldPosition;
+use strum::IntoEnumIterator;
+
+/// What `ChunkStorage::set_block` changed. `faces` are the block faces in this chunk, in chunk-local
+/// coordinates, that appeared, disappeared or changed texture. `neighbour_chunks` are the adjacent
+/// chunks whose padding has to be updated and whose facing side has to be remeshed.
+#[derive(Debug, Default, PartialEq)]
+pub struct BlockEdit {
+    pub faces: Vec<((usize, usize, usize), BlockSide)>,
+    pub neighbour_chunks: Vec<WorldPosition>,
+}
+
+impl BlockEdit {
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty() && self.neighbour_chunks.is_empty()
+    }
+
+    /// The sides of this chunk's mesh to rebuild, for `Mesh::cull_side` and `Mesh::greedy_sided`.
+    pub fn get_sides(&self) -> Vec<BlockSide> {
+        BlockSide::iter().filter(|side| self.faces.iter().any(|(_, face_side)| face_side == side)).collect()
+    }
+}
+
+/// The block layout the meshers and padding processors work with, so layouts can be swapped and
+/// compared. `is_block_solid` and `update_padding` take wrapped coordinates, where 0 and
+/// CHUNK_DIMENSION + 1 are the padding copied from neighbouring chunks; `get_block` and `set_block`
+/// take chunk-local ones, each from 0 to CHUNK_DIMENSION - 1. The padding only records whether the
+/// neighbouring border block occludes, as that is all the faces of this chunk depend on.
+pub trait ChunkStorage {
+    type SolidBlocks<'a>: Iterator<Item = ((usize, usize, usize), &'a Block)> where Self: 'a;
+
+    fn get_position(&self) -> WorldPosition;
+
+    fn is_block_solid(&self, cx: usize, cy: usize, cz: usize) -> bool;
+
+    /// Whether the block at wrapped coordinates hides the faces of the blocks next to it, see
+    /// `BlockProperties::occludes_neighbours`.
+    fn is_block_occluding(&self, cx: usize, cy: usize, cz: usize) -> bool {
+        if !self.is_block_solid(cx, cy, cz) {
+            return false;
+        }
+        let dimension = CHUNK_DIMENSION as usize;
+        if ![cx, cy, cz].iter().all(|c| (1..=dimension).contains(c)) {
+            return true;
+        }
+        self.get_block(cx - 1, cy - 1, cz - 1).occludes_neighbours()
+    }
+
+    /// Sets a padding bit from whether the neighbouring chunk's border block occludes.
+    fn update_padding(&mut self, cx: usize, cy: usize, cz: usize, solid_value: bool);
+
+    /// The block at chunk-local coordinates. Empty positions are AIR.
+    fn get_block(&self, x: usize, y: usize, z: usize) -> Block;
+
+    /// The block filling the whole chunk, if it holds only one kind.
+    fn get_uniform_block(&self) -> Option<Block>;
+
+    /// Places `block` at chunk-local coordinates and returns the faces and neighbouring chunks to remesh.
+    fn set_block(&mut self, x: usize, y: usize, z: usize, block: Block) -> BlockEdit;
+
+    /// Every solid block with its wrapped coordinates, z outermost and x innermost.
+    fn solid_blocks(&self) -> Self::SolidBlocks<'_>;
+
+    /// Bytes held by the chunk, its allocations included.
+    fn get_memory_usage(&self) -> usize;
+}
+
+/// Works out the `BlockEdit` of replacing `previous` with `block` at chunk-local coordinates, once
+/// `chunk` holds the new block.
+pub fn get_block_edit<C: ChunkStorage + ?Sized>(chunk: &C, x: usize, y: usize, z: usize, previous: Block, block: Block) -> BlockEdit {
+    let (cx, cy, cz) = to_wrapped(x, y, z);
+    let was_solid = !previous.is_air();
+    let is_solid = !block.is_air();
+    let is_occlusion_changed = previous.occludes_neighbours() != block.occludes_neighbours();
+
+    // The block's faces towards neighbours that don't occlude appear, disappear or change texture,
+    // and when its occlusion changes so do the faces of solid neighbours towards it. Neighbours
+    // across the border belong to the adjacent chunk.
+    let mut block_edit = BlockEdit::default();
+    let position = chunk.get_position();
+    for side in BlockSide::iter() {
+        let (dx, dy, dz) = get_side_offset(side);
+        let (nx, ny, nz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+        let (wx, wy, wz) = ((cx as i32 + dx) as usize, (cy as i32 + dy) as usize, (cz as i32 + dz) as usize);
+        let is_neighbour_occluding = chunk.is_block_occluding(wx, wy, wz);
+        if !is_neighbour_occluding && (was_solid != is_solid || (is_solid && previous.block_state != block.block_state)) {
+            block_edit.faces.push(((x, y, z), side));
+        }
+        if [nx, ny, nz].iter().any(|n| *n < 0 || *n >= CHUNK_DIMENSION) {
+            if is_occlusion_changed {
+                block_edit.neighbour_chunks.push(WorldPosition::new(position.x + dx, position.y + dy, position.z + dz));
+            }
+        } else if is_occlusion_changed && chunk.is_block_solid(wx, wy, wz) {
+            block_edit.faces.push(((nx as usize, ny as usize, nz as usize), get_opposite_side(side)));
+        }
+    }
+    block_edit
+}
+
+pub fn to_wrapped(x: usize, y: usize, z: usize) -> (usize, usize, usize) {
+    assert!(x < CHUNK_DIMENSION as usize && y < CHUNK_DIMENSION as usize && z < CHUNK_DIMENSION as usize, "({}, {}, {}) is outside the chunk", x, y, z);
+    (x + 1, y + 1, z + 1)
+}
+
+pub fn get_side_offset(side: BlockSide) -> (i32, i32, i32) {
+    match side {
+        BlockSide::FRONT => (-1, 0, 0),
+        BlockSide::BACK => (1, 0, 0),
+        BlockSide::LEFT => (0, 0, -1),
+        BlockSide::RIGHT => (0, 0, 1),
+        BlockSide::TOP => (0, 1, 0),
+        BlockSide::BOTTOM => (0, -1, 0),
+    }
+}
+
+pub fn get_opposite_side(side: BlockSide) -> BlockSide {
+    match side {
+        BlockSide::FRONT => BlockSide::BACK,
+        BlockSide::BACK => BlockSide::FRONT,
+        BlockSide::LEFT => BlockSide::RIGHT,
+        BlockSide::RIGHT => BlockSide::LEFT,
+        BlockSide::TOP => BlockSide::BOTTOM,
+        BlockSide::BOTTOM => BlockSide::TOP,
+    }
+}
+
+/// The wrapped position of a padding block, `i` and `j` from 1 to CHUNK_DIMENSION across the side.
+pub fn get_padding_position(side: BlockSide, i: usize, j: usize) -> (usize, usize, usize) {
+    let outside = CHUNK_DIMENSION as usize + 1;
+    match side {
+        BlockSide::FRONT => (0, i, j),
+        BlockSide::BACK => (outside, i, j),
+        BlockSide::LEFT => (i, j, 0),
+        BlockSide::RIGHT => (i, j, outside),
+        BlockSide::TOP => (i, outside, j),
+        BlockSide::BOTTOM => (i, 0, j),
+    }
+}
+
+/// Index of a padding block in six planes of CHUNK_PLANE_SIZE in BlockSide order, or None for the
+/// interior and for the edges and corners of the padding, which no side fills in.
+pub fn get_padding_index(cx: usize, cy: usize, cz: usize) -> Option<usize> {
+    let dimension = CHUNK_DIMENSION as usize;
+    let inside = |c: usize| (1..=dimension).contains(&c);
+    let (side, i, j) = match (cx, cy, cz) {
+        (0, cy, cz) if inside(cy) && inside(cz) => (BlockSide::FRONT, cy, cz),
+        (cx, cy, cz) if cx == dimension + 1 && inside(cy) && inside(cz) => (BlockSide::BACK, cy, cz),
+        (cx, cy, 0) if inside(cx) && inside(cy) => (BlockSide::LEFT, cx, cy),
+        (cx, cy, cz) if cz == dimension + 1 && inside(cx) && inside(cy) => (BlockSide::RIGHT, cx, cy),
+        (cx, cy, cz) if cy == dimension + 1 && inside(cx) && inside(cz) => (BlockSide::TOP, cx, cz),
+        (cx, 0, cz) if inside(cx) && inside(cz) => (BlockSide::BOTTOM, cx, cz),
+        _ => return None,
+    };
+    Some(side as usize * CHUNK_PLANE_SIZE as usize + (i - 1) + (j - 1) * dimension)
+}
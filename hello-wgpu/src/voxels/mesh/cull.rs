@@ -1,13 +1,13 @@
 use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide, logi};
 use web_time::Instant;
 
-use crate::voxels::{mesh::Mesh, chunk::{ChunkBlockIterator, Chunk}};
+use crate::voxels::{mesh::Mesh, chunk_storage::ChunkStorage};
 
-pub fn cull(chunk: &Chunk, index: u32) -> Mesh {
+pub fn cull<C: ChunkStorage>(chunk: &C, index: u32) -> Mesh {
     cull_side(chunk, index, &vec![BlockSide::FRONT, BlockSide::BACK, BlockSide::LEFT, BlockSide::RIGHT, BlockSide::TOP, BlockSide::BOTTOM])
 }
 
-pub fn cull_side(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Mesh {
+pub fn cull_side<C: ChunkStorage>(chunk: &C, index: u32, sides: &Vec<BlockSide>) -> Mesh {
     let now = Instant::now();
     let mut mesh = Mesh::new();
 
@@ -16,11 +16,9 @@ pub fn cull_side(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Mesh {
 
     let mut num_faces_generated = [0;6];
 
-    let mut cbi = ChunkBlockIterator::new(chunk);
-
-    while let Some(((i,j,k), block)) = cbi.get_next_block() {
+    for ((i,j,k), block) in chunk.solid_blocks() {
         for side in sides.iter() {
-            if !Mesh::is_adjacent_blocks_solid_side(chunk, i, j, k, *side) {
+            if !Mesh::is_adjacent_block_occluding_side(chunk, i, j, k, *side) {
                 vertex_arr[*side as usize].append(&mut Mesh::generate_cube_side(WorldPosition::new(i as i32-1,j as i32-1,k as i32-1), block.get_texture_indices(), index, *side));
                 index_arr[*side as usize].append(&mut Mesh::generate_cube_indices_side(*side, num_faces_generated[*side as usize]));
                 num_faces_generated[*side as usize] += 1;
@@ -34,7 +32,7 @@ pub fn cull_side(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Mesh {
 
     let after = Instant::now();
     let time  = (after-now).as_millis();
-    let cpos = chunk.position;
+    let cpos = chunk.get_position();
     logi!("Cull mesh for position {} sides {:?} took {} milliseconds", cpos, sides, time);
 
     mesh
@@ -6,12 +6,12 @@ pub struct Face {
     pub ll: (usize, usize, usize),
     pub ur: (usize, usize, usize),
     pub ul: (usize, usize, usize),
-    pub block_type_int: usize,
+    pub block_state_int: usize,
     pub block_side: BlockSide
 }
 
 impl Face {
-    pub fn new(i: usize, j: usize, k: usize, block_type_int: usize, block_side: BlockSide) -> Self {
+    pub fn new(i: usize, j: usize, k: usize, block_state_int: usize, block_side: BlockSide) -> Self {
         match block_side {
             BlockSide::FRONT => {
                 Face {
@@ -19,7 +19,7 @@ impl Face {
                     lr: (i, j, k+1),
                     ul: (i, j+1, k),
                     ur: (i, j+1, k+1),
-                    block_type_int,
+                    block_state_int,
                     block_side
                 }
             },
@@ -30,7 +30,7 @@ impl Face {
                     lr: (i+1, j, k),
                     ul: (i+1, j+1, k+1),
                     ur: (i+1, j+1, k),
-                    block_type_int,
+                    block_state_int,
                     block_side
                 }
             }
@@ -41,7 +41,7 @@ impl Face {
                     lr: (i, j, k),
                     ul: (i+1, j+1, k),
                     ur: (i, j+1, k),
-                    block_type_int,
+                    block_state_int,
                     block_side
                 }
             }
@@ -52,7 +52,7 @@ impl Face {
                     lr: (i+1, j, k+1),
                     ul: (i, j+1, k+1),
                     ur: (i+1, j+1, k+1),
-                    block_type_int,
+                    block_state_int,
                     block_side
                 }
             }
@@ -63,7 +63,7 @@ impl Face {
                     lr: (i, j+1, k+1),
                     ul: (i+1, j+1, k),
                     ur: (i+1, j+1, k+1),
-                    block_type_int,
+                    block_state_int,
                     block_side
                 }
             }
@@ -74,7 +74,7 @@ impl Face {
                     lr: (i, j, k),
                     ul: (i+1, j, k+1),
                     ur: (i+1, j, k),
-                    block_type_int,
+                    block_state_int,
                     block_side
                 }
             }
@@ -83,14 +83,14 @@ impl Face {
     }
 
     pub fn merge_up(&self, other: &Face) -> Option<Face> {
-        if self.block_type_int == other.block_type_int && self.ul == other.ll && self.ur == other.lr {
+        if self.block_state_int == other.block_state_int && self.ul == other.ll && self.ur == other.lr {
             return Some(Face {
                 ul: other.ul,
                 ur: other.ur,
                 ll: self.ll,
                 lr: self.lr,
                 block_side: self.block_side,
-                block_type_int: self.block_type_int
+                block_state_int: self.block_state_int
             });
         }
 
@@ -98,14 +98,14 @@ impl Face {
     }
 
     pub fn merge_right(&self, other: &Face) -> Option<Face> {
-        if self.block_type_int == other.block_type_int && self.lr == other.ll && self.ur == other.ul {
+        if self.block_state_int == other.block_state_int && self.lr == other.ll && self.ur == other.ul {
             return Some(Face {
                 ul: self.ul,
                 ur: other.ur,
                 ll: self.ll,
                 lr: other.lr,
                 block_side: self.block_side,
-                block_type_int: self.block_type_int
+                block_state_int: self.block_state_int
             });
         }
 
@@ -113,14 +113,14 @@ impl Face {
     }
 
     pub fn merge_left(&self, other: &Face) -> Option<Face> {
-        if self.block_type_int == other.block_type_int && self.ll == other.lr && self.ul == other.ur {
+        if self.block_state_int == other.block_state_int && self.ll == other.lr && self.ul == other.ur {
             return Some(Face {
                 ul: other.ul,
                 ur: self.ur,
                 ll: other.ll,
                 lr: self.lr,
                 block_side: self.block_side,
-                block_type_int: self.block_type_int
+                block_state_int: self.block_state_int
             });
         }
 
@@ -1,7 +1,7 @@
 use fundamentals::{enums::block_side::BlockSide, consts::CHUNK_DIMENSION, logi};
 use web_time::Instant;
 
-use crate::voxels::{mesh::face::Face, chunk::{Chunk, ChunkBlockIterator}};
+use crate::voxels::{mesh::face::Face, chunk_storage::ChunkStorage};
 
 use super::Mesh;
 
@@ -105,14 +105,13 @@ fn greedy_merge_and_modify_vecs(current_layer: &mut Vec<Vec<Face>>, before_layer
     }
 }
 
-pub fn greedy(chunk: &Chunk, index: u32) -> Mesh {
+pub fn greedy<C: ChunkStorage>(chunk: &C, index: u32) -> Mesh {
     greedy_sided(chunk, index, &vec![BlockSide::FRONT, BlockSide::BACK, BlockSide::LEFT, BlockSide::RIGHT, BlockSide::TOP, BlockSide::BOTTOM])
 }
 
-pub fn greedy_sided(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Mesh {
+pub fn greedy_sided<C: ChunkStorage>(chunk: &C, index: u32, sides: &Vec<BlockSide>) -> Mesh {
     let now = Instant::now();
     let mut mesh = Mesh::new();
-    let mut cbi = ChunkBlockIterator::new(chunk);
 
     let mut side_layers = vec![vec![Vec::new(); CHUNK_DIMENSION as usize]; 6];
     let mut side_before_layers = vec![vec![Vec::new(); CHUNK_DIMENSION as usize]; 6];
@@ -130,7 +129,7 @@ pub fn greedy_sided(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Mesh {
     let contains_top = sides.contains(&BlockSide::TOP);
     let contains_bottom = sides.contains(&BlockSide::BOTTOM);
 
-    while let Some(((i,j,k), block)) = cbi.get_next_block() {
+    for ((i,j,k), block) in chunk.solid_blocks() {
         if current_y < j-1 {
             if contains_left {
                 greedy_merge_and_modify_vecs(&mut side_layers[BlockSide::LEFT as usize], &mut side_before_layers[BlockSide::LEFT as usize], &mut faces_to_make, BlockSide::LEFT);
@@ -156,11 +155,11 @@ pub fn greedy_sided(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Mesh {
         current_x = i-1;
         current_y = j-1;
         current_z = k-1;
-        let adjacent_blocks_data = Mesh::generate_adjacent_blocks(&chunk, i, j, k);
+        let adjacent_blocks_data = Mesh::generate_adjacent_blocks(chunk, i, j, k);
 
         for side in sides.iter() {
             if !adjacent_blocks_data[*side as usize] {
-                let side_face = Face::new(current_x,current_y,current_z, block.block_type as usize, *side);
+                let side_face = Face::new(current_x,current_y,current_z, block.block_state as usize, *side);
                 let orientation_index = match side {
                     &BlockSide::FRONT => {
                         current_x
@@ -265,7 +264,7 @@ pub fn greedy_sided(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Mesh {
 
     let after = Instant::now();
     let time  = (after-now).as_millis();
-    let cpos = chunk.position;
+    let cpos = chunk.get_position();
     logi!("Greedy mesh for position {} sides {:?} took {} milliseconds", cpos, sides, time);
 
     mesh
@@ -3,10 +3,10 @@ mod greedy;
 mod cull;
 
 use derivables::{vertex::Vertex, block::Block};
-use fundamentals::{world_position::WorldPosition, enums::{block_side::BlockSide, block_type::BlockTypeSize}, consts::NUM_VERTICES_IN_BUCKET};
+use fundamentals::{world_position::WorldPosition, enums::block_side::BlockSide, consts::{NUM_VERTICES_IN_BUCKET, BlockStateSize}};
 use self::face::Face;
 
-use super::chunk::Chunk;
+use super::chunk_storage::ChunkStorage;
 
 #[derive(Debug)]
 pub struct Mesh {
@@ -30,19 +30,19 @@ impl Mesh {
         }
     }
 
-    pub fn cull(chunk: &Chunk, index: u32) -> Self {
+    pub fn cull<C: ChunkStorage>(chunk: &C, index: u32) -> Self {
         cull::cull(chunk, index)
     }
 
-    pub fn cull_side(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Self {
+    pub fn cull_side<C: ChunkStorage>(chunk: &C, index: u32, sides: &Vec<BlockSide>) -> Self {
         cull::cull_side(chunk, index, sides)
     }
 
-    pub fn greedy(chunk: &Chunk, index: u32) -> Self {
+    pub fn greedy<C: ChunkStorage>(chunk: &C, index: u32) -> Self {
         greedy::greedy(chunk, index)
     }
 
-    pub fn greedy_sided(chunk: &Chunk, index: u32, sides: &Vec<BlockSide>) -> Self {
+    pub fn greedy_sided<C: ChunkStorage>(chunk: &C, index: u32, sides: &Vec<BlockSide>) -> Self {
         greedy::greedy_sided(chunk, index, sides)
     }
 
@@ -69,30 +69,31 @@ impl Mesh {
         self.bottom.2 = self.bottom.1.len() as u32;
     }
 
-    pub fn generate_adjacent_blocks(chunk: &Chunk, i: usize, j: usize, k: usize) -> [bool; 6] {
+    /// Whether each neighbour hides the face towards it, so glass or leaves don't hide the faces behind them.
+    pub fn generate_adjacent_blocks<C: ChunkStorage>(chunk: &C, i: usize, j: usize, k: usize) -> [bool; 6] {
         let mut adjacency_data = [false;6];
-        adjacency_data[0] = chunk.is_block_solid(i-1, j, k);
-        adjacency_data[1] = chunk.is_block_solid(i+1, j, k);
-        adjacency_data[2] = chunk.is_block_solid(i, j, k-1);
-        adjacency_data[3] = chunk.is_block_solid(i, j, k+1);
-        adjacency_data[4] = chunk.is_block_solid(i, j+1, k);
-        adjacency_data[5] = chunk.is_block_solid(i, j-1, k);
+        adjacency_data[0] = chunk.is_block_occluding(i-1, j, k);
+        adjacency_data[1] = chunk.is_block_occluding(i+1, j, k);
+        adjacency_data[2] = chunk.is_block_occluding(i, j, k-1);
+        adjacency_data[3] = chunk.is_block_occluding(i, j, k+1);
+        adjacency_data[4] = chunk.is_block_occluding(i, j+1, k);
+        adjacency_data[5] = chunk.is_block_occluding(i, j-1, k);
         adjacency_data
     }
 
-    pub fn is_adjacent_blocks_solid_side(chunk: &Chunk, i: usize, j: usize, k: usize, side: BlockSide) -> bool {
+    pub fn is_adjacent_block_occluding_side<C: ChunkStorage>(chunk: &C, i: usize, j: usize, k: usize, side: BlockSide) -> bool {
         match side {
-            BlockSide::FRONT => chunk.is_block_solid(i-1, j, k),
-            BlockSide::BACK => chunk.is_block_solid(i+1, j, k),
-            BlockSide::LEFT => chunk.is_block_solid(i, j, k-1),
-            BlockSide::RIGHT => chunk.is_block_solid(i, j, k+1),
-            BlockSide::TOP => chunk.is_block_solid(i, j+1, k),
-            BlockSide::BOTTOM => chunk.is_block_solid(i, j-1, k)
+            BlockSide::FRONT => chunk.is_block_occluding(i-1, j, k),
+            BlockSide::BACK => chunk.is_block_occluding(i+1, j, k),
+            BlockSide::LEFT => chunk.is_block_occluding(i, j, k-1),
+            BlockSide::RIGHT => chunk.is_block_occluding(i, j, k+1),
+            BlockSide::TOP => chunk.is_block_occluding(i, j+1, k),
+            BlockSide::BOTTOM => chunk.is_block_occluding(i, j-1, k)
         }
     }
 
     fn generate_face_vertices(face: &Face, index: u32) -> Vec<Vertex> {
-        let texture_indices = &Block::get_texture_indices_from_int(face.block_type_int as BlockTypeSize);
+        let texture_indices = &Block::get_texture_indices_from_state(face.block_state_int as BlockStateSize);
         let (texture_index, u_offset, v_offset) = match face.block_side {
             BlockSide::FRONT => {
                 (0, (face.lr.2-face.ll.2) as u8, (face.ul.1-face.ll.1) as u8)
@@ -356,4 +357,36 @@ impl Mesh {
             bottom: ( bottom_vertices, bottom_indices, bottom_len as u32 ),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fundamentals::block_registry;
+    use crate::voxels::chunk::Chunk;
+
+    fn get_block(name: &str) -> Block {
+        Block::new(block_registry::get().get_block_type(name).unwrap())
+    }
+
+    // The number of faces on each side, in BlockSide order.
+    fn get_face_counts(mesh: &Mesh) -> [usize; 6] {
+        [&mesh.front, &mesh.back, &mesh.left, &mesh.right, &mesh.top, &mesh.bottom].map(|side| side.0.len() / 4)
+    }
+
+    #[test]
+    fn blocks_that_do_not_occlude_leave_their_neighbours_faces() {
+        let (wood, glass) = (get_block("WOOD"), get_block("GLASS"));
+        assert!(wood.occludes_neighbours() && !glass.occludes_neighbours());
+        // Along x: wood, glass, glass.
+        let mut chunk = Chunk::empty(&WorldPosition::new(0, 0, 0));
+        chunk.set_block(1, 1, 1, wood);
+        chunk.set_block(2, 1, 1, glass);
+        chunk.set_block(3, 1, 1, glass);
+
+        // Only the face of the first glass block towards the wood is hidden.
+        assert_eq!(get_face_counts(&Mesh::cull(&chunk, 0)), [2, 3, 3, 3, 3, 3]);
+        // The greedy mesher merges the two glass faces on the long sides.
+        assert_eq!(get_face_counts(&Mesh::greedy(&chunk, 0)), [2, 3, 2, 2, 2, 2]);
+    }
+}
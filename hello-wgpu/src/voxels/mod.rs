@@ -1,3 +1,7 @@
 pub mod world;
 pub mod chunk;
+pub mod chunk_storage;
+pub mod palette_chunk;
+pub mod chunk_encoding;
+pub mod region;
 pub mod mesh;
\ No newline at end of file
@@ -0,0 +1,375 @@
+use derivables::block::Block;
+use fundamentals::block_registry::AIR_STATE;
+use fundamentals::consts::{CHUNK_DIMENSION, CHUNK_PLANE_SIZE, CHUNK_SIZE};
+use fundamentals::enums::block_side::BlockSide;
+use fundamentals::world_position::WorldPosition;
+use bitvec::prelude::BitVec;
+use strum::IntoEnumIterator;
+use super::chunk_storage::{self, BlockEdit, ChunkStorage};
+
+/// Chunk storage as a local palette of the blocks in the chunk plus an index into it per position,
+/// packed with as many bits as the palette needs. Palette entry 0 is always AIR, so a chunk of only
+/// AIR needs no index bits at all.
+pub struct PaletteChunk {
+    pub position: WorldPosition,
+    palette: Vec<Block>,
+    // How many positions use each palette entry. Entries at 0 are reused before the palette grows.
+    palette_counts: Vec<u32>,
+    bits_per_index: u32,
+    // Indices are in z, y, x order like `Chunk::blocks` and never straddle two words.
+    indices: Vec<u64>,
+    // The neighbouring chunks' border blocks, a plane per side in BlockSide order.
+    padding: BitVec,
+}
+
+impl PaletteChunk {
+    pub fn empty(position: &WorldPosition) -> Self {
+        let mut padding = BitVec::with_capacity(6 * CHUNK_PLANE_SIZE as usize);
+        padding.resize(6 * CHUNK_PLANE_SIZE as usize, false);
+        PaletteChunk {
+            position: *position,
+            palette: vec![Block::from_state(AIR_STATE)],
+            palette_counts: vec![CHUNK_SIZE as u32],
+            bits_per_index: 0,
+            indices: Vec::new(),
+            padding,
+        }
+    }
+
+    /// Copies the blocks and padding of any other storage.
+    pub fn from_storage<C: ChunkStorage>(chunk: &C) -> Self {
+        let mut palette_chunk = Self::empty(&chunk.get_position());
+        for ((cx, cy, cz), block) in chunk.solid_blocks() {
+            palette_chunk.place_block(get_index(cx, cy, cz), *block);
+        }
+        for side in BlockSide::iter() {
+            for j in 1..=CHUNK_DIMENSION as usize {
+                for i in 1..=CHUNK_DIMENSION as usize {
+                    let (cx, cy, cz) = chunk_storage::get_padding_position(side, i, j);
+                    palette_chunk.update_padding(cx, cy, cz, chunk.is_block_solid(cx, cy, cz));
+                }
+            }
+        }
+        palette_chunk
+    }
+
+    pub fn get_bits_per_index(&self) -> u32 {
+        self.bits_per_index
+    }
+
+    fn get_palette_index(&self, index: usize) -> usize {
+        if self.bits_per_index == 0 {
+            return 0;
+        }
+        let indices_per_word = 64 / self.bits_per_index as usize;
+        let shift = (index % indices_per_word) * self.bits_per_index as usize;
+        ((self.indices[index / indices_per_word] >> shift) & ((1 << self.bits_per_index) - 1)) as usize
+    }
+
+    fn set_palette_index(&mut self, index: usize, palette_index: usize) {
+        let indices_per_word = 64 / self.bits_per_index as usize;
+        let shift = (index % indices_per_word) * self.bits_per_index as usize;
+        let word = &mut self.indices[index / indices_per_word];
+        *word = (*word & !(((1 << self.bits_per_index) - 1) << shift)) | ((palette_index as u64) << shift);
+    }
+
+    // Stores `block` at `index`, growing the palette and index width if it is new to the chunk and
+    // shrinking them if the block it replaces was the last of its kind.
+    fn place_block(&mut self, index: usize, block: Block) {
+        let previous_palette_index = self.get_palette_index(index);
+        // Compared by state so the palette doesn't need the block registry.
+        let palette_index = if block.block_state == AIR_STATE {
+            0
+        } else if let Some(palette_index) = self.palette.iter().position(|entry| entry.block_state == block.block_state) {
+            palette_index
+        } else if let Some(palette_index) = self.palette_counts.iter().skip(1).position(|count| *count == 0) {
+            self.palette[palette_index + 1] = block;
+            palette_index + 1
+        } else {
+            self.palette.push(block);
+            self.palette_counts.push(0);
+            let bits_per_index = get_bits_per_index(self.palette.len());
+            if bits_per_index != self.bits_per_index {
+                self.repack(bits_per_index, |palette_index| palette_index);
+            }
+            self.palette.len() - 1
+        };
+        if palette_index == previous_palette_index {
+            return;
+        }
+
+        self.set_palette_index(index, palette_index);
+        self.palette_counts[palette_index] += 1;
+        self.palette_counts[previous_palette_index] -= 1;
+        if previous_palette_index != 0 && self.palette_counts[previous_palette_index] == 0 {
+            self.shrink();
+        }
+    }
+
+    // Drops unused palette entries once the rest fit in fewer bits per index.
+    fn shrink(&mut self) {
+        let num_used = 1 + self.palette_counts.iter().skip(1).filter(|count| **count > 0).count();
+        let bits_per_index = get_bits_per_index(num_used);
+        if bits_per_index == self.bits_per_index {
+            return;
+        }
+
+        let mut remapping = vec![0; self.palette.len()];
+        let mut palette = vec![self.palette[0]];
+        let mut palette_counts = vec![self.palette_counts[0]];
+        for palette_index in 1..self.palette.len() {
+            if self.palette_counts[palette_index] > 0 {
+                remapping[palette_index] = palette.len();
+                palette.push(self.palette[palette_index]);
+                palette_counts.push(self.palette_counts[palette_index]);
+            }
+        }
+        self.repack(bits_per_index, |palette_index| remapping[palette_index]);
+        self.palette = palette;
+        self.palette_counts = palette_counts;
+    }
+
+    fn repack<F: Fn(usize) -> usize>(&mut self, bits_per_index: u32, remap: F) {
+        let previous = PaletteChunk { position: self.position, palette: Vec::new(), palette_counts: Vec::new(), bits_per_index: self.bits_per_index, indices: std::mem::take(&mut self.indices), padding: BitVec::new() };
+        self.bits_per_index = bits_per_index;
+        if bits_per_index == 0 {
+            return;
+        }
+        let indices_per_word = 64 / bits_per_index as usize;
+        self.indices = vec![0; CHUNK_SIZE.div_ceil(indices_per_word)];
+        for index in 0..CHUNK_SIZE {
+            let palette_index = remap(previous.get_palette_index(index));
+            if palette_index != 0 {
+                self.set_palette_index(index, palette_index);
+            }
+        }
+    }
+}
+
+impl ChunkStorage for PaletteChunk {
+    type SolidBlocks<'a> = PaletteBlockIterator<'a>;
+
+    fn get_position(&self) -> WorldPosition {
+        self.position
+    }
+
+    fn is_block_solid(&self, cx: usize, cy: usize, cz: usize) -> bool {
+        let dimension = CHUNK_DIMENSION as usize;
+        match chunk_storage::get_padding_index(cx, cy, cz) {
+            Some(padding_index) => self.padding[padding_index],
+            None if (1..=dimension).contains(&cx) && (1..=dimension).contains(&cy) && (1..=dimension).contains(&cz) =>
+                self.get_palette_index(get_index(cx, cy, cz)) != 0,
+            // Edges and corners of the padding are never filled in.
+            None => false,
+        }
+    }
+
+    fn update_padding(&mut self, cx: usize, cy: usize, cz: usize, solid_value: bool) {
+        if let Some(padding_index) = chunk_storage::get_padding_index(cx, cy, cz) {
+            self.padding.set(padding_index, solid_value);
+        }
+    }
+
+    fn get_block(&self, x: usize, y: usize, z: usize) -> Block {
+        let (cx, cy, cz) = chunk_storage::to_wrapped(x, y, z);
+        self.palette[self.get_palette_index(get_index(cx, cy, cz))]
+    }
+
+    fn set_block(&mut self, x: usize, y: usize, z: usize, block: Block) -> BlockEdit {
+        let (cx, cy, cz) = chunk_storage::to_wrapped(x, y, z);
+        let previous = self.get_block(x, y, z);
+        self.place_block(get_index(cx, cy, cz), block);
+        chunk_storage::get_block_edit(self, x, y, z, previous, block)
+    }
+
+    fn get_uniform_block(&self) -> Option<Block> {
+        if self.bits_per_index == 0 {
+            return Some(self.palette[0]);
+        }
+        // AIR keeps palette entry 0 even when it's unused, so a chunk of one solid block still has index bits.
+        let mut used_entries = self.palette_counts.iter().enumerate().filter(|(_, count)| **count > 0);
+        match (used_entries.next(), used_entries.next()) {
+            (Some((palette_index, _)), None) => Some(self.palette[palette_index]),
+            _ => None,
+        }
+    }
+
+    fn solid_blocks(&self) -> PaletteBlockIterator<'_> {
+        PaletteBlockIterator { chunk_ref: self, index: 0 }
+    }
+
+    fn get_memory_usage(&self) -> usize {
+        std::mem::size_of::<PaletteChunk>()
+            + self.palette.capacity() * std::mem::size_of::<Block>()
+            + self.palette_counts.capacity() * std::mem::size_of::<u32>()
+            + self.indices.capacity() * std::mem::size_of::<u64>()
+            + self.padding.capacity() / 8
+    }
+}
+
+pub struct PaletteBlockIterator<'a> {
+    chunk_ref: &'a PaletteChunk,
+    index: usize,
+}
+
+impl<'a> Iterator for PaletteBlockIterator<'a> {
+    type Item = ((usize, usize, usize), &'a Block);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunk_ref;
+        if chunk.bits_per_index == 0 {
+            return None;
+        }
+        let indices_per_word = 64 / chunk.bits_per_index as usize;
+        while self.index < CHUNK_SIZE {
+            // A word of AIR is all zeroes.
+            if self.index % indices_per_word == 0 && chunk.indices[self.index / indices_per_word] == 0 {
+                self.index += indices_per_word;
+                continue;
+            }
+            let index = self.index;
+            self.index += 1;
+            let palette_index = chunk.get_palette_index(index);
+            if palette_index != 0 {
+                let dimension = CHUNK_DIMENSION as usize;
+                let position = (index % dimension + 1, (index / dimension) % dimension + 1, index / CHUNK_PLANE_SIZE as usize + 1);
+                return Some((position, &chunk.palette[palette_index]));
+            }
+        }
+        None
+    }
+}
+
+// Index of a wrapped interior position.
+fn get_index(cx: usize, cy: usize, cz: usize) -> usize {
+    (cx - 1) + (cy - 1) * CHUNK_DIMENSION as usize + (cz - 1) * CHUNK_PLANE_SIZE as usize
+}
+
+fn get_bits_per_index(palette_length: usize) -> u32 {
+    usize::BITS - (palette_length - 1).leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fundamentals::consts::BlockStateSize;
+
+    const AIR_BLOCK: Block = Block { block_state: AIR_STATE };
+
+    // Palette entries are only compared by state, so the tests can use more states than the registry has.
+    fn get_test_block(state: usize) -> Block {
+        Block::from_state(state as BlockStateSize)
+    }
+
+    fn assert_matches_dense(chunk: &PaletteChunk, dense: &[Block]) {
+        for (index, block) in dense.iter().enumerate() {
+            assert_eq!(chunk.palette[chunk.get_palette_index(index)].block_state, block.block_state, "block at index {}", index);
+        }
+    }
+
+    #[test]
+    fn bits_per_index_grow_and_shrink_with_the_palette() {
+        // Widths once 1..=17 solid states have been placed, so the palette holds 2..=18 entries.
+        let expected_bits = [1, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5];
+        let mut chunk = PaletteChunk::empty(&WorldPosition::new(0, 0, 0));
+        let mut dense = vec![AIR_BLOCK; CHUNK_SIZE];
+        assert_eq!(chunk.get_bits_per_index(), 0);
+        assert!(chunk.indices.is_empty());
+
+        for state in 1..=expected_bits.len() {
+            let index = state * 997 % CHUNK_SIZE;
+            chunk.place_block(index, get_test_block(state));
+            dense[index] = get_test_block(state);
+            assert_eq!(chunk.get_bits_per_index(), expected_bits[state - 1], "after placing state {}", state);
+            assert_eq!(chunk.palette.len(), state + 1);
+            assert_matches_dense(&chunk, &dense);
+        }
+
+        for state in (1..=expected_bits.len()).rev() {
+            let index = state * 997 % CHUNK_SIZE;
+            chunk.place_block(index, AIR_BLOCK);
+            dense[index] = AIR_BLOCK;
+            let expected = if state == 1 { 0 } else { expected_bits[state - 2] };
+            assert_eq!(chunk.get_bits_per_index(), expected, "after removing state {}", state);
+            assert_matches_dense(&chunk, &dense);
+        }
+        assert!(chunk.indices.is_empty());
+        assert_eq!(chunk.palette.len(), 1);
+        assert_eq!(chunk.palette_counts, vec![CHUNK_SIZE as u32]);
+        assert_eq!(chunk.get_uniform_block().map(|block| block.block_state), Some(AIR_STATE));
+    }
+
+    #[test]
+    fn shrink_remaps_the_remaining_entries() {
+        let num_states = 17;
+        let mut rng = fastrand::Rng::with_seed(19);
+        let mut chunk = PaletteChunk::empty(&WorldPosition::new(0, 0, 0));
+        let mut dense = vec![AIR_BLOCK; CHUNK_SIZE];
+        for (index, block) in dense.iter_mut().enumerate() {
+            *block = get_test_block(rng.usize(0..=num_states));
+            chunk.place_block(index, *block);
+        }
+        assert_eq!(chunk.get_bits_per_index(), get_bits_per_index(num_states + 1));
+        assert_matches_dense(&chunk, &dense);
+
+        // Clearing the lowest states first moves every entry left down the palette on each shrink.
+        for state in 1..=num_states {
+            for index in 0..CHUNK_SIZE {
+                if dense[index].block_state as usize == state {
+                    chunk.place_block(index, AIR_BLOCK);
+                    dense[index] = AIR_BLOCK;
+                }
+            }
+            let num_remaining = num_states - state;
+            assert_eq!(chunk.get_bits_per_index(), get_bits_per_index(num_remaining + 1), "after clearing state {}", state);
+            assert!(chunk.palette.len() <= 1 << chunk.get_bits_per_index());
+            assert_eq!(chunk.palette_counts.iter().sum::<u32>(), CHUNK_SIZE as u32);
+            assert_matches_dense(&chunk, &dense);
+        }
+        assert_eq!(chunk.get_bits_per_index(), 0);
+        assert!(chunk.solid_blocks().next().is_none());
+    }
+
+    #[test]
+    fn unused_entries_are_reused_before_the_palette_grows() {
+        let mut chunk = PaletteChunk::empty(&WorldPosition::new(0, 0, 0));
+        for state in 1..=3 {
+            chunk.place_block(state, get_test_block(state));
+        }
+        assert_eq!(chunk.get_bits_per_index(), 2);
+
+        // Three entries are still used, which needs 2 bits, so the freed entry stays in the palette.
+        chunk.place_block(2, AIR_BLOCK);
+        assert_eq!(chunk.get_bits_per_index(), 2);
+        assert_eq!(chunk.palette.len(), 4);
+
+        chunk.place_block(10, get_test_block(9));
+        assert_eq!(chunk.get_bits_per_index(), 2);
+        assert_eq!(chunk.palette.len(), 4);
+        assert_eq!(chunk.palette[2].block_state, 9);
+        let blocks: Vec<(usize, BlockStateSize)> = (0..CHUNK_SIZE)
+            .filter(|index| chunk.get_palette_index(*index) != 0)
+            .map(|index| (index, chunk.palette[chunk.get_palette_index(index)].block_state))
+            .collect();
+        assert_eq!(blocks, vec![(1, 1), (3, 3), (10, 9)]);
+    }
+
+    #[test]
+    fn a_chunk_of_one_solid_block_is_uniform() {
+        let mut chunk = PaletteChunk::empty(&WorldPosition::new(0, 0, 0));
+        for index in 0..CHUNK_SIZE - 1 {
+            chunk.place_block(index, get_test_block(2));
+        }
+        assert!(chunk.get_uniform_block().is_none());
+
+        chunk.place_block(CHUNK_SIZE - 1, get_test_block(2));
+        assert_eq!(chunk.get_bits_per_index(), 1);
+        assert_eq!(chunk.get_uniform_block().map(|block| block.block_state), Some(2));
+
+        // A second solid block, even replacing a single position, ends the uniformity.
+        chunk.place_block(0, get_test_block(3));
+        assert!(chunk.get_uniform_block().is_none());
+        chunk.place_block(0, get_test_block(2));
+        assert_eq!(chunk.get_uniform_block().map(|block| block.block_state), Some(2));
+    }
+}
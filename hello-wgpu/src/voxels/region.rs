@@ -0,0 +1,469 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use formats::formats::block_id_table_format::BlockIdTableFormat;
+use formats::formats::checksum::crc32;
+use formats::formats::migration;
+use fundamentals::world_position::WorldPosition;
+
+pub const REGION_MAGIC: [u8; 4] = *b"WRGN";
+pub const REGION_VERSION: u32 = 1;
+/// Chunks along each axis of a region.
+pub const REGION_DIMENSION: i32 = 8;
+pub const REGION_SIZE: usize = (REGION_DIMENSION * REGION_DIMENSION * REGION_DIMENSION) as usize;
+pub const SECTOR_SIZE: usize = 4096;
+
+// The entry after the chunks holds the block ID table the region's chunks were written with.
+const ID_TABLE_INDEX: usize = REGION_SIZE;
+const NUM_ENTRIES: usize = REGION_SIZE + 1;
+const HEADER_LENGTH: usize = 4 + 4 + 4 + NUM_ENTRIES * 8 + 4;
+const HEADER_SECTORS: usize = HEADER_LENGTH.div_ceil(SECTOR_SIZE);
+
+// Keeps the temporary files of saves running at the same time apart.
+static NEXT_TEMPORARY_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegionError {
+    Io { path: PathBuf, message: String },
+    BadMagic { found: Vec<u8> },
+    UnsupportedVersion { version: u32 },
+    DimensionMismatch { dimension: u32 },
+    Truncated { needed: usize, length: usize },
+    ChecksumMismatch { expected: u32, found: u32 },
+    EntryOutOfBounds { index: usize, first_sector: u32, length: u32 },
+    OverlappingEntries { index: usize, other_index: usize },
+    MissingIdTable,
+    InvalidIdTable { message: String },
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionError::Io { path, message } =>
+                write!(f, "{}: {}", path.display(), message),
+            RegionError::BadMagic { found } =>
+                write!(f, "not a region, expected magic {:?} but found {:?}", REGION_MAGIC, found),
+            RegionError::UnsupportedVersion { version } =>
+                write!(f, "region version {} is not supported, this build reads version {}", version, REGION_VERSION),
+            RegionError::DimensionMismatch { dimension } =>
+                write!(f, "region is {} chunks wide but this build uses {}", dimension, REGION_DIMENSION),
+            RegionError::Truncated { needed, length } =>
+                write!(f, "region is truncated, {} bytes needed but there are {}", needed, length),
+            RegionError::ChecksumMismatch { expected, found } =>
+                write!(f, "region header is corrupt, checksum {:08x} does not match {:08x}", found, expected),
+            RegionError::EntryOutOfBounds { index, first_sector, length } =>
+                write!(f, "region entry {} of {} bytes at sector {} is outside the file", index, length, first_sector),
+            RegionError::OverlappingEntries { index, other_index } =>
+                write!(f, "region entries {} and {} share sectors", other_index, index),
+            RegionError::MissingIdTable =>
+                write!(f, "region has chunks but no block ID table"),
+            RegionError::InvalidIdTable { message } =>
+                write!(f, "region block ID table is invalid: {}", message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct RegionEntry {
+    // 0 when the entry is empty, the header always takes the first sectors.
+    first_sector: u32,
+    length: u32,
+}
+
+impl RegionEntry {
+    fn get_num_sectors(&self) -> usize {
+        (self.length as usize).div_ceil(SECTOR_SIZE)
+    }
+}
+
+/// A cube of REGION_DIMENSION chunks on each side saved in one file.
+///
+/// Layout, little endian: the magic `WRGN`, then u32 version and u32 dimension, an entry per chunk
+/// in x, y, z order plus one for the block ID table, each a u32 first sector and u32 length, and a
+/// CRC-32 of the header before it. Entries are stored in whole SECTOR_SIZE sectors after the header.
+/// Each chunk keeps its own checksum from `Chunk::to_bytes`.
+pub struct Region {
+    entries: Vec<RegionEntry>,
+    // The whole file, header sectors included. The header is only written by `save`.
+    data: Vec<u8>,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Region {
+    pub fn new() -> Self {
+        Region { entries: vec![RegionEntry::default(); NUM_ENTRIES], data: vec![0; HEADER_SECTORS * SECTOR_SIZE] }
+    }
+
+    /// Reads the region at `path`, or an empty one if nothing has been saved there yet.
+    pub fn open(path: &Path) -> Result<Self, RegionError> {
+        match fs::read(path) {
+            Ok(bytes) => Self::from_bytes(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(get_io_error(path, e)),
+        }
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, RegionError> {
+        let entries = read_header(&data)?;
+        validate_entries(&entries, data.len())?;
+        Ok(Region { entries, data })
+    }
+
+    /// Reads the chunk at `index` and the block ID table from the region at `path` without reading
+    /// the rest of the file. None if the region or the chunk hasn't been saved.
+    pub fn read_chunk_bytes(path: &Path, index: usize) -> Result<Option<(Vec<u8>, BlockIdTableFormat)>, RegionError> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(get_io_error(path, e)),
+        };
+        let file_length = file.metadata().map_err(|e| get_io_error(path, e))?.len() as usize;
+        let mut header = vec![0; HEADER_LENGTH.min(file_length)];
+        file.read_exact(&mut header).map_err(|e| get_io_error(path, e))?;
+        let entries = read_header(&header)?;
+        validate_entries(&entries, file_length)?;
+
+        let entry = entries[index];
+        if entry.first_sector == 0 {
+            return Ok(None);
+        }
+        let chunk_bytes = read_entry(&mut file, path, entry)?;
+        let id_table = match entries[ID_TABLE_INDEX] {
+            id_table_entry if id_table_entry.first_sector == 0 => return Err(RegionError::MissingIdTable),
+            id_table_entry => parse_id_table(&read_entry(&mut file, path, id_table_entry)?)?,
+        };
+        Ok(Some((chunk_bytes, id_table)))
+    }
+
+    pub fn get_chunk_bytes(&self, index: usize) -> Option<&[u8]> {
+        self.get_entry_bytes(index)
+    }
+
+    pub fn set_chunk_bytes(&mut self, index: usize, bytes: &[u8]) {
+        self.set_entry_bytes(index, bytes);
+    }
+
+    /// The block ID table the region's chunks were written with, None for a new region.
+    pub fn get_id_table(&self) -> Result<Option<BlockIdTableFormat>, RegionError> {
+        match self.get_entry_bytes(ID_TABLE_INDEX) {
+            Some(bytes) => Ok(Some(parse_id_table(bytes)?)),
+            None if self.entries[..REGION_SIZE].iter().any(|entry| entry.first_sector != 0) => Err(RegionError::MissingIdTable),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_id_table(&mut self, id_table: &BlockIdTableFormat) {
+        self.set_entry_bytes(ID_TABLE_INDEX, &serde_json::to_vec(id_table).unwrap());
+    }
+
+    /// Writes the region to a temporary file next to `path` and renames it over `path` once it is on
+    /// disk, then syncs the directory, so a crash leaves either the old region or the new one. A region
+    /// is read, changed and saved as a whole, so only one writer may have a region open at a time.
+    pub fn save(&mut self, path: &Path) -> Result<(), RegionError> {
+        let num_used_sectors = self.entries.iter()
+            .filter(|entry| entry.first_sector != 0)
+            .map(|entry| entry.first_sector as usize + entry.get_num_sectors())
+            .max()
+            .unwrap_or(HEADER_SECTORS);
+        self.data.truncate(num_used_sectors * SECTOR_SIZE);
+        let header = write_header(&self.entries);
+        self.data[..HEADER_LENGTH].copy_from_slice(&header);
+
+        let temporary_id = NEXT_TEMPORARY_ID.fetch_add(1, Ordering::Relaxed);
+        let temporary_path = path.with_extension(format!("region.{}-{}.tmp", std::process::id(), temporary_id));
+        let write = || -> io::Result<()> {
+            let mut file = File::create(&temporary_path)?;
+            file.write_all(&self.data)?;
+            file.sync_all()?;
+            fs::rename(&temporary_path, path)?;
+            sync_directory(path)
+        };
+        write().map_err(|e| get_io_error(path, e))
+    }
+
+    fn get_entry_bytes(&self, index: usize) -> Option<&[u8]> {
+        let entry = self.entries[index];
+        if entry.first_sector == 0 {
+            return None;
+        }
+        let start = entry.first_sector as usize * SECTOR_SIZE;
+        Some(&self.data[start..start + entry.length as usize])
+    }
+
+    // Keeps the entry's sectors if the bytes still fit, otherwise frees them and takes the first run
+    // of free sectors that is long enough, or appends to the file.
+    fn set_entry_bytes(&mut self, index: usize, bytes: &[u8]) {
+        let num_sectors = bytes.len().div_ceil(SECTOR_SIZE);
+        let previous = self.entries[index];
+        self.entries[index] = RegionEntry::default();
+        if num_sectors == 0 {
+            return;
+        }
+
+        let first_sector = if previous.first_sector != 0 && num_sectors <= previous.get_num_sectors() {
+            previous.first_sector as usize
+        } else {
+            self.find_free_sectors(num_sectors)
+        };
+        let start = first_sector * SECTOR_SIZE;
+        let end = (first_sector + num_sectors) * SECTOR_SIZE;
+        if self.data.len() < end {
+            self.data.resize(end, 0);
+        }
+        self.data[start..start + bytes.len()].copy_from_slice(bytes);
+        self.data[start + bytes.len()..end].fill(0);
+        self.entries[index] = RegionEntry { first_sector: first_sector as u32, length: bytes.len() as u32 };
+    }
+
+    fn find_free_sectors(&self, num_sectors: usize) -> usize {
+        let num_file_sectors = self.data.len() / SECTOR_SIZE;
+        let mut used = vec![false; num_file_sectors];
+        for entry in self.entries.iter().filter(|entry| entry.first_sector != 0) {
+            let first_sector = entry.first_sector as usize;
+            used[first_sector..first_sector + entry.get_num_sectors()].fill(true);
+        }
+
+        let mut run_start = HEADER_SECTORS;
+        for (sector, is_used) in used.iter().enumerate().skip(HEADER_SECTORS) {
+            if *is_used {
+                run_start = sector + 1;
+            } else if sector + 1 - run_start == num_sectors {
+                return run_start;
+            }
+        }
+        // A free run at the end of the file is extended.
+        run_start
+    }
+}
+
+/// The position of the region holding the chunk at `chunk_position`.
+pub fn get_region_position(chunk_position: &WorldPosition) -> WorldPosition {
+    WorldPosition::new(
+        chunk_position.x.div_euclid(REGION_DIMENSION),
+        chunk_position.y.div_euclid(REGION_DIMENSION),
+        chunk_position.z.div_euclid(REGION_DIMENSION),
+    )
+}
+
+/// The index of the chunk at `chunk_position` within its region.
+pub fn get_region_index(chunk_position: &WorldPosition) -> usize {
+    let x = chunk_position.x.rem_euclid(REGION_DIMENSION);
+    let y = chunk_position.y.rem_euclid(REGION_DIMENSION);
+    let z = chunk_position.z.rem_euclid(REGION_DIMENSION);
+    (x + y * REGION_DIMENSION + z * REGION_DIMENSION * REGION_DIMENSION) as usize
+}
+
+pub fn get_region_path(directory: &Path, region_position: &WorldPosition) -> PathBuf {
+    directory.join(format!("r.{}.{}.{}.region", region_position.x, region_position.y, region_position.z))
+}
+
+fn read_header(bytes: &[u8]) -> Result<Vec<RegionEntry>, RegionError> {
+    if bytes.len() < HEADER_LENGTH {
+        return Err(RegionError::Truncated { needed: HEADER_LENGTH, length: bytes.len() });
+    }
+    if bytes[..4] != REGION_MAGIC {
+        return Err(RegionError::BadMagic { found: bytes[..4].to_vec() });
+    }
+    let read_u32 = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let version = read_u32(4);
+    if version != REGION_VERSION {
+        return Err(RegionError::UnsupportedVersion { version });
+    }
+    let expected_checksum = read_u32(HEADER_LENGTH - 4);
+    let found_checksum = crc32(&bytes[..HEADER_LENGTH - 4]);
+    if found_checksum != expected_checksum {
+        return Err(RegionError::ChecksumMismatch { expected: expected_checksum, found: found_checksum });
+    }
+    let dimension = read_u32(8);
+    if dimension != REGION_DIMENSION as u32 {
+        return Err(RegionError::DimensionMismatch { dimension });
+    }
+    Ok((0..NUM_ENTRIES).map(|index| RegionEntry { first_sector: read_u32(12 + index * 8), length: read_u32(16 + index * 8) }).collect())
+}
+
+fn write_header(entries: &[RegionEntry]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_LENGTH);
+    header.extend_from_slice(&REGION_MAGIC);
+    header.extend_from_slice(&REGION_VERSION.to_le_bytes());
+    header.extend_from_slice(&(REGION_DIMENSION as u32).to_le_bytes());
+    for entry in entries {
+        header.extend_from_slice(&entry.first_sector.to_le_bytes());
+        header.extend_from_slice(&entry.length.to_le_bytes());
+    }
+    header.extend_from_slice(&crc32(&header).to_le_bytes());
+    header
+}
+
+// Every entry has to lie after the header, within the file and apart from every other entry.
+fn validate_entries(entries: &[RegionEntry], file_length: usize) -> Result<(), RegionError> {
+    let mut owners = vec![None; file_length / SECTOR_SIZE];
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.first_sector == 0 {
+            continue;
+        }
+        let first_sector = entry.first_sector as usize;
+        let end_sector = first_sector + entry.get_num_sectors();
+        if entry.length == 0 || first_sector < HEADER_SECTORS || end_sector > owners.len() {
+            return Err(RegionError::EntryOutOfBounds { index, first_sector: entry.first_sector, length: entry.length });
+        }
+        for owner in &mut owners[first_sector..end_sector] {
+            if let Some(other_index) = *owner {
+                return Err(RegionError::OverlappingEntries { index, other_index });
+            }
+            *owner = Some(index);
+        }
+    }
+    Ok(())
+}
+
+fn read_entry(file: &mut File, path: &Path, entry: RegionEntry) -> Result<Vec<u8>, RegionError> {
+    let mut bytes = vec![0; entry.length as usize];
+    file.seek(SeekFrom::Start((entry.first_sector as usize * SECTOR_SIZE) as u64)).map_err(|e| get_io_error(path, e))?;
+    file.read_exact(&mut bytes).map_err(|e| get_io_error(path, e))?;
+    Ok(bytes)
+}
+
+fn parse_id_table(bytes: &[u8]) -> Result<BlockIdTableFormat, RegionError> {
+    let json = std::str::from_utf8(bytes).map_err(|e| RegionError::InvalidIdTable { message: e.to_string() })?;
+    let migrated = migration::migrate_str::<BlockIdTableFormat>(json).map_err(|e| RegionError::InvalidIdTable { message: e.to_string() })?;
+    Ok(migrated.value)
+}
+
+// The rename only survives a crash once the directory entry is on disk. Directories can't be opened
+// as files on Windows, where the rename is durable on its own.
+#[cfg(unix)]
+fn sync_directory(path: &Path) -> io::Result<()> {
+    let directory = path.parent().filter(|directory| !directory.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn get_io_error(path: &Path, e: io::Error) -> RegionError {
+    RegionError::Io { path: path.to_path_buf(), message: e.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_region(entry_lengths: &[usize]) -> Region {
+        let mut region = Region::new();
+        for (index, length) in entry_lengths.iter().enumerate() {
+            region.set_chunk_bytes(index, &vec![index as u8 + 1; *length]);
+        }
+        region
+    }
+
+    fn get_bytes(entries: &[RegionEntry], num_sectors: usize) -> Vec<u8> {
+        let mut all_entries = vec![RegionEntry::default(); NUM_ENTRIES];
+        all_entries[..entries.len()].copy_from_slice(entries);
+        let mut bytes = vec![0; num_sectors * SECTOR_SIZE];
+        bytes[..HEADER_LENGTH].copy_from_slice(&write_header(&all_entries));
+        bytes
+    }
+
+    #[test]
+    fn entries_take_whole_sectors_after_the_header() {
+        let region = get_region(&[10, SECTOR_SIZE + 1]);
+
+        assert_eq!(region.entries[0], RegionEntry { first_sector: HEADER_SECTORS as u32, length: 10 });
+        assert_eq!(region.entries[1], RegionEntry { first_sector: HEADER_SECTORS as u32 + 1, length: SECTOR_SIZE as u32 + 1 });
+        assert_eq!(region.data.len(), (HEADER_SECTORS + 3) * SECTOR_SIZE);
+        assert_eq!(region.get_chunk_bytes(1), Some(vec![2; SECTOR_SIZE + 1].as_slice()));
+    }
+
+    #[test]
+    fn a_growing_entry_reuses_freed_sectors_without_growing_the_file() {
+        let mut region = get_region(&[10, 10, 10]);
+        region.set_chunk_bytes(1, &[]);
+        region.set_chunk_bytes(0, &vec![7; SECTOR_SIZE + 1]);
+
+        assert_eq!(region.get_chunk_bytes(1), None);
+        assert_eq!(region.entries[0].first_sector, HEADER_SECTORS as u32);
+        assert_eq!(region.data.len(), (HEADER_SECTORS + 3) * SECTOR_SIZE);
+        assert_eq!(region.get_chunk_bytes(0), Some(vec![7; SECTOR_SIZE + 1].as_slice()));
+        assert_eq!(region.get_chunk_bytes(2), Some(vec![3; 10].as_slice()));
+    }
+
+    #[test]
+    fn an_entry_that_outgrows_its_run_moves_to_the_end() {
+        let mut region = get_region(&[10, 10]);
+        region.set_chunk_bytes(0, &vec![7; SECTOR_SIZE + 1]);
+
+        assert_eq!(region.entries[0].first_sector, HEADER_SECTORS as u32 + 2);
+        assert_eq!(region.data.len(), (HEADER_SECTORS + 4) * SECTOR_SIZE);
+        // The sector it left is the first one the next entry gets.
+        region.set_chunk_bytes(2, &[9]);
+        assert_eq!(region.entries[2].first_sector, HEADER_SECTORS as u32);
+    }
+
+    #[test]
+    fn a_shrinking_entry_keeps_its_sectors() {
+        let mut region = get_region(&[2 * SECTOR_SIZE, 10]);
+        region.set_chunk_bytes(0, &[7; 5]);
+
+        assert_eq!(region.entries[0], RegionEntry { first_sector: HEADER_SECTORS as u32, length: 5 });
+        assert_eq!(region.get_chunk_bytes(0), Some([7; 5].as_slice()));
+        assert_eq!(region.get_chunk_bytes(1), Some(vec![2; 10].as_slice()));
+    }
+
+    #[test]
+    fn from_bytes_rejects_a_corrupt_header() {
+        let mut bytes = get_bytes(&[RegionEntry { first_sector: HEADER_SECTORS as u32, length: 10 }], HEADER_SECTORS + 1);
+        bytes[12] ^= 1;
+
+        assert!(matches!(Region::from_bytes(bytes), Err(RegionError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn from_bytes_rejects_overlapping_entries() {
+        let entry = RegionEntry { first_sector: HEADER_SECTORS as u32, length: 10 };
+        let bytes = get_bytes(&[entry, entry], HEADER_SECTORS + 1);
+
+        assert_eq!(Region::from_bytes(bytes).err(), Some(RegionError::OverlappingEntries { index: 1, other_index: 0 }));
+    }
+
+    #[test]
+    fn from_bytes_rejects_entries_past_the_end_of_the_file() {
+        let entry = RegionEntry { first_sector: HEADER_SECTORS as u32, length: SECTOR_SIZE as u32 + 1 };
+        let bytes = get_bytes(&[entry], HEADER_SECTORS + 1);
+
+        assert_eq!(Region::from_bytes(bytes).err(), Some(RegionError::EntryOutOfBounds { index: 0, first_sector: entry.first_sector, length: entry.length }));
+    }
+
+    #[test]
+    fn save_writes_a_region_that_reads_back() {
+        let directory = std::env::temp_dir().join(format!("hello-wgpu-region-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = get_region_path(&directory, &WorldPosition::new(0, -1, 2));
+        let id_table = BlockIdTableFormat { format_version: 1, block_states: vec![String::from("AIR"), String::from("STONE")] };
+        let mut region = Region::new();
+        region.set_id_table(&id_table);
+        region.set_chunk_bytes(0, &[1; 10]);
+        region.set_chunk_bytes(1, &vec![2; 2 * SECTOR_SIZE]);
+        // Freeing the last entry leaves sectors at the end that the save drops.
+        region.set_chunk_bytes(1, &[]);
+        region.save(&path).unwrap();
+
+        let saved_region = Region::open(&path).unwrap();
+        assert_eq!(saved_region.get_chunk_bytes(0), Some(vec![1; 10].as_slice()));
+        assert_eq!(saved_region.get_chunk_bytes(1), None);
+        assert_eq!(saved_region.get_id_table().unwrap(), Some(id_table.clone()));
+        assert_eq!(fs::metadata(&path).unwrap().len() as usize, (HEADER_SECTORS + 2) * SECTOR_SIZE);
+        assert_eq!(Region::read_chunk_bytes(&path, 0).unwrap(), Some((vec![1; 10], id_table)));
+        assert_eq!(Region::read_chunk_bytes(&path, 1).unwrap(), None);
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1, "the temporary file should be renamed");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
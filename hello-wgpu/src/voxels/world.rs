@@ -1,19 +1,53 @@
-use std::{collections::HashMap, sync::{RwLock, Arc}};
+use std::{cmp::Reverse, collections::{HashMap, HashSet}, path::Path, sync::{RwLock, Arc}};
 use super::chunk::Chunk;
-use fundamentals::{world_position::WorldPosition, consts::CHUNK_GENERATION_METHOD};
+use super::chunk_encoding::ChunkCompression;
+use super::chunk_storage::ChunkStorage;
+use super::region::{self, Region, REGION_SIZE};
+use cgmath::{InnerSpace, Point3, Vector3};
+use derivables::block::Block;
+use formats::formats::block_id_table_format::BlockIdTableFormat;
+use formats::formats::block_replacement_format::BlockReplacementFormat;
+use fundamentals::{world_position::WorldPosition, runtime_config::RuntimeConfig};
+use fundamentals::block_registry;
+use fundamentals::consts::CHUNK_DIMENSION;
+use fundamentals::enums::block_side::BlockSide;
+use fundamentals::block_remapping::{self, BlockIdRemapper};
+use fundamentals::loge;
 
 pub struct World {
     chunks: HashMap<WorldPosition, Arc<RwLock<Chunk>>>,
+    // Chunks that changed since they were last saved.
+    dirty_chunks: HashSet<WorldPosition>,
+    // Chunks that are never unloaded, loaded yet or not.
+    pinned_chunks: HashSet<WorldPosition>,
+    // Chunks queued to be loaded or generated that haven't been added yet.
+    requested_chunks: HashSet<WorldPosition>,
+    pub runtime_config: Arc<RuntimeConfig>,
+    pub block_replacements: Arc<BlockReplacementFormat>,
+}
+
+/// The first block a ray hits.
+#[derive(Clone, Copy, Debug)]
+pub struct RaycastHit {
+    pub position: WorldPosition,
+    pub block: Block,
+    /// The face of the block the ray entered through.
+    pub side: BlockSide,
+    /// How far along the ray the face is.
+    pub distance: f32,
+    /// The empty block the ray was in before, where a block placed against the face goes.
+    pub previous_position: WorldPosition,
 }
 
 impl World {
-    pub fn new() -> Self {
-        World { chunks: HashMap::new() }
+    pub fn new(runtime_config: Arc<RuntimeConfig>) -> Self {
+        let block_replacements = Arc::new(block_remapping::load_block_replacements());
+        World { chunks: HashMap::new(), dirty_chunks: HashSet::new(), pinned_chunks: HashSet::new(), requested_chunks: HashSet::new(), runtime_config, block_replacements }
     }
 
-    pub fn generate_chunk_at(position: &WorldPosition) -> Chunk {
-        match CHUNK_GENERATION_METHOD {
-            "perlin" => Chunk::perlin(position),
+    pub fn generate_chunk_at(position: &WorldPosition, runtime_config: &RuntimeConfig) -> Chunk {
+        match runtime_config.chunk_generation_method.as_str() {
+            "perlin" => Chunk::perlin(position, &runtime_config.chunk_generation_options),
             "checkerboard" => Chunk::checkerboard(position),
             "solid" => Chunk::solid(position),
             "empty" => Chunk::empty(position),
@@ -21,14 +55,429 @@ impl World {
         }
     }
 
+    /// Reads the chunk at `position` from the regions in `directory`, with its blocks remapped to the
+    /// current registry. None if it was never saved.
+    pub fn load_chunk_at(position: &WorldPosition, directory: &Path, block_replacements: &BlockReplacementFormat) -> Result<Option<Chunk>, String> {
+        let region_path = region::get_region_path(directory, &region::get_region_position(position));
+        let (chunk_bytes, id_table) = match Region::read_chunk_bytes(&region_path, region::get_region_index(position)) {
+            Ok(Some(saved)) => saved,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(format!("{}: {}", region_path.display(), e)),
+        };
+        let remapper = get_remapper(&id_table, block_replacements).map_err(|e| format!("{}: {}", region_path.display(), e))?;
+        let chunk = decode_chunk(&chunk_bytes, &remapper).map_err(|e| format!("{}: chunk {}: {}", region_path.display(), position, e))?;
+        if chunk.position != *position {
+            return Err(format!("{}: chunk {} is stored where {} should be", region_path.display(), chunk.position, position));
+        }
+        Ok(Some(chunk))
+    }
+
     pub fn add_chunk(&mut self, chunk: Chunk) {
+        self.requested_chunks.remove(&chunk.position);
         self.chunks.insert(chunk.position, Arc::new(RwLock::new(chunk)));
     }
-    
+
+    /// Records that the chunk at `position` is about to be loaded. False if it is loaded or was
+    /// already requested, so it isn't queued twice.
+    pub fn request_chunk(&mut self, position: &WorldPosition) -> bool {
+        !self.chunks.contains_key(position) && self.requested_chunks.insert(*position)
+    }
+
     pub fn get_chunk_at(&self, pos: &WorldPosition) -> Option<Arc<RwLock<Chunk>>> {
         match self.chunks.get(pos) {
             Some(chunk) => Some(chunk.clone()),
             None => None
         }
     }
-}
\ No newline at end of file
+
+    /// The block at the world block position `position`, or None if its chunk isn't loaded.
+    pub fn get_block(&self, position: &WorldPosition) -> Option<Block> {
+        let (chunk_position, (x, y, z)) = get_chunk_and_local_position(position);
+        let chunk = self.chunks.get(&chunk_position)?;
+        let block = chunk.read().unwrap().get_block(x, y, z);
+        Some(block)
+    }
+
+    /// Steps through the blocks along the ray from `origin` block face by block face, as in Amanatides
+    /// and Woo's "A Fast Voxel Traversal Algorithm", and returns the first one that isn't AIR within
+    /// `max_distance`. The block `origin` is in is skipped. The ray stops at chunks that aren't loaded.
+    pub fn raycast(&self, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<RaycastHit> {
+        if direction.magnitude2() == 0.0 {
+            return None;
+        }
+        let direction: [f32; 3] = direction.normalize().into();
+        let origin: [f32; 3] = origin.into();
+
+        let mut position = origin.map(|value| value.floor() as i32);
+        let mut step = [0; 3];
+        // Distance along the ray to the next face on each axis, and between faces on each axis.
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                t_max[axis] = (position[axis] as f32 + 1.0 - origin[axis]) / direction[axis];
+                t_delta[axis] = 1.0 / direction[axis];
+            } else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                t_max[axis] = (origin[axis] - position[axis] as f32) / -direction[axis];
+                t_delta[axis] = 1.0 / -direction[axis];
+            }
+        }
+
+        loop {
+            let axis = if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] { 0 } else { 2 }
+            } else if t_max[1] < t_max[2] { 1 } else { 2 };
+            let distance = t_max[axis];
+            if distance > max_distance {
+                return None;
+            }
+
+            let previous_position = WorldPosition::new(position[0], position[1], position[2]);
+            position[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+
+            let world_position = WorldPosition::new(position[0], position[1], position[2]);
+            let block = self.get_block(&world_position)?;
+            if !block.is_air() {
+                return Some(RaycastHit { position: world_position, block, side: get_entered_side(axis, step[axis]), distance, previous_position });
+            }
+        }
+    }
+
+    /// Marks the chunk at `position` to be written by the next `save_dirty`.
+    pub fn mark_dirty(&mut self, position: &WorldPosition) {
+        self.dirty_chunks.insert(*position);
+    }
+
+    /// Keeps the chunk at `position` loaded however far the player goes, until it is unpinned.
+    pub fn pin_chunk(&mut self, position: &WorldPosition) {
+        self.pinned_chunks.insert(*position);
+    }
+
+    pub fn unpin_chunk(&mut self, position: &WorldPosition) {
+        self.pinned_chunks.remove(position);
+    }
+
+    pub fn is_chunk_pinned(&self, position: &WorldPosition) -> bool {
+        self.pinned_chunks.contains(position)
+    }
+
+    /// The memory the blocks of every loaded chunk take.
+    pub fn get_memory_usage(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.read().unwrap().get_memory_usage()).sum()
+    }
+
+    /// Unloads the chunks outside `unload_radius` of `player_chunk`, then the farthest ones outside the
+    /// render radius while the chunks take more than `chunk_memory_budget_mb`. Pinned chunks stay, and
+    /// dirty chunks are saved first; any that can't be saved, or have nowhere to be saved to, stay too.
+    /// The regions are written without holding the world lock. Returns the unloaded positions.
+    pub fn evict_chunks(world: &RwLock<World>, player_chunk: &WorldPosition) -> Vec<WorldPosition> {
+        let (positions_to_evict, chunks_to_save, world_directory, block_replacements) = {
+            let mut world = world.write().unwrap();
+            let positions_to_evict = world.get_chunks_to_evict(player_chunk);
+            let chunks_to_save = world.take_dirty_chunks(&positions_to_evict);
+            (positions_to_evict, chunks_to_save, world.runtime_config.world_directory.clone(), world.block_replacements.clone())
+        };
+        if let Some(world_directory) = world_directory.filter(|_| !chunks_to_save.is_empty()) {
+            if let Err(e) = Self::save_chunks(&world_directory, &chunks_to_save, &block_replacements) {
+                loge!("Keeping unsaved chunks loaded: {}", e);
+                let mut world = world.write().unwrap();
+                for (position, _) in &chunks_to_save {
+                    world.mark_dirty(position);
+                }
+            }
+        }
+        world.write().unwrap().unload_chunks(&positions_to_evict)
+    }
+
+    fn get_chunks_to_evict(&self, player_chunk: &WorldPosition) -> Vec<WorldPosition> {
+        let runtime_config = self.runtime_config.clone();
+        // Without a world directory a dirty chunk would be generated again without its edits.
+        let can_save = runtime_config.world_directory.is_some();
+        let mut candidates: Vec<(WorldPosition, usize)> = self.chunks.iter()
+            .filter(|(position, _)| !self.pinned_chunks.contains(position) && !runtime_config.is_in_render_radius(player_chunk, position))
+            .filter(|(position, _)| can_save || !self.dirty_chunks.contains(position))
+            .map(|(position, chunk)| (*position, chunk.read().unwrap().get_memory_usage()))
+            .collect();
+        candidates.sort_by_key(|(position, _)| Reverse(get_distance_squared(player_chunk, position)));
+
+        let memory_budget = runtime_config.chunk_memory_budget_mb as usize * 1024 * 1024;
+        let mut memory_usage = self.get_memory_usage();
+        let mut positions_to_evict = Vec::new();
+        for (position, chunk_memory_usage) in candidates {
+            if runtime_config.is_in_unload_radius(player_chunk, &position) && memory_usage <= memory_budget {
+                continue;
+            }
+            memory_usage -= chunk_memory_usage;
+            positions_to_evict.push(position);
+        }
+        positions_to_evict
+    }
+
+    // Clears the dirty mark of the loaded chunks at `positions` that have one and returns them to be
+    // saved. A chunk edited while it is saved is marked dirty again.
+    fn take_dirty_chunks(&mut self, positions: &[WorldPosition]) -> Vec<(WorldPosition, Arc<RwLock<Chunk>>)> {
+        let mut dirty_chunks = Vec::new();
+        for position in positions {
+            if let Some(chunk) = self.chunks.get(position).filter(|_| self.dirty_chunks.remove(position)) {
+                dirty_chunks.push((*position, chunk.clone()));
+            }
+        }
+        dirty_chunks
+    }
+
+    // Removes the chunks at `positions` that are loaded and have nothing left to save.
+    fn unload_chunks(&mut self, positions: &[WorldPosition]) -> Vec<WorldPosition> {
+        let mut unloaded_positions = Vec::new();
+        for position in positions {
+            if !self.dirty_chunks.contains(position) && self.chunks.remove(position).is_some() {
+                unloaded_positions.push(*position);
+            }
+        }
+        unloaded_positions
+    }
+
+    /// Writes every dirty chunk to its region in `directory` and returns how many were saved. The
+    /// regions are written without holding the world lock.
+    pub fn save_dirty(world: &RwLock<World>, directory: &Path) -> Result<usize, String> {
+        let (chunks_to_save, block_replacements) = {
+            let mut world = world.write().unwrap();
+            let dirty_positions: Vec<WorldPosition> = world.dirty_chunks.iter().copied().collect();
+            (world.take_dirty_chunks(&dirty_positions), world.block_replacements.clone())
+        };
+        Self::save_chunks(directory, &chunks_to_save, &block_replacements).inspect_err(|_| {
+            let mut world = world.write().unwrap();
+            for (position, _) in &chunks_to_save {
+                world.mark_dirty(position);
+            }
+        })
+    }
+
+    // A region last written with another block ID table has its other chunks remapped to the current
+    // one, as a region only keeps one table.
+    fn save_chunks(directory: &Path, chunks: &[(WorldPosition, Arc<RwLock<Chunk>>)], block_replacements: &BlockReplacementFormat) -> Result<usize, String> {
+        let mut chunks_by_region: HashMap<WorldPosition, Vec<&(WorldPosition, Arc<RwLock<Chunk>>)>> = HashMap::new();
+        for chunk in chunks {
+            chunks_by_region.entry(region::get_region_position(&chunk.0)).or_default().push(chunk);
+        }
+        if chunks_by_region.is_empty() {
+            return Ok(0);
+        }
+        std::fs::create_dir_all(directory).map_err(|e| format!("{}: {}", directory.display(), e))?;
+
+        let id_table = block_registry::get().get_id_table();
+        let mut num_saved = 0;
+        for (region_position, region_chunks) in chunks_by_region {
+            let region_path = region::get_region_path(directory, &region_position);
+            let mut region = Region::open(&region_path).map_err(|e| format!("{}: {}", region_path.display(), e))?;
+            let saved_id_table = region.get_id_table().map_err(|e| format!("{}: {}", region_path.display(), e))?;
+            if let Some(saved_id_table) = saved_id_table.filter(|saved_id_table| saved_id_table.block_states != id_table.block_states) {
+                let remapper = get_remapper(&saved_id_table, block_replacements).map_err(|e| format!("{}: {}", region_path.display(), e))?;
+                for index in 0..REGION_SIZE {
+                    let Some(chunk_bytes) = region.get_chunk_bytes(index) else { continue };
+                    let chunk = decode_chunk(chunk_bytes, &remapper).map_err(|e| format!("{}: entry {}: {}", region_path.display(), index, e))?;
+                    region.set_chunk_bytes(index, &chunk.to_bytes(ChunkCompression::Lz4));
+                }
+            }
+
+            for (position, chunk) in &region_chunks {
+                let chunk_bytes = chunk.read().unwrap().to_bytes(ChunkCompression::Lz4);
+                region.set_chunk_bytes(region::get_region_index(position), &chunk_bytes);
+            }
+            region.set_id_table(&id_table);
+            region.save(&region_path).map_err(|e| e.to_string())?;
+            num_saved += region_chunks.len();
+        }
+        Ok(num_saved)
+    }
+}
+
+/// The chunk holding the world block position `position` and the position within that chunk.
+pub fn get_chunk_and_local_position(position: &WorldPosition) -> (WorldPosition, (usize, usize, usize)) {
+    let chunk_position = WorldPosition::new(position.x.div_euclid(CHUNK_DIMENSION), position.y.div_euclid(CHUNK_DIMENSION), position.z.div_euclid(CHUNK_DIMENSION));
+    let local_position = (position.x.rem_euclid(CHUNK_DIMENSION) as usize, position.y.rem_euclid(CHUNK_DIMENSION) as usize, position.z.rem_euclid(CHUNK_DIMENSION) as usize);
+    (chunk_position, local_position)
+}
+
+// Moving towards +x enters a block through its -x face, and so on.
+fn get_entered_side(axis: usize, step: i32) -> BlockSide {
+    match (axis, step > 0) {
+        (0, true) => BlockSide::FRONT,
+        (0, false) => BlockSide::BACK,
+        (1, true) => BlockSide::BOTTOM,
+        (1, false) => BlockSide::TOP,
+        (_, true) => BlockSide::LEFT,
+        (_, false) => BlockSide::RIGHT,
+    }
+}
+
+fn get_distance_squared(from: &WorldPosition, to: &WorldPosition) -> i64 {
+    let (x, y, z) = ((to.x - from.x) as i64, (to.y - from.y) as i64, (to.z - from.z) as i64);
+    x * x + y * y + z * z
+}
+
+fn get_remapper(id_table: &BlockIdTableFormat, block_replacements: &BlockReplacementFormat) -> Result<BlockIdRemapper, String> {
+    BlockIdRemapper::new(id_table, block_registry::get(), block_replacements).map_err(|errors| errors.join(", "))
+}
+
+fn decode_chunk(chunk_bytes: &[u8], remapper: &BlockIdRemapper) -> Result<Chunk, String> {
+    let mut chunk = Chunk::from_bytes(chunk_bytes).map_err(|e| e.to_string())?;
+    chunk.remap_blocks(remapper)?;
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_world(world_directory: Option<std::path::PathBuf>) -> RwLock<World> {
+        let runtime_config = RuntimeConfig { render_radius: 1, unload_radius: 2, world_directory, ..RuntimeConfig::load() };
+        RwLock::new(World::new(Arc::new(runtime_config)))
+    }
+
+    fn add_far_chunks(world: &RwLock<World>) -> (WorldPosition, WorldPosition) {
+        let (dirty_position, clean_position) = (WorldPosition::new(10, 0, 0), WorldPosition::new(11, 0, 0));
+        let mut world = world.write().unwrap();
+        world.add_chunk(Chunk::solid(&WorldPosition::new(0, 0, 0)));
+        world.add_chunk(Chunk::solid(&dirty_position));
+        world.add_chunk(Chunk::solid(&clean_position));
+        world.mark_dirty(&dirty_position);
+        (dirty_position, clean_position)
+    }
+
+    #[test]
+    fn evict_chunks_keeps_dirty_chunks_without_world_directory() {
+        let world = get_test_world(None);
+        let (dirty_position, clean_position) = add_far_chunks(&world);
+
+        let unloaded_positions = World::evict_chunks(&world, &WorldPosition::new(0, 0, 0));
+
+        assert_eq!(unloaded_positions, vec![clean_position]);
+        let world = world.read().unwrap();
+        assert!(world.get_chunk_at(&dirty_position).is_some());
+        assert!(world.get_chunk_at(&WorldPosition::new(0, 0, 0)).is_some());
+    }
+
+    #[test]
+    fn evict_chunks_saves_dirty_chunks_before_unloading() {
+        let world_directory = std::env::temp_dir().join(format!("hello-wgpu-evict-{}", std::process::id()));
+        let world = get_test_world(Some(world_directory.clone()));
+        let (dirty_position, clean_position) = add_far_chunks(&world);
+
+        let mut unloaded_positions = World::evict_chunks(&world, &WorldPosition::new(0, 0, 0));
+        unloaded_positions.sort_by_key(|position| position.x);
+
+        assert_eq!(unloaded_positions, vec![dirty_position, clean_position]);
+        let block_replacements = world.read().unwrap().block_replacements.clone();
+        let saved_chunk = World::load_chunk_at(&dirty_position, &world_directory, &block_replacements).unwrap();
+        assert_eq!(saved_chunk.and_then(|chunk| chunk.get_uniform_block()).map(|block| block.block_state), Chunk::solid(&dirty_position).get_uniform_block().map(|block| block.block_state));
+        assert!(World::load_chunk_at(&clean_position, &world_directory, &block_replacements).unwrap().is_none());
+        std::fs::remove_dir_all(&world_directory).unwrap();
+    }
+
+    // Empty chunks from -2 to 1 on every axis, with a solid block at each of `block_positions`.
+    fn get_raycast_world(block_positions: &[WorldPosition]) -> World {
+        let mut world = get_test_world(None).into_inner().unwrap();
+        for x in -2..2 {
+            for y in -2..2 {
+                for z in -2..2 {
+                    world.add_chunk(Chunk::empty(&WorldPosition::new(x, y, z)));
+                }
+            }
+        }
+        for position in block_positions {
+            let (chunk_position, (x, y, z)) = get_chunk_and_local_position(position);
+            world.get_chunk_at(&chunk_position).unwrap().write().unwrap().set_block(x, y, z, Block::from_state(1));
+        }
+        world
+    }
+
+    fn assert_hit(hit: Option<RaycastHit>, position: WorldPosition, side: BlockSide, distance: f32, previous_position: WorldPosition) {
+        let hit = hit.expect("the ray should hit a block");
+        assert_eq!(hit.position, position);
+        assert_eq!(hit.side, side);
+        assert!((hit.distance - distance).abs() < 1e-4, "distance {} instead of {}", hit.distance, distance);
+        assert_eq!(hit.previous_position, previous_position);
+    }
+
+    #[test]
+    fn get_chunk_and_local_position_floors_negative_positions() {
+        let last = CHUNK_DIMENSION as usize - 1;
+        let cases = [
+            (WorldPosition::new(0, 0, 0), WorldPosition::new(0, 0, 0), (0, 0, 0)),
+            (WorldPosition::new(-1, -1, -1), WorldPosition::new(-1, -1, -1), (last, last, last)),
+            (WorldPosition::new(-CHUNK_DIMENSION, CHUNK_DIMENSION - 1, CHUNK_DIMENSION), WorldPosition::new(-1, 0, 1), (0, last, 0)),
+            (WorldPosition::new(-CHUNK_DIMENSION - 1, 5, -CHUNK_DIMENSION * 3 + 2), WorldPosition::new(-2, 0, -3), (last, 5, 2)),
+        ];
+        for (position, chunk_position, local_position) in cases {
+            assert_eq!(get_chunk_and_local_position(&position), (chunk_position, local_position), "for {}", position);
+        }
+    }
+
+    #[test]
+    fn raycast_crosses_chunks_towards_negative_coordinates() {
+        let block_position = WorldPosition::new(-CHUNK_DIMENSION - 3, 0, 0);
+        let world = get_raycast_world(&[block_position]);
+
+        let hit = world.raycast(Point3::new(0.5, 0.5, 0.5), Vector3::new(-1.0, 0.0, 0.0), 100.0);
+
+        assert_hit(hit, block_position, BlockSide::BACK, CHUNK_DIMENSION as f32 + 2.5, WorldPosition::new(-CHUNK_DIMENSION - 2, 0, 0));
+    }
+
+    #[test]
+    fn raycast_along_each_axis_enters_through_the_facing_side() {
+        let d = CHUNK_DIMENSION;
+        let cases = [
+            (Vector3::new(1.0, 0.0, 0.0), WorldPosition::new(d + 1, 0, 0), BlockSide::FRONT, d as f32 + 0.5, WorldPosition::new(d, 0, 0)),
+            (Vector3::new(0.0, 1.0, 0.0), WorldPosition::new(0, 5, 0), BlockSide::BOTTOM, 4.8, WorldPosition::new(0, 4, 0)),
+            (Vector3::new(0.0, -1.0, 0.0), WorldPosition::new(0, -d, 0), BlockSide::TOP, d as f32 - 0.8, WorldPosition::new(0, -d + 1, 0)),
+            (Vector3::new(0.0, 0.0, 1.0), WorldPosition::new(0, 0, d), BlockSide::LEFT, d as f32 - 0.5, WorldPosition::new(0, 0, d - 1)),
+            (Vector3::new(0.0, 0.0, -1.0), WorldPosition::new(0, 0, -2), BlockSide::RIGHT, 1.5, WorldPosition::new(0, 0, -1)),
+        ];
+        for (direction, position, side, distance, previous_position) in cases {
+            // Only the block in front of the ray exists, so hitting it means the other axes never stepped.
+            let world = get_raycast_world(&[position]);
+            assert_hit(world.raycast(Point3::new(0.5, 0.2, 0.5), direction, 100.0), position, side, distance, previous_position);
+        }
+    }
+
+    #[test]
+    fn raycast_steps_the_axis_with_the_nearest_face() {
+        let d = CHUNK_DIMENSION;
+        // Across the chunk boundary on x while z stays in the first block.
+        let world = get_raycast_world(&[WorldPosition::new(d + 1, 0, 0), WorldPosition::new(d, 0, 1)]);
+        let direction = Vector3::new(1.0, 0.0, 0.25);
+        let hit = world.raycast(Point3::new(d as f32 - 0.5, 0.5, 0.5), direction, 100.0);
+        assert_hit(hit, WorldPosition::new(d + 1, 0, 0), BlockSide::FRONT, 1.5 * direction.magnitude(), WorldPosition::new(d, 0, 0));
+
+        // The z face is nearer than the x face, so the ray enters the block next to it on z.
+        let world = get_raycast_world(&[WorldPosition::new(0, 0, 1), WorldPosition::new(1, 0, 0)]);
+        let direction = Vector3::new(1.0, 0.0, 2.0);
+        let hit = world.raycast(Point3::new(0.5, 0.5, 0.5), direction, 100.0);
+        assert_hit(hit, WorldPosition::new(0, 0, 1), BlockSide::LEFT, 0.25 * direction.magnitude(), WorldPosition::new(0, 0, 0));
+    }
+
+    #[test]
+    fn raycast_stops_at_max_distance() {
+        let block_position = WorldPosition::new(0, 0, 5);
+        let world = get_raycast_world(&[block_position]);
+        let (origin, direction) = (Point3::new(0.5, 0.5, 0.5), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(world.raycast(origin, direction, 4.4).is_none());
+        assert_hit(world.raycast(origin, direction, 4.5), block_position, BlockSide::LEFT, 4.5, WorldPosition::new(0, 0, 4));
+        // Nothing to hit within reach.
+        assert!(world.raycast(origin, Vector3::new(0.0, 1.0, 0.0), 10.0).is_none());
+    }
+
+    #[test]
+    fn raycast_skips_the_origin_block_and_stops_at_unloaded_chunks() {
+        let world = get_raycast_world(&[WorldPosition::new(0, 0, 0), WorldPosition::new(0, 0, 2)]);
+        let origin = Point3::new(0.5, 0.5, 0.5);
+
+        assert_hit(world.raycast(origin, Vector3::new(0.0, 0.0, 1.0), 10.0), WorldPosition::new(0, 0, 2), BlockSide::LEFT, 1.5, WorldPosition::new(0, 0, 1));
+        assert!(world.raycast(origin, Vector3::new(1.0, 0.0, 0.0), 1000.0).is_none());
+        assert!(world.raycast(origin, Vector3::new(0.0, 0.0, 0.0), 10.0).is_none());
+    }
+}
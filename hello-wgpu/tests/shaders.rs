@@ -0,0 +1,25 @@
+// The chunk render pipeline isn't created yet, so nothing else checks the generated chunk shader.
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+fn validate(path: &str) -> naga::Module {
+    let source = std::fs::read_to_string(format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path)).unwrap();
+    let module = naga::front::wgsl::parse_str(&source).unwrap_or_else(|e| panic!("{}: {}", path, e.emit_to_string(&source)));
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{}: {}", path, e.emit_to_string(&source)));
+    module
+}
+
+#[test]
+fn chunk_shader_is_valid() {
+    let module = validate("src/shader.wgsl");
+    for entry_point in ["vs_main", "fs_main"] {
+        assert!(module.entry_points.iter().any(|point| point.name == entry_point), "missing {}", entry_point);
+    }
+}
+
+#[test]
+fn subvoxel_shaders_are_valid() {
+    validate("src/gpu_manager/shaders/subvoxels/subvoxel_shader.wgsl");
+    validate("src/gpu_manager/shaders/subvoxels/grid_aligned_subvoxel_shader.wgsl");
+}
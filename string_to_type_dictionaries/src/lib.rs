@@ -1,2 +0,0 @@
-pub mod string_to_block_type;
-pub mod string_to_texture_indices;